@@ -0,0 +1,399 @@
+use kiss3d::conrod::{widget, widget_ids, Borderable, Colorable, Labelable, Positionable, Sizeable, UiCell, Widget};
+use kiss3d::event::{Action, Key, WindowEvent};
+use kiss3d::nalgebra::{Point2, Point3, Vector2};
+use kiss3d::window::Window;
+use serde::{Deserialize, Serialize};
+
+use crate::scene_file::{Color, Errors};
+
+// Snapping is off while Alt is held
+pub const NO_SNAP_KEYS: [Key; 2] = [Key::LAlt, Key::RAlt];
+
+// Grid lines closer than that are not drawn, there would be more lines than pixels between them
+const MIN_DRAWN_STEP: f32 = 4.0;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridUnits {
+    // Like the control points, -1 to 1 across the window, so cells are as stretched as the window
+    Normalized,
+    Pixels,
+}
+
+impl GridUnits {
+    pub const ALL: [GridUnits; 2] = [GridUnits::Normalized, GridUnits::Pixels];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GridUnits::Normalized => "Normalized",
+            GridUnits::Pixels => "Pixels",
+        }
+    }
+
+    // Spacing the dialer allows, and the precision it shows
+    fn spacing_range(&self) -> (f32, f32, u8) {
+        match self {
+            GridUnits::Normalized => (0.01, 1.0, 2),
+            GridUnits::Pixels => (5.0, 400.0, 0),
+        }
+    }
+
+    fn default_spacing(&self) -> f32 {
+        match self {
+            GridUnits::Normalized => 0.1,
+            GridUnits::Pixels => 50.0,
+        }
+    }
+}
+
+// Background grid, its lines going through the axes origin
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridSettings {
+    pub is_shown: bool,
+    pub spacing: f32,
+    pub units: GridUnits,
+    pub color: Color,
+    pub snapping: Snapping,
+}
+
+// What dragged points snap to, each coordinate on its own
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Snapping {
+    // Only while the grid is shown
+    pub to_grid: bool,
+    pub to_axes: bool,
+    pub to_points: bool,
+    // Pixels under which a coordinate snaps
+    pub distance: f32,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        GridSettings {
+            is_shown: false,
+            spacing: GridUnits::Normalized.default_spacing(),
+            units: GridUnits::Normalized,
+            color: [0.85, 0.85, 0.85],
+            snapping: Snapping::default(),
+        }
+    }
+}
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Snapping { to_grid: true, to_axes: true, to_points: true, distance: 8.0 }
+    }
+}
+
+impl GridSettings {
+    pub fn validate(&self, errors: &mut Errors) {
+        let (min, max, _) = self.units.spacing_range();
+        errors.check("grid.spacing", (min..=max).contains(&self.spacing), &format!("must be between {} and {} for these units", min, max));
+        errors.color("grid.color", &self.color);
+        errors.check("grid.snapping.distance", self.snapping.distance >= 0.0, "must not be negative");
+    }
+}
+
+// Grid of the current frame, in pixels from the window center
+pub struct Grid<'a> {
+    settings: &'a GridSettings,
+    origin: Point2<f32>,
+    step: Vector2<f32>,
+    half_size: Vector2<f32>,
+    // Axes drawn whether the grid is or not
+    has_axes: bool,
+}
+
+impl<'a> Grid<'a> {
+    pub fn new(settings: &'a GridSettings, origin: Point2<f32>, window_width: u32, window_height: u32, has_axes: bool) -> Self {
+        let half_size = Vector2::new(window_width as f32 / 2.0, window_height as f32 / 2.0);
+        let step = match settings.units {
+            GridUnits::Normalized => half_size * settings.spacing,
+            GridUnits::Pixels => Vector2::new(settings.spacing, settings.spacing),
+        };
+        Grid { settings, origin, step, half_size, has_axes }
+    }
+
+    pub fn settings(&self) -> &'a GridSettings {
+        self.settings
+    }
+
+    pub fn draw(&self, window: &mut Window) {
+        if !self.settings.is_shown || self.step.x < MIN_DRAWN_STEP || self.step.y < MIN_DRAWN_STEP {
+            return;
+        }
+
+        // The line width is the lab's, kiss3d has one for all the lines of a frame
+        let color = Point3::from(self.settings.color);
+        for x in lines(self.origin.x, self.step.x, self.half_size.x) {
+            window.draw_planar_line(&Point2::new(x, -self.half_size.y), &Point2::new(x, self.half_size.y), &color);
+        }
+        for y in lines(self.origin.y, self.step.y, self.half_size.y) {
+            window.draw_planar_line(&Point2::new(-self.half_size.x, y), &Point2::new(self.half_size.x, y), &color);
+        }
+    }
+
+    // Axes across the whole window, for the labs without axes of their own
+    pub fn draw_axes(&self, window: &mut Window, color: &Point3<f32>) {
+        if !self.has_axes {
+            return;
+        }
+
+        window.draw_planar_line(&Point2::new(-self.half_size.x, self.origin.y), &Point2::new(self.half_size.x, self.origin.y), color);
+        window.draw_planar_line(&Point2::new(self.origin.x, -self.half_size.y), &Point2::new(self.origin.x, self.half_size.y), color);
+    }
+
+    // Each coordinate goes to the closest grid line, axis or coordinate of the other points within the snapping distance
+    pub fn snap(&self, point: Point2<f32>, others: &[Point2<f32>]) -> Point2<f32> {
+        let snapping = &self.settings.snapping;
+        let snap = |value: f32, origin: f32, step: f32, others: &mut dyn Iterator<Item = f32>| {
+            let mut candidates = vec![];
+            if snapping.to_grid && self.settings.is_shown {
+                candidates.push(origin + ((value - origin) / step).round() * step);
+            }
+            if snapping.to_axes && self.has_axes {
+                candidates.push(origin);
+            }
+            if snapping.to_points {
+                candidates.extend(others);
+            }
+
+            candidates
+                .into_iter()
+                .filter(|candidate| (candidate - value).abs() <= snapping.distance)
+                .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+                .unwrap_or(value)
+        };
+
+        Point2::new(
+            snap(point.x, self.origin.x, self.step.x, &mut others.iter().map(|other| other.x)),
+            snap(point.y, self.origin.y, self.step.y, &mut others.iter().map(|other| other.y)),
+        )
+    }
+}
+
+// Coordinates of the grid lines within half a window of the center
+fn lines(origin: f32, step: f32, half_size: f32) -> impl Iterator<Item = f32> {
+    let first = ((-half_size - origin) / step).ceil() as i32;
+    let last = ((half_size - origin) / step).floor() as i32;
+    (first..=last).map(move |i| origin + i as f32 * step)
+}
+
+// Follows the keys that turn snapping off while held
+#[derive(Default)]
+pub struct SnapKey {
+    is_held: bool,
+}
+
+impl SnapKey {
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::Key(key, action, _) = *event {
+            if NO_SNAP_KEYS.contains(&key) {
+                self.is_held = action == Action::Press;
+            }
+        }
+    }
+
+    pub fn is_snapping(&self) -> bool {
+        !self.is_held
+    }
+}
+
+widget_ids! {
+    pub struct GridIds {
+        canvas,
+        grid_toggle,
+        spacing_dialer,
+        units_list,
+        grid_snap_toggle,
+        axes_snap_toggle,
+        points_snap_toggle,
+        hint_text,
+    }
+}
+
+// Panel in the bottom right corner
+pub fn proceed_grid_ui(ui_cell: &mut UiCell, ids: &GridIds, settings: &mut GridSettings) {
+    let margin = 20.0;
+    let widget_w = 120.0;
+    let widget_h = 30.0;
+
+    widget::Canvas::new()
+        .w_h(2.0 * widget_w + 3.0 * margin, 4.0 * widget_h + 5.5 * margin)
+        .rgb(1.0, 1.0, 1.0)
+        .border_rgb(1.0, 1.0, 1.0)
+        .bottom_right()
+        .set(ids.canvas, ui_cell);
+
+    if let Some(value) = widget::Toggle::new(settings.is_shown)
+        .w_h(widget_w, widget_h)
+        .top_left_with_margin_on(ids.canvas, margin)
+        .label("Grid")
+        .set(ids.grid_toggle, ui_cell)
+        .last()
+    {
+        settings.is_shown = value;
+    }
+
+    let labels: Vec<&str> = GridUnits::ALL.iter().map(|u| u.label()).collect();
+    let selected = GridUnits::ALL.iter().position(|u| *u == settings.units);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(widget_w, widget_h)
+        .right_from(ids.grid_toggle, margin)
+        .set(ids.units_list, ui_cell)
+    {
+        // The spacing of some units means nothing in the others
+        if settings.units != GridUnits::ALL[index] {
+            settings.units = GridUnits::ALL[index];
+            settings.spacing = settings.units.default_spacing();
+        }
+    }
+
+    let (min, max, precision) = settings.units.spacing_range();
+    if let Some(value) = widget::NumberDialer::new(settings.spacing, min, max, precision)
+        .w_h(widget_w, widget_h)
+        .down_from(ids.grid_toggle, margin / 2.0)
+        .label("Spacing")
+        .set(ids.spacing_dialer, ui_cell)
+    {
+        settings.spacing = value;
+    }
+
+    let snapping = &mut settings.snapping;
+    if let Some(value) = widget::Toggle::new(snapping.to_grid)
+        .w_h(widget_w, widget_h)
+        .down_from(ids.spacing_dialer, margin / 2.0)
+        .label("Snap to grid")
+        .set(ids.grid_snap_toggle, ui_cell)
+        .last()
+    {
+        snapping.to_grid = value;
+    }
+
+    if let Some(value) = widget::Toggle::new(snapping.to_axes)
+        .w_h(widget_w, widget_h)
+        .right_from(ids.grid_snap_toggle, margin)
+        .label("Snap to axes")
+        .set(ids.axes_snap_toggle, ui_cell)
+        .last()
+    {
+        snapping.to_axes = value;
+    }
+
+    if let Some(value) = widget::Toggle::new(snapping.to_points)
+        .w_h(widget_w, widget_h)
+        .down_from(ids.grid_snap_toggle, margin / 2.0)
+        .label("Snap to points")
+        .set(ids.points_snap_toggle, ui_cell)
+        .last()
+    {
+        snapping.to_points = value;
+    }
+
+    widget::Text::new("Hold Alt to drag without snapping")
+        .font_size(11)
+        .down_from(ids.points_snap_toggle, margin / 2.0)
+        .set(ids.hint_text, ui_cell);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(is_shown: bool, snapping: Snapping) -> GridSettings {
+        GridSettings { is_shown, spacing: 50.0, units: GridUnits::Pixels, snapping, ..GridSettings::default() }
+    }
+
+    fn only(to_grid: bool, to_axes: bool, to_points: bool) -> Snapping {
+        Snapping { to_grid, to_axes, to_points, distance: 8.0 }
+    }
+
+    #[test]
+    fn coordinates_snap_to_the_nearest_grid_line_on_their_own() {
+        let settings = settings(true, only(true, false, false));
+        let grid = Grid::new(&settings, Point2::new(10.0, -20.0), 400, 400, false);
+
+        assert_eq!(grid.snap(Point2::new(58.0, -64.0), &[]), Point2::new(60.0, -70.0));
+        assert_eq!(grid.snap(Point2::new(58.0, -45.0), &[]), Point2::new(60.0, -45.0));
+    }
+
+    #[test]
+    fn the_grid_snaps_only_while_shown() {
+        let settings = settings(false, only(true, false, false));
+        let grid = Grid::new(&settings, Point2::new(10.0, -20.0), 400, 400, false);
+
+        assert_eq!(grid.snap(Point2::new(58.0, -64.0), &[]), Point2::new(58.0, -64.0));
+    }
+
+    #[test]
+    fn normalized_spacing_follows_the_window_size() {
+        let settings = GridSettings { is_shown: true, spacing: 0.1, units: GridUnits::Normalized, ..GridSettings::default() };
+        let grid = Grid::new(&settings, Point2::origin(), 800, 600, false);
+
+        assert_eq!(grid.snap(Point2::new(83.0, 58.0), &[]), Point2::new(80.0, 60.0));
+    }
+
+    #[test]
+    fn coordinates_snap_to_the_axes_the_lab_has() {
+        let settings = settings(false, only(false, true, false));
+        let point = Point2::new(14.0, 100.0);
+
+        let with_axes = Grid::new(&settings, Point2::new(10.0, -20.0), 400, 400, true);
+        assert_eq!(with_axes.snap(point, &[]), Point2::new(10.0, 100.0));
+
+        let without_axes = Grid::new(&settings, Point2::new(10.0, -20.0), 400, 400, false);
+        assert_eq!(without_axes.snap(point, &[]), point);
+    }
+
+    #[test]
+    fn coordinates_snap_to_the_closest_other_point() {
+        let settings = settings(false, only(false, false, true));
+        let grid = Grid::new(&settings, Point2::origin(), 400, 400, false);
+        let others = [Point2::new(104.0, 300.0), Point2::new(97.0, 150.0)];
+
+        assert_eq!(grid.snap(Point2::new(100.0, 100.0), &others), Point2::new(97.0, 100.0));
+        assert_eq!(grid.snap(Point2::new(100.0, 145.0), &others), Point2::new(97.0, 150.0));
+    }
+
+    #[test]
+    fn the_closest_candidate_wins_across_kinds() {
+        let settings = settings(true, only(true, true, true));
+        let grid = Grid::new(&settings, Point2::origin(), 400, 400, true);
+
+        // The grid line at 50 is 4 away, the other point 1 away
+        assert_eq!(grid.snap(Point2::new(46.0, 3.0), &[Point2::new(47.0, 200.0)]), Point2::new(47.0, 0.0));
+    }
+
+    #[test]
+    fn nothing_snaps_beyond_the_distance() {
+        let settings = settings(true, only(true, false, false));
+        let grid = Grid::new(&settings, Point2::origin(), 400, 400, false);
+
+        assert_eq!(grid.snap(Point2::new(42.0, 58.0), &[]), Point2::new(50.0, 50.0));
+        assert_eq!(grid.snap(Point2::new(41.5, 58.5), &[]), Point2::new(41.5, 58.5));
+
+        let off = GridSettings { snapping: Snapping { distance: 0.0, ..only(true, true, true) }, ..settings };
+        let grid = Grid::new(&off, Point2::origin(), 400, 400, true);
+        assert_eq!(grid.snap(Point2::new(42.0, 58.0), &[]), Point2::new(42.0, 58.0));
+    }
+
+    #[test]
+    fn lines_cover_the_window_around_an_offset_origin() {
+        assert_eq!(lines(10.0, 50.0, 100.0).collect::<Vec<f32>>(), [-90.0, -40.0, 10.0, 60.0]);
+        assert_eq!(lines(-10.0, 50.0, 100.0).collect::<Vec<f32>>(), [-60.0, -10.0, 40.0, 90.0]);
+    }
+
+    #[test]
+    fn lines_on_the_window_border_are_kept() {
+        assert_eq!(lines(0.0, 50.0, 100.0).collect::<Vec<f32>>(), [-100.0, -50.0, 0.0, 50.0, 100.0]);
+    }
+
+    #[test]
+    fn lines_of_an_origin_outside_the_window_still_cover_it() {
+        assert_eq!(lines(250.0, 100.0, 100.0).collect::<Vec<f32>>(), [-50.0, 50.0]);
+        assert_eq!(lines(-1000.0, 300.0, 100.0).collect::<Vec<f32>>(), [-100.0]);
+    }
+}
@@ -0,0 +1,247 @@
+use kiss3d::event::{Action, Key, Modifiers, MouseButton, WindowEvent};
+
+// Ctrl+Z undoes the last edit, Ctrl+Shift+Z redoes it
+pub const UNDO_KEY: Key = Key::Z;
+
+// Oldest edits are forgotten past that many
+const HISTORY_LIMIT: usize = 100;
+
+// Edit of a lab that can be undone, recorded once it happened
+pub trait Command {
+    type Target;
+
+    fn apply(&self, target: &mut Self::Target);
+    fn revert(&self, target: &mut Self::Target);
+    fn label(&self) -> String;
+    // Takes the end of the next edit when both are steps of one continuous edit, like a drag
+    fn merge(&mut self, next: &Self) -> bool;
+}
+
+pub enum HistoryAction {
+    Undo,
+    Redo,
+}
+
+// Done edits, the last one on top, and the undone ones that can be redone until something else is edited.
+// Edits recorded while the left mouse button is held are merged whenever the commands allow it.
+pub struct History<C: Command> {
+    done: Vec<C>,
+    undone: Vec<C>,
+    is_mouse_pressed: bool,
+    // The last done edit was recorded during the current press
+    is_coalescing: bool,
+}
+
+impl<C: Command> Default for History<C> {
+    fn default() -> Self {
+        History { done: vec![], undone: vec![], is_mouse_pressed: false, is_coalescing: false }
+    }
+}
+
+impl<C: Command> History<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, command: C) {
+        self.undone.clear();
+        if self.is_coalescing {
+            if let Some(last) = self.done.last_mut() {
+                if last.merge(&command) {
+                    return;
+                }
+            }
+        }
+
+        self.done.push(command);
+        if self.done.len() > HISTORY_LIMIT {
+            self.done.remove(0);
+        }
+        self.is_coalescing = self.is_mouse_pressed;
+    }
+
+    pub fn undo(&mut self, target: &mut C::Target) -> bool {
+        self.is_coalescing = false;
+        let Some(command) = self.done.pop() else { return false };
+        command.revert(target);
+        self.undone.push(command);
+        true
+    }
+
+    pub fn redo(&mut self, target: &mut C::Target) -> bool {
+        self.is_coalescing = false;
+        let Some(command) = self.undone.pop() else { return false };
+        command.apply(target);
+        self.done.push(command);
+        true
+    }
+
+    pub fn perform(&mut self, action: HistoryAction, target: &mut C::Target) -> bool {
+        match action {
+            HistoryAction::Undo => self.undo(target),
+            HistoryAction::Redo => self.redo(target),
+        }
+    }
+
+    // Follows the left button for continuous edits, returns the undo or redo asked for
+    pub fn handle_event(&mut self, event: &WindowEvent) -> Option<HistoryAction> {
+        match *event {
+            WindowEvent::MouseButton(MouseButton::Button1, action, _) => {
+                self.is_mouse_pressed = action == Action::Press;
+                self.is_coalescing = false;
+                None
+            },
+            WindowEvent::Key(key, Action::Press, modifiers) if key == UNDO_KEY && modifiers.contains(Modifiers::Control) => {
+                Some(if modifiers.contains(Modifiers::Shift) { HistoryAction::Redo } else { HistoryAction::Undo })
+            },
+            _ => None,
+        }
+    }
+
+    // The last `count` edits, oldest first: the last done one marked, the undone ones after it in brackets
+    pub fn text(&self, count: usize) -> String {
+        let mut lines: Vec<String> = self.done.iter().map(|command| format!("  {}", command.label())).collect();
+        if let Some(last) = lines.last_mut() {
+            last.replace_range(..1, ">");
+        }
+        lines.extend(self.undone.iter().rev().map(|command| format!("  ({})", command.label())));
+
+        let skip = lines.len().saturating_sub(count);
+        let mut text = format!("History: {} done, {} undone", self.done.len(), self.undone.len());
+        for line in &lines[skip..] {
+            text += "\n";
+            text += line;
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sets a number, merging with any other setting
+    struct Set {
+        from: i32,
+        to: i32,
+    }
+
+    impl Command for Set {
+        type Target = i32;
+
+        fn apply(&self, value: &mut i32) {
+            *value = self.to;
+        }
+
+        fn revert(&self, value: &mut i32) {
+            *value = self.from;
+        }
+
+        fn label(&self) -> String {
+            format!("Set {}", self.to)
+        }
+
+        fn merge(&mut self, next: &Self) -> bool {
+            self.to = next.to;
+            true
+        }
+    }
+
+    fn set(history: &mut History<Set>, value: &mut i32, to: i32) {
+        history.record(Set { from: *value, to });
+        *value = to;
+    }
+
+    fn mouse(history: &mut History<Set>, action: Action) {
+        history.handle_event(&WindowEvent::MouseButton(MouseButton::Button1, action, Modifiers::empty()));
+    }
+
+    #[test]
+    fn edits_during_a_press_are_one_step() {
+        let mut history = History::new();
+        let mut value = 0;
+
+        mouse(&mut history, Action::Press);
+        for to in 1..=5 {
+            set(&mut history, &mut value, to);
+        }
+        mouse(&mut history, Action::Release);
+        assert_eq!(history.done.len(), 1);
+
+        assert!(history.undo(&mut value));
+        assert_eq!(value, 0);
+        assert!(history.redo(&mut value));
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn edits_without_a_press_or_across_presses_are_separate_steps() {
+        let mut history = History::new();
+        let mut value = 0;
+
+        set(&mut history, &mut value, 1);
+        set(&mut history, &mut value, 2);
+        for to in [3, 4] {
+            mouse(&mut history, Action::Press);
+            set(&mut history, &mut value, to);
+            mouse(&mut history, Action::Release);
+        }
+        assert_eq!(history.done.len(), 4);
+
+        history.undo(&mut value);
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    fn undo_ends_coalescing() {
+        let mut history = History::new();
+        let mut value = 0;
+
+        mouse(&mut history, Action::Press);
+        set(&mut history, &mut value, 1);
+        history.undo(&mut value);
+        set(&mut history, &mut value, 2);
+        assert_eq!(history.done.len(), 1);
+        assert_eq!(history.done[0].from, 0);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = History::new();
+        let mut value = 0;
+
+        set(&mut history, &mut value, 1);
+        set(&mut history, &mut value, 2);
+        history.undo(&mut value);
+        assert_eq!(history.undone.len(), 1);
+
+        set(&mut history, &mut value, 3);
+        assert!(history.undone.is_empty());
+        assert!(!history.redo(&mut value));
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    fn oldest_edits_are_forgotten_past_the_limit() {
+        let mut history = History::new();
+        let mut value = 0;
+
+        for to in 1..=HISTORY_LIMIT as i32 + 5 {
+            set(&mut history, &mut value, to);
+        }
+        assert_eq!(history.done.len(), HISTORY_LIMIT);
+
+        while history.undo(&mut value) {}
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn control_z_undoes_and_shift_redoes() {
+        let mut history: History<Set> = History::new();
+        let key = |modifiers| WindowEvent::Key(UNDO_KEY, Action::Press, modifiers);
+
+        assert!(matches!(history.handle_event(&key(Modifiers::Control)), Some(HistoryAction::Undo)));
+        assert!(matches!(history.handle_event(&key(Modifiers::Control | Modifiers::Shift)), Some(HistoryAction::Redo)));
+        assert!(history.handle_event(&key(Modifiers::empty())).is_none());
+    }
+}
@@ -0,0 +1,6 @@
+// Code every lab uses the same way, kept here instead of in a copy per lab
+pub mod coordinate_converter;
+pub mod grid;
+pub mod history;
+pub mod scene_file;
+pub mod screenshot;
@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use kiss3d::event::Key;
+use kiss3d::nalgebra::Point3;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Writes the current state to the scene file the lab was started with, or to the default one
+pub const SAVE_SCENE_KEY: Key = Key::F5;
+pub const DEFAULT_SCENE_FILE: &str = "scene.toml";
+
+pub type Color = [f32; 3];
+
+pub fn color(color: Color) -> Point3<f32> {
+    Point3::from(color)
+}
+
+// Checks of a scene beyond the types of its values
+pub trait Validate {
+    fn validate(&self, errors: &mut Errors);
+}
+
+// Validation errors, each naming the key of the offending value
+#[derive(Default)]
+pub struct Errors(Vec<String>);
+
+impl Errors {
+    pub fn check(&mut self, key: &str, is_valid: bool, message: &str) {
+        if !is_valid {
+            self.0.push(format!("`{}`: {}", key, message));
+        }
+    }
+
+    pub fn color(&mut self, key: &str, color: &Color) {
+        self.check(key, color.iter().all(|c| (0.0..=1.0).contains(c)), "color components must be between 0 and 1");
+    }
+
+    pub fn range(&mut self, key: &str, [min, max]: &[f32; 2]) {
+        self.check(key, min < max, "the range must start below its end");
+    }
+}
+
+enum Format {
+    Toml,
+    Json,
+}
+
+fn format(path: &Path) -> Result<Format, String> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => Ok(Format::Toml),
+        Some("json") => Ok(Format::Json),
+        _ => Err(format!("{}: scene files are .toml or .json", path.display())),
+    }
+}
+
+// The scene of `--scene PATH` or `--scene=PATH` and where to save it,
+// the compiled-in scene and the default file without the option
+pub fn load_from_args<T: Default + DeserializeOwned + Validate>() -> Result<(T, PathBuf), String> {
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--scene") {
+            Some("") => args.next(),
+            Some(value) => value.strip_prefix('=').map(str::to_string),
+            None => continue,
+        };
+        path = Some(PathBuf::from(value.ok_or_else(|| "--scene expects a .toml or .json file".to_string())?));
+    }
+
+    match path {
+        Some(path) => Ok((load(&path)?, path)),
+        None => Ok((T::default(), PathBuf::from(DEFAULT_SCENE_FILE))),
+    }
+}
+
+// Missing keys keep their compiled-in values, unknown ones are errors
+pub fn load<T: DeserializeOwned + Validate>(path: &Path) -> Result<T, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let line = |offset: usize| text[..offset.min(text.len())].matches('\n').count() + 1;
+    let location = |line: Option<usize>, key: &serde_path_to_error::Path| {
+        let mut location = path.display().to_string();
+        if let Some(line) = line {
+            location += &format!(":{}", line);
+        }
+        if key.iter().next().is_some() {
+            location += &format!(": `{}`", key);
+        }
+        location
+    };
+
+    let scene: T = match format(path)? {
+        Format::Toml => serde_path_to_error::deserialize(toml::Deserializer::new(&text)).map_err(|e| {
+            let line = e.inner().span().map(|span| line(span.start));
+            format!("{}: {}", location(line, e.path()), e.inner().message().trim_end())
+        })?,
+        // JSON errors tell the line and the column themselves
+        Format::Json => serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&text))
+            .map_err(|e| format!("{}: {}", location(None, e.path()), e.inner()))?,
+    };
+
+    let mut errors = Errors::default();
+    scene.validate(&mut errors);
+    if errors.0.is_empty() {
+        Ok(scene)
+    } else {
+        let errors: Vec<String> = errors.0.iter().map(|error| format!("{}: {}", path.display(), error)).collect();
+        Err(errors.join("\n"))
+    }
+}
+
+// TOML goes through JSON, which writes f32 values as short as they were read, not widened to f64
+pub fn save<T: Serialize>(path: &Path, scene: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(scene).map_err(|e| e.to_string())?;
+    let text = match format(path)? {
+        Format::Toml => {
+            let value: toml::Value = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            toml::to_string(&value).map_err(|e| e.to_string())?
+        },
+        Format::Json => json,
+    };
+    fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use kiss3d::event::{Action, Key, WindowEvent};
+use kiss3d::window::Window;
+
+// Writes the window contents to a PNG file named by the time it was taken
+pub const SCREENSHOT_KEY: Key = Key::F12;
+
+// Saves what is on screen: one image on the screenshot key, and with `--frames N` one image
+// for each of the first N frames, numbered in a directory of their own, to make videos or GIFs of
+pub struct Screenshots {
+    requested: bool,
+    frames_left: usize,
+    frame_index: usize,
+    frames_directory: PathBuf,
+}
+
+impl Screenshots {
+    // Reads `--frames N` or `--frames=N` from the command line, other arguments are left alone
+    pub fn from_args() -> Result<Self, String> {
+        let mut frames = 0;
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let value = match arg.strip_prefix("--frames") {
+                Some("") => args.next(),
+                Some(value) => value.strip_prefix('=').map(str::to_string),
+                None => continue,
+            };
+            frames = value
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| "--frames expects the number of frames to record".to_string())?;
+        }
+
+        Ok(Screenshots {
+            requested: false,
+            frames_left: frames,
+            frame_index: 0,
+            frames_directory: PathBuf::from(format!("frames-{}", timestamp())),
+        })
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::Key(key, Action::Press, _) = event {
+            self.requested |= *key == SCREENSHOT_KEY;
+        }
+    }
+
+    // To be called right after a frame is rendered, while it is still on screen
+    pub fn capture(&mut self, window: &Window) {
+        if self.requested {
+            self.requested = false;
+            save(window, PathBuf::from(format!("screenshot-{}.png", timestamp())));
+        }
+
+        if self.frames_left == 0 {
+            return;
+        }
+        if self.frame_index == 0 {
+            if let Err(e) = fs::create_dir_all(&self.frames_directory) {
+                eprintln!("Could not create {}: {}", self.frames_directory.display(), e);
+                self.frames_left = 0;
+                return;
+            }
+        }
+        save(window, self.frames_directory.join(format!("frame-{:05}.png", self.frame_index)));
+        self.frame_index += 1;
+        self.frames_left -= 1;
+    }
+}
+
+fn save(window: &Window, path: PathBuf) {
+    match window.snap_image().save(&path) {
+        Ok(()) => println!("Saved {}", path.display()),
+        Err(e) => eprintln!("Could not save {}: {}", path.display(), e),
+    }
+}
+
+// UTC date and time down to milliseconds, like 2024-05-01_12-30-05-250, for file names that
+// do not overwrite each other
+pub fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (days, seconds) = ((now.as_secs() / 86400) as i64, now.as_secs() % 86400);
+
+    // Civil date from the days since 1970-01-01, in 400-year eras starting on March 1st
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}-{:03}",
+        year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60, now.subsec_millis()
+    )
+}
@@ -2,6 +2,7 @@ extern crate kiss3d;
 extern crate nalgebra as na;
 
 use kiss3d::conrod::{self, widget, UiCell, Colorable, Borderable};
+use kiss3d::event::{Action, WindowEvent};
 use kiss3d::light::Light;
 use kiss3d::window::Window;
 use kiss3d::camera::{FixedView};
@@ -13,15 +14,17 @@ use std::ops::Add;
 
 use conrod::{Sizeable, Positionable, Labelable, Widget, widget_ids};
 
-const UI_WIDTH_P: f64 = 150.;
+use lab_common::history::{Command, History};
+use scene::{scene, Scene};
+use lab_common::scene_file::{self, color, SAVE_SCENE_KEY};
+use lab_common::screenshot::Screenshots;
 
-const AXE_LENGTH_N: f32 = 0.24;
+mod scene;
 
-const X_INIT_POS_N: f32 = 0.05;
-const Y_INIT_POS_N: f32 = -0.06;
-const Z_INIT_POS_N: f32 = -0.4;
+const UI_WIDTH_P: f64 = 150.;
 
-const Y_INIT_ROT: f32 = -45.0 / 180.0 * PI;
+// Edits listed at the bottom of the UI
+const HISTORY_LINES: usize = 12;
 
 struct Rotation {
     x_angle: f32,
@@ -41,6 +44,47 @@ impl Rotation {
     fn z(&self) -> UnitQuaternion<f32> {
         UnitQuaternion::from_axis_angle(&Vector3::z_axis(), self.z_angle / 180.0 * PI)
     }
+
+    fn angles(&self) -> [f32; 3] {
+        [self.x_angle, self.y_angle, self.z_angle]
+    }
+
+    fn set_angles(&mut self, [x_angle, y_angle, z_angle]: [f32; 3]) {
+        *self = Rotation {x_angle, y_angle, z_angle};
+    }
+}
+
+// Slider edit, a whole drag of one slider at once
+struct Rotate {
+    from: [f32; 3],
+    to: [f32; 3],
+}
+
+impl Command for Rotate {
+    type Target = Rotation;
+
+    fn apply(&self, rotation: &mut Rotation) {
+        rotation.set_angles(self.to);
+    }
+
+    fn revert(&self, rotation: &mut Rotation) {
+        rotation.set_angles(self.from);
+    }
+
+    fn label(&self) -> String {
+        let changes: Vec<String> = ["X", "Y", "Z"]
+            .iter()
+            .zip(self.from.iter().zip(self.to.iter()))
+            .filter(|(_, (from, to))| from != to)
+            .map(|(axis, (_, to))| format!("{} to {:.1}°", axis, to))
+            .collect();
+        format!("Rotate {}", changes.join(", "))
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        self.to = next.to;
+        true
+    }
 }
 
 widget_ids! {
@@ -52,14 +96,16 @@ widget_ids! {
         angle_x,
         angle_y,
         angle_z,
+        history_text,
     }
 }
 
-fn draw_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
+fn draw_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation, history: &History<Rotate>) {
     let slider_w_p = 16.0;
     let slider_h_p = 180.0;
     let sliders_gap_p = 33.0;
     let font_size = 11;
+    let [min_angle, max_angle] = scene().ui.rotation_range;
 
     widget::Canvas::new()
         .align_left()
@@ -68,7 +114,7 @@ fn draw_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
         .border_rgb(1.0, 1.0, 1.0)
         .set(ids.canvas, ui_cell);
 
-    for v in widget::Slider::new(rot.x_angle, -180.0, 180.0)
+    if let Some(v) = widget::Slider::new(rot.x_angle, min_angle, max_angle)
         .label("X")
         .w(slider_w_p)
         .h(slider_h_p)
@@ -84,7 +130,7 @@ fn draw_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
         .align_middle_x_of(ids.slider_x)
         .set(ids.angle_x, ui_cell);
 
-    for v in widget::Slider::new(rot.y_angle, -180.0, 180.0)
+    if let Some(v) = widget::Slider::new(rot.y_angle, min_angle, max_angle)
         .label("Y")
         .w(slider_w_p)
         .h(slider_h_p)
@@ -100,7 +146,7 @@ fn draw_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
         .align_middle_x_of(ids.slider_y)
         .set(ids.angle_y, ui_cell);
 
-    for v in widget::Slider::new(rot.z_angle, -180.0, 180.0)
+    if let Some(v) = widget::Slider::new(rot.z_angle, min_angle, max_angle)
         .label("Z")
         .w(slider_w_p)
         .h(slider_h_p)
@@ -115,15 +161,20 @@ fn draw_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
         .down_from(ids.slider_z, 10.)
         .align_middle_x_of(ids.slider_z)
         .set(ids.angle_z, ui_cell);
+
+    widget::Text::new(&history.text(HISTORY_LINES))
+        .font_size(font_size)
+        .bottom_left_with_margin_on(ids.canvas, 10.)
+        .set(ids.history_text, ui_cell);
 }
 
 fn draw_axes(window: &mut Window) {
-    let color = Point3::new(0.0, 0.0, 0.0);
-    let init_shift = Vector3::new(X_INIT_POS_N, Y_INIT_POS_N, Z_INIT_POS_N);
-    let init_rot = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), Y_INIT_ROT);
+    let color = color(scene().axes.color);
+    let init_shift = Vector3::from(scene().camera.position);
+    let init_rot = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), scene().camera.y_rotation / 180.0 * PI);
 
     // Axes
-    let half_axe = AXE_LENGTH_N / 2.0;
+    let half_axe = scene().axes.length / 2.0;
 
     window.set_line_width(1.0);
     window.draw_line(
@@ -204,6 +255,19 @@ fn draw_axes(window: &mut Window) {
 }
 
 fn main() {
+    // Screenshots and frame recording
+    let mut screenshots = Screenshots::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    // Scene
+    let (loaded_scene, scene_path) = scene_file::load_from_args::<Scene>().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    scene::install(loaded_scene);
+
     // Window
     let mut window = Window::new("Kiss3d: obj");
     window.set_light(Light::StickToCamera);
@@ -213,26 +277,56 @@ fn main() {
     let mut camera = FixedView::new();
 
     // State
-    let init_rot = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), Y_INIT_ROT);
-    let mut rotation = Rotation {x_angle: 0.0, y_angle: 0.0, z_angle: 0.0};
+    let init_rot = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), scene().camera.y_rotation / 180.0 * PI);
+    let [x_angle, y_angle, z_angle] = scene().rotation;
+    let mut rotation = Rotation {x_angle, y_angle, z_angle};
+    let mut history = History::new();
 
     // Teapot
-    let obj_path = Path::new("./src/media/teapot.obj");
-    let mtl_path = Path::new("./src/media");
+    let obj_path = Path::new(&scene().model.obj);
+    let mtl_path = Path::new(&scene().model.materials);
 
-    let mut teapot = window.add_obj(obj_path, mtl_path, Vector3::new(0.001, 0.001, 0.001));
+    let scale = scene().model.scale;
+    let mut teapot = window.add_obj(obj_path, mtl_path, Vector3::new(scale, scale, scale));
     
     // UI
     let ids = Ids::new(window.conrod_ui_mut().widget_id_generator());
 
     while window.render_with_camera(&mut camera) {
     //while window.render() {
-        teapot.set_local_translation(Translation3::new(X_INIT_POS_N, Y_INIT_POS_N, Z_INIT_POS_N));
+        screenshots.capture(&window);
+
+        teapot.set_local_translation(Translation3::from(Vector3::from(scene().camera.position)));
         teapot.set_local_rotation(init_rot * rotation.x() * rotation.y() * rotation.z());
 
         draw_axes(&mut window);
 
+        let angles = rotation.angles();
         let mut ui_cell = window.conrod_ui_mut().set_widgets();
-        draw_ui(&mut ui_cell, &ids, &mut rotation);
+        draw_ui(&mut ui_cell, &ids, &mut rotation, &history);
+        drop(ui_cell);
+        if rotation.angles() != angles {
+            history.record(Rotate { from: angles, to: rotation.angles() });
+        }
+
+        for event in window.events().iter() {
+            screenshots.handle_event(&event.value);
+            if let Some(action) = history.handle_event(&event.value) {
+                history.perform(action, &mut rotation);
+            }
+
+            if let WindowEvent::Key(key, Action::Press, _) = event.value {
+                if key == SAVE_SCENE_KEY {
+                    let current_scene = Scene {
+                        rotation: [rotation.x_angle, rotation.y_angle, rotation.z_angle],
+                        ..scene().clone()
+                    };
+                    match scene_file::save(&scene_path, &current_scene) {
+                        Ok(()) => println!("Saved {}", scene_path.display()),
+                        Err(error) => eprintln!("{}", error),
+                    }
+                }
+            }
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use lab_common::scene_file::{Color, Errors, Validate};
+
+static SCENE: OnceLock<Scene> = OnceLock::new();
+
+// The teapot, where it is seen from, its rotation and the axes around it
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    pub camera: Camera,
+    pub model: Model,
+    // Degrees around x, y and z, as set by the sliders
+    pub rotation: [f32; 3],
+    pub axes: Axes,
+    pub ui: Ui,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Camera {
+    // Of the model and the axes in front of the fixed view
+    pub position: [f32; 3],
+    // Degrees around y, the axes turned to be seen from aside
+    pub y_rotation: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Model {
+    pub obj: String,
+    pub materials: String,
+    pub scale: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Axes {
+    pub length: f32,
+    pub color: Color,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ui {
+    pub rotation_range: [f32; 2],
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            camera: Camera::default(),
+            model: Model::default(),
+            rotation: [0.0, 0.0, 0.0],
+            axes: Axes::default(),
+            ui: Ui::default(),
+        }
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera { position: [0.05, -0.06, -0.4], y_rotation: -45.0 }
+    }
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Model { obj: "./src/media/teapot.obj".to_string(), materials: "./src/media".to_string(), scale: 0.001 }
+    }
+}
+
+impl Default for Axes {
+    fn default() -> Self {
+        Axes { length: 0.24, color: [0.0, 0.0, 0.0] }
+    }
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Ui { rotation_range: [-180.0, 180.0] }
+    }
+}
+
+impl Validate for Scene {
+    fn validate(&self, errors: &mut Errors) {
+        errors.check("model.scale", self.model.scale > 0.0, "must be positive");
+        errors.check("axes.length", self.axes.length > 0.0, "must be positive");
+        errors.color("axes.color", &self.axes.color);
+        errors.range("ui.rotation_range", &self.ui.rotation_range);
+
+        let [min, max] = self.ui.rotation_range;
+        errors.check("rotation", self.rotation.iter().all(|angle| (min..=max).contains(angle)), "angles must be within ui.rotation_range");
+    }
+}
+
+// Set once at startup, before anything reads the scene
+pub fn install(scene: Scene) {
+    let _ = SCENE.set(scene);
+}
+
+pub fn scene() -> &'static Scene {
+    SCENE.get_or_init(Scene::default)
+}
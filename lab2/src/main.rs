@@ -6,18 +6,68 @@ use kiss3d::window::Window;
 use kiss3d::camera::{FixedView};
 use kiss3d::text::Font;
 use kiss3d::ncollide3d;
+use kiss3d::conrod::{widget, widget_ids, Borderable, Colorable, Labelable, Positionable, Sizeable, UiCell, Widget};
+use kiss3d::conrod::text::{rt, Scale};
 use na::{Translation2, Point3, Point2, Vector2, OPoint};
 
 use std::ops::{Add};
+use std::rc::Rc;
+
+use lab_common::coordinate_converter::CoordinateConverter;
+use lab_common::grid::{proceed_grid_ui, Grid, GridIds, SnapKey};
+use lab_common::history::{Command, History};
+use scene::{scene, Scene};
+use lab_common::scene_file::{self, color, SAVE_SCENE_KEY};
+use lab_common::screenshot::Screenshots;
+
+mod scene;
+
+// Edits listed in the top left corner
+const HISTORY_LINES: usize = 12;
+
+widget_ids! {
+    pub struct Ids {
+        history_text,
+        points_canvas,
+        points_title,
+        point_canvases[],
+        point_labels[],
+        x_dialers[],
+        y_dialers[],
+        cursor_text,
+    }
+}
 
-use coordinate_converter::CoordinateConverter;
+// A control point moved, a whole drag at once
+struct MovePoint {
+    index: usize,
+    from: Point2<f32>,
+    to: Point2<f32>,
+}
 
-mod coordinate_converter;
+impl Command for MovePoint {
+    type Target = Vec<Point2<f32>>;
 
-const AXE_LENGTH_N: f32 = 1.6;
+    fn apply(&self, control_points: &mut Vec<Point2<f32>>) {
+        control_points[self.index] = self.to;
+    }
 
-const X_INIT_POS_N: f32 = 0.0;
-const Y_INIT_POS_N: f32 = 0.0;
+    fn revert(&self, control_points: &mut Vec<Point2<f32>>) {
+        control_points[self.index] = self.from;
+    }
+
+    fn label(&self) -> String {
+        format!("Move point {} to {:.2} {:.2}", self.index + 1, self.to.x, self.to.y)
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        if next.index != self.index {
+            return false;
+        }
+        self.to = next.to;
+        true
+    }
+}
 
 struct DragAndDrop {
     is_hovering: bool,
@@ -59,9 +109,12 @@ impl DragAndDrop {
     }
 }
 
-fn draw_axes(window: &mut Window, length_normalized: f32, cc: &CoordinateConverter) {
-    let color = Point3::new(0.0, 0.0, 0.0);
-    let init_shift = Vector2::new(X_INIT_POS_N, Y_INIT_POS_N);
+fn draw_axes(window: &mut Window, length_normalized: f32, grid: &Grid, cc: &CoordinateConverter) {
+    let color = color(scene().axes.color);
+    let init_shift = Vector2::from(scene().axes.origin);
+
+    // Under the axes
+    grid.draw(window);
 
     let half_axe = length_normalized / 2.0;
 
@@ -118,26 +171,117 @@ fn draw_axes(window: &mut Window, length_normalized: f32, cc: &CoordinateConvert
     );
 }
 
+// Width and height of a line of text as kiss3d lays it out, in the units of its text positions
+fn text_size(font: &Rc<Font>, text: &str, font_size: f32) -> Vector2<f32> {
+    let scale = Scale::uniform(font_size);
+    let v_metrics = font.font().v_metrics(scale);
+    let width = font.font()
+        .layout(text, scale, rt::point(0.0, 0.0))
+        .last()
+        .map_or(0.0, |glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width);
+
+    Vector2::new(width, v_metrics.ascent - v_metrics.descent)
+}
+
+// Centered above the point, or under it near the top of the window, and kept within the window sides.
+// Text positions are top-left, over twice the window size, like the converter's top-left coordinates.
 fn draw_point_coordinates(window: &mut Window, point: &Point2<f32>, circle_radius: f32, cc: &CoordinateConverter) {
     let text_color = Point3::new(0.0, 0.0, 0.0);
-    let font = 35.0;
-    let font_height = font / 3.0;
-    let font_width = font_height / 2.0;
+    let font = Font::default();
+    let font_size = 35.0;
+    let text_gap = 10.0;
     let text = format!("{:.2} {:.2}", point.x, point.y);
-    let text_shift = Vector2::new(-(text.len() as f32 / 2.0 * font_width), circle_radius + font_height + 10.0);
-    
-    let shifted_point = point.add(text_shift);
-    let position = Point2::new(cc.x_centered_to_top_left_p(shifted_point.x), cc.y_centered_to_top_left_p(shifted_point.y));
+    let size = text_size(&font, &text, font_size);
+
+    let right = cc.x_centered_to_top_left_p(window.width() as f32 / 2.0);
+    let x = (cc.x_centered_to_top_left_p(point.x) - size.x / 2.0).min(right - size.x).max(0.0);
+    let above = cc.y_centered_to_top_left_p(point.y + circle_radius + text_gap) - size.y;
+    let y = if above >= 0.0 { above } else { cc.y_centered_to_top_left_p(point.y - circle_radius - text_gap) };
 
     window.draw_text(
         &text, 
-        &position,
-        font, 
-        &Font::default(), 
+        &Point2::new(x, y),
+        font_size, 
+        &font, 
         &text_color
     );
 }
 
+// Every control point with its normalized coordinates, and where the cursor is, in the top right corner
+fn draw_points_ui(ui_cell: &mut UiCell, ids: &Ids, control_points: &mut [Point2<f32>], cursor: &Point2<f32>, cc: &CoordinateConverter) {
+    let margin = 20.0;
+    let point_canvas_height = 35.0;
+    let point_canvas_width = 220.0;
+    let point_canvas_gap = 10.0;
+    let label_width = 25.0;
+    let number_dialer_width = 80.0;
+    let number_dialer_height = 25.0;
+    let number_dialer_gap = 10.0;
+    let text_height = 15.0;
+    let [r, g, b] = scene().colors.control_point;
+
+    let rows = control_points.len() as f64;
+    widget::Canvas::new()
+        .w_h(
+            point_canvas_width + 2.0 * margin,
+            rows * (point_canvas_height + point_canvas_gap) + 3.0 * text_height + 2.0 * margin + point_canvas_gap
+        )
+        .rgb(1.0, 1.0, 1.0)
+        .border_rgb(1.0, 1.0, 1.0)
+        .top_right()
+        .set(ids.points_canvas, ui_cell);
+
+    widget::Text::new("Control points")
+        .font_size(12)
+        .top_left_with_margin_on(ids.points_canvas, margin)
+        .set(ids.points_title, ui_cell);
+
+    for (index, point) in control_points.iter_mut().enumerate() {
+        let canvas = widget::Canvas::new()
+            .w_h(point_canvas_width, point_canvas_height)
+            .rgb(1.0, 1.0, 1.0)
+            .border_rgb(r, g, b);
+        let canvas = match index {
+            0 => canvas.down_from(ids.points_title, point_canvas_gap),
+            _ => canvas.down_from(ids.point_canvases[index - 1], point_canvas_gap),
+        };
+        canvas.set(ids.point_canvases[index], ui_cell);
+
+        widget::Text::new(&(index + 1).to_string())
+            .font_size(12)
+            .mid_left_with_margin_on(ids.point_canvases[index], 10.0)
+            .set(ids.point_labels[index], ui_cell);
+
+        if let Some(value) = widget::NumberDialer::new(point.x, -1.0, 1.0, 2)
+            .w_h(number_dialer_width, number_dialer_height)
+            .border_rgb(1.0, 1.0, 1.0)
+            .mid_left_with_margin_on(ids.point_canvases[index], label_width)
+            .label("X")
+            .set(ids.x_dialers[index], ui_cell)
+        {
+            point.x = value;
+        }
+
+        if let Some(value) = widget::NumberDialer::new(point.y, -1.0, 1.0, 2)
+            .w_h(number_dialer_width, number_dialer_height)
+            .border_rgb(1.0, 1.0, 1.0)
+            .right_from(ids.x_dialers[index], number_dialer_gap)
+            .label("Y")
+            .set(ids.y_dialers[index], ui_cell)
+        {
+            point.y = value;
+        }
+    }
+
+    widget::Text::new(&format!(
+        "Cursor {:.2} {:.2}\n{:.0} {:.0} px from the center",
+        cc.x_centered_p_to_n(cursor.x), cc.y_centered_p_to_n(cursor.y), cursor.x, cursor.y
+    ))
+        .font_size(12)
+        .down_from(ids.point_canvases[control_points.len() - 1], point_canvas_gap)
+        .set(ids.cursor_text, ui_cell);
+}
+
 fn is_point_in_circle(point: &Point2<f32>, circle_center: &Point2<f32>, radius: f32) -> bool {
     let x = point.x - circle_center.x;
     let y = point.y - circle_center.y;
@@ -146,6 +290,19 @@ fn is_point_in_circle(point: &Point2<f32>, circle_center: &Point2<f32>, radius:
 }
 
 fn main() {
+    // Screenshots and frame recording
+    let mut screenshots = Screenshots::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    // Scene
+    let (loaded_scene, scene_path) = scene_file::load_from_args::<Scene>().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    scene::install(loaded_scene);
+
     // Window
     let mut window = Window::new("Kiss3d: obj");
     window.set_light(Light::StickToCamera);
@@ -156,20 +313,17 @@ fn main() {
 
     // State
     let mut cursor = Point2::new(0.0, 0.0);
+    let mut grid_settings = scene().grid.clone();
+    let mut snap_key = SnapKey::default();
 
     // Settings
-    let circle_radius = 10.0;
-    let control_point_color = Point3::new(0.6, 0.0, 0.0);
-    let control_line_color = Point3::new(0.6, 0.0, 0.0);
-    let bezier_curve_color = Point3::new(1.0, 0.0, 0.0);
+    let circle_radius = scene().circle_radius;
+    let control_point_color = color(scene().colors.control_point);
+    let control_line_color = color(scene().colors.control_line);
+    let bezier_curve_color = color(scene().colors.curve);
 
     // Control points
-    let mut control_points_2d_n = vec![
-        Point2::new(-0.4f32, -0.1),
-        Point2::new(-0.2f32, 0.4),
-        Point2::new(0.2f32, 0.4),
-        Point2::new(0.4f32, -0.1),
-    ];
+    let mut control_points_2d_n: Vec<Point2<f32>> = scene().control_points.iter().map(|point| Point2::from(*point)).collect();
     let control_points_count = control_points_2d_n.len();
 
     // Control point's circles
@@ -182,13 +336,26 @@ fn main() {
 
     // Drag and drop helper
     let mut dd = DragAndDrop::new();
+    let mut history = History::new();
+
+    // UI
+    let mut ids = Ids::new(window.conrod_ui_mut().widget_id_generator());
+    let mut id_generator = window.conrod_ui_mut().widget_id_generator();
+    ids.point_canvases.resize(control_points_count, &mut id_generator);
+    ids.point_labels.resize(control_points_count, &mut id_generator);
+    ids.x_dialers.resize(control_points_count, &mut id_generator);
+    ids.y_dialers.resize(control_points_count, &mut id_generator);
+    let grid_ids = GridIds::new(window.conrod_ui_mut().widget_id_generator());
     
     while window.render_with_camera(&mut camera) {
+        screenshots.capture(&window);
+
         let window_width = window.width();
         let window_height = window.height();
 
         // Coordinate system helper
         let cc = CoordinateConverter::new(window_width, window_height);
+        let grid = Grid::new(&grid_settings, Point2::from(scene().axes.origin), window_width, window_height, true);
 
         // Map control points to 2d centered coordinate system
         let control_points_2d: Vec<OPoint<f32, na::Const<2>>> = control_points_2d_n
@@ -208,7 +375,7 @@ fn main() {
                 Point3::new(point.x, point.y, 0.0)
             })
             .collect();
-        let bezier: Vec<OPoint<f32, na::Const<2>>> = ncollide3d::procedural::bezier_curve(&control_points_3d, 100)
+        let bezier: Vec<OPoint<f32, na::Const<2>>> = ncollide3d::procedural::bezier_curve(&control_points_3d, scene().curve_segments)
         .iter()
         .map(|point| {
             Point2::new(
@@ -221,7 +388,7 @@ fn main() {
         // Check points hovering
         dd.set_hovering(None);
         for (index, point) in control_points_2d.iter().enumerate() {
-            let hovering = is_point_in_circle(&cursor, &point, circle_radius);
+            let hovering = is_point_in_circle(&cursor, point, circle_radius);
             if hovering {
                 dd.set_hovering(Some(index as u32));
                 draw_point_coordinates(&mut window, point, circle_radius, &cc);
@@ -230,10 +397,25 @@ fn main() {
 
         // Proceed drag and drop
         if let Some(control_point_index) = dd.is_dragging() {
-            control_points_2d_n[control_point_index as usize] = Point2::new(
-                cc.x_centered_p_to_n(cursor.x),
-                cc.y_centered_p_to_n(cursor.y),
+            let index = control_point_index as usize;
+            let mut snapped = cursor;
+            if snap_key.is_snapping() {
+                let others: Vec<Point2<f32>> = control_points_2d
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != index)
+                    .map(|(_, point)| *point)
+                    .collect();
+                snapped = grid.snap(cursor, &others);
+            }
+            let position = Point2::new(
+                cc.x_centered_p_to_n(snapped.x),
+                cc.y_centered_p_to_n(snapped.y),
             );
+            if position != control_points_2d_n[index] {
+                history.record(MovePoint { index, from: control_points_2d_n[index], to: position });
+                control_points_2d_n[index] = position;
+            }
         }
 
         // Translate point circles
@@ -257,9 +439,32 @@ fn main() {
             window.draw_planar_line(&bezier[i], &bezier[i + 1], &bezier_curve_color);
         }
 
-        draw_axes(&mut window, AXE_LENGTH_N, &cc);
+        draw_axes(&mut window, scene().axes.length, &grid, &cc);
+
+        let mut ui_cell = window.conrod_ui_mut().set_widgets();
+        widget::Text::new(&history.text(HISTORY_LINES))
+            .font_size(12)
+            .top_left_with_margin(20.0)
+            .set(ids.history_text, &mut ui_cell);
+        proceed_grid_ui(&mut ui_cell, &grid_ids, &mut grid_settings);
+        let control_points_before_ui = control_points_2d_n.clone();
+        draw_points_ui(&mut ui_cell, &ids, &mut control_points_2d_n, &cursor, &cc);
+        drop(ui_cell);
+        for (index, (from, to)) in control_points_before_ui.iter().zip(control_points_2d_n.iter()).enumerate() {
+            if from != to {
+                history.record(MovePoint { index, from: *from, to: *to });
+            }
+        }
+
+        // Clicks on the panels don't grab the points under them
+        let is_ui_capturing_mouse = window.is_conrod_ui_capturing_mouse();
 
         for event in window.events().iter() {
+            screenshots.handle_event(&event.value);
+            snap_key.handle_event(&event.value);
+            if let Some(action) = history.handle_event(&event.value) {
+                history.perform(action, &mut control_points_2d_n);
+            }
             match event.value {
                 WindowEvent::CursorPos(x, y, _modif) => {
                     cursor = na::Point2::new(
@@ -267,13 +472,22 @@ fn main() {
                         cc.y_top_left_to_centered_p(y as f32)
                     );
                 },
-                WindowEvent::MouseButton(btn, action ,_) => {
-                    if let MouseButton::Button1 = btn {
-                        if let Action::Press = action {
-                            dd.set_mouse_pressed(true);
-                        } else {
-                            dd.set_mouse_pressed(false);
-                        }
+                WindowEvent::MouseButton(MouseButton::Button1, action ,_) => {
+                    if let Action::Press = action {
+                        dd.set_mouse_pressed(!is_ui_capturing_mouse);
+                    } else {
+                        dd.set_mouse_pressed(false);
+                    }
+                }
+                WindowEvent::Key(key, Action::Press, _) if key == SAVE_SCENE_KEY => {
+                    let current_scene = Scene {
+                        control_points: control_points_2d_n.iter().map(|point| [point.x, point.y]).collect(),
+                        grid: grid_settings.clone(),
+                        ..scene().clone()
+                    };
+                    match scene_file::save(&scene_path, &current_scene) {
+                        Ok(()) => println!("Saved {}", scene_path.display()),
+                        Err(error) => eprintln!("{}", error),
                     }
                 }
                 _ => {}
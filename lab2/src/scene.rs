@@ -0,0 +1,94 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use lab_common::grid::GridSettings;
+use lab_common::scene_file::{Color, Errors, Validate};
+
+static SCENE: OnceLock<Scene> = OnceLock::new();
+
+// The Bézier curve, how it is drawn and the axes under it
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    // In normalized coordinates, -1 to 1 across the window
+    pub control_points: Vec<[f32; 2]>,
+    pub curve_segments: usize,
+    pub circle_radius: f32,
+    pub colors: Colors,
+    pub axes: Axes,
+    pub grid: GridSettings,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub control_point: Color,
+    pub control_line: Color,
+    pub curve: Color,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Axes {
+    // Normalized, like the control points
+    pub length: f32,
+    // Pixels from the window center
+    pub origin: [f32; 2],
+    pub color: Color,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            control_points: vec![[-0.4, -0.1], [-0.2, 0.4], [0.2, 0.4], [0.4, -0.1]],
+            curve_segments: 100,
+            circle_radius: 10.0,
+            colors: Colors::default(),
+            axes: Axes::default(),
+            grid: GridSettings::default(),
+        }
+    }
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            control_point: [0.6, 0.0, 0.0],
+            control_line: [0.6, 0.0, 0.0],
+            curve: [1.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl Default for Axes {
+    fn default() -> Self {
+        Axes { length: 1.6, origin: [0.0, 0.0], color: [0.0, 0.0, 0.0] }
+    }
+}
+
+impl Validate for Scene {
+    fn validate(&self, errors: &mut Errors) {
+        errors.check("control_points", self.control_points.len() >= 2, "a curve needs at least 2 control points");
+        for (i, point) in self.control_points.iter().enumerate() {
+            errors.check(&format!("control_points[{}]", i), point.iter().all(|c| (-1.0..=1.0).contains(c)), "coordinates must be between -1 and 1");
+        }
+        errors.check("curve_segments", self.curve_segments >= 1, "must be at least 1");
+        errors.check("circle_radius", self.circle_radius > 0.0, "must be positive");
+        errors.color("colors.control_point", &self.colors.control_point);
+        errors.color("colors.control_line", &self.colors.control_line);
+        errors.color("colors.curve", &self.colors.curve);
+        errors.check("axes.length", self.axes.length > 0.0, "must be positive");
+        errors.color("axes.color", &self.axes.color);
+        self.grid.validate(errors);
+    }
+}
+
+// Set once at startup, before anything reads the scene
+pub fn install(scene: Scene) {
+    let _ = SCENE.set(scene);
+}
+
+pub fn scene() -> &'static Scene {
+    SCENE.get_or_init(Scene::default)
+}
@@ -0,0 +1,54 @@
+use nalgebra::{Point3, Vector3};
+
+pub struct BezierCurve {
+    control_points: Vec<Vector3<f32>>,
+}
+
+impl BezierCurve {
+    pub fn new(control_points: &[Point3<f32>]) -> Self {
+        Self {
+            control_points: control_points.iter().map(|p| p.coords).collect()
+        }
+    }
+
+    // de Casteljau
+    pub fn point(&self, t: f32) -> Point3<f32> {
+        let mut points = self.control_points.clone();
+        let n = points.len();
+
+        for k in 1..n {
+            for i in 0..n - k {
+                points[i] = points[i] * (1.0 - t) + points[i + 1] * t;
+            }
+        }
+
+        Point3::from(points[0])
+    }
+
+    // Hodograph: the derivative of a Bezier curve is a Bezier curve of lower degree
+    pub fn derivative(&self) -> BezierCurve {
+        let degree = self.control_points.len() - 1;
+        if degree == 0 {
+            return BezierCurve { control_points: vec![Vector3::zeros()] };
+        }
+
+        BezierCurve {
+            control_points: self.control_points
+                .windows(2)
+                .map(|pair| (pair[1] - pair[0]) * degree as f32)
+                .collect()
+        }
+    }
+
+    pub fn vector(&self, t: f32) -> Vector3<f32> {
+        self.point(t).coords
+    }
+
+    pub fn start(&self) -> Point3<f32> {
+        Point3::from(self.control_points[0])
+    }
+
+    pub fn end(&self) -> Point3<f32> {
+        Point3::from(self.control_points[self.control_points.len() - 1])
+    }
+}
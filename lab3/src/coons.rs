@@ -0,0 +1,183 @@
+use nalgebra::{Point3, Vector3};
+
+use crate::bezier::BezierCurve;
+use crate::support::{BilinearSurface, Surface, SurfaceDerivatives};
+
+// Corner points closer than this are considered to be the same point
+const CORNER_TOLERANCE: f32 = 1e-4;
+
+pub struct CornerMismatch {
+    pub u: f32,
+    pub w: f32,
+    pub distance: f32,
+}
+
+// Bilinearly blended Coons patch.
+// Boundaries order: S(u, 0), S(u, 1), S(0, w), S(1, w)
+pub struct CoonsSurface {
+    boundaries: [BezierCurve; 4],
+    first_derivatives: [BezierCurve; 4],
+    second_derivatives: [BezierCurve; 4],
+    correction: BilinearSurface,
+}
+
+impl CoonsSurface {
+    pub fn new(boundaries: &[Vec<Point3<f32>>; 4]) -> Self {
+        let boundaries = boundaries.each_ref().map(|control_points| BezierCurve::new(control_points));
+
+        // If the boundaries don't meet, the corner is taken in the middle of the gap
+        let corners: Vec<Point3<f32>> = Self::corner_pairs(&boundaries)
+            .iter()
+            .map(|(a, b)| nalgebra::center(a, b))
+            .collect();
+
+        let first_derivatives = boundaries.each_ref().map(|curve| curve.derivative());
+        let second_derivatives = first_derivatives.each_ref().map(|curve| curve.derivative());
+
+        Self {
+            boundaries,
+            first_derivatives,
+            second_derivatives,
+            correction: BilinearSurface::new(&corners),
+        }
+    }
+
+    // Corners in BilinearSurface order: (0, 0), (0, 1), (1, 0), (1, 1)
+    fn corner_pairs(boundaries: &[BezierCurve; 4]) -> [(Point3<f32>, Point3<f32>); 4] {
+        let [u0, u1, w0, w1] = boundaries;
+
+        [
+            (u0.start(), w0.start()),
+            (u1.start(), w0.end()),
+            (u0.end(), w1.start()),
+            (u1.end(), w1.end()),
+        ]
+    }
+
+    pub fn corner_mismatches(&self) -> Vec<CornerMismatch> {
+        let corners_uw = [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 1.0)];
+
+        Self::corner_pairs(&self.boundaries)
+            .iter()
+            .zip(corners_uw.iter())
+            .map(|((a, b), (u, w))| CornerMismatch { u: *u, w: *w, distance: (a - b).norm() })
+            .filter(|mismatch| mismatch.distance > CORNER_TOLERANCE)
+            .collect()
+    }
+}
+
+impl Surface for CoonsSurface {
+    fn point(&self, u: f32, w: f32) -> Point3<f32> {
+        let [u0, u1, w0, w1] = &self.boundaries;
+
+        let ruled_u = u0.point(u).coords * (1.0 - w) + u1.point(u).coords * w;
+        let ruled_w = w0.point(w).coords * (1.0 - u) + w1.point(w).coords * u;
+
+        Point3::from(ruled_u + ruled_w - self.correction.point(u, w).coords)
+    }
+
+    fn derivatives(&self, u: f32, w: f32) -> SurfaceDerivatives {
+        let [u0, u1, w0, w1] = &self.boundaries;
+        let [du0, du1, dw0, dw1] = &self.first_derivatives;
+        let [ddu0, ddu1, ddw0, ddw1] = &self.second_derivatives;
+        let correction = self.correction.derivatives(u, w);
+
+        SurfaceDerivatives {
+            du: du0.vector(u) * (1.0 - w) + du1.vector(u) * w
+                - w0.vector(w) + w1.vector(w)
+                - correction.du,
+            dw: -u0.vector(u) + u1.vector(u)
+                + dw0.vector(w) * (1.0 - u) + dw1.vector(w) * u
+                - correction.dw,
+            duu: ddu0.vector(u) * (1.0 - w) + ddu1.vector(u) * w,
+            duw: -du0.vector(u) + du1.vector(u)
+                - dw0.vector(w) + dw1.vector(w)
+                - correction.duw,
+            dww: ddw0.vector(w) * (1.0 - u) + ddw1.vector(w) * u,
+        }
+    }
+}
+
+// Cubic boundaries along the edges of a bilinear patch, slightly bent to be visible
+pub fn boundaries_from_corners(corners: &[Point3<f32>]) -> [Vec<Point3<f32>>; 4] {
+    let bend = Vector3::new(0.0, 0.03, 0.0);
+    let cubic = |start: Point3<f32>, end: Point3<f32>| {
+        vec![
+            start,
+            start + (end - start) / 3.0 + bend,
+            start + (end - start) * 2.0 / 3.0 - bend,
+            end,
+        ]
+    };
+
+    [
+        cubic(corners[0], corners[2]),
+        cubic(corners[1], corners[3]),
+        cubic(corners[0], corners[1]),
+        cubic(corners[2], corners[3]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORNERS_UW: [(f32, f32); 4] = [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 1.0)];
+
+    fn corners() -> [Point3<f32>; 4] {
+        [
+            Point3::new(-1.0, 0.0, -1.0),
+            Point3::new(-1.0, 0.5, 1.0),
+            Point3::new(1.0, -0.5, -1.0),
+            Point3::new(1.0, 0.2, 1.0),
+        ]
+    }
+
+    fn assert_close(a: Point3<f32>, b: Point3<f32>) {
+        assert!((a - b).norm() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn bilinear_surface_passes_through_its_vertices() {
+        let corners = corners();
+        let surface = BilinearSurface::new(&corners);
+
+        for ((u, w), corner) in CORNERS_UW.iter().zip(corners.iter()) {
+            assert_close(surface.point(*u, *w), *corner);
+        }
+    }
+
+    #[test]
+    fn coons_surface_passes_through_its_corners_and_boundaries() {
+        let corners = corners();
+        let boundaries = boundaries_from_corners(&corners);
+        let surface = CoonsSurface::new(&boundaries);
+        assert!(surface.corner_mismatches().is_empty());
+
+        for ((u, w), corner) in CORNERS_UW.iter().zip(corners.iter()) {
+            assert_close(surface.point(*u, *w), *corner);
+        }
+
+        let [u0, u1, w0, w1] = boundaries.each_ref().map(|control_points| BezierCurve::new(control_points));
+        for t in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            assert_close(surface.point(t, 0.0), u0.point(t));
+            assert_close(surface.point(t, 1.0), u1.point(t));
+            assert_close(surface.point(0.0, t), w0.point(t));
+            assert_close(surface.point(1.0, t), w1.point(t));
+        }
+    }
+
+    #[test]
+    fn coons_surface_takes_the_middle_of_a_corner_gap() {
+        let corners = corners();
+        let mut boundaries = boundaries_from_corners(&corners);
+        let shift = Vector3::new(0.0, 0.4, 0.0);
+        boundaries[0][0] += shift;
+        let surface = CoonsSurface::new(&boundaries);
+
+        let mismatches = surface.corner_mismatches();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!((mismatches[0].u, mismatches[0].w), (0.0, 0.0));
+        assert_close(surface.point(0.0, 0.0), corners[0] + shift / 2.0);
+    }
+}
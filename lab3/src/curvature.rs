@@ -0,0 +1,129 @@
+use image::{DynamicImage, Rgb, RgbImage};
+
+use crate::support::{Surface, SurfaceDerivatives};
+use crate::tessellation::Tessellation;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Coloring {
+    Plain,
+    Gaussian,
+    Mean,
+    PrincipalMin,
+    PrincipalMax,
+    NormalDeviation,
+    Checker,
+}
+
+impl Coloring {
+    pub const ALL: [Coloring; 7] = [
+        Coloring::Plain,
+        Coloring::Gaussian,
+        Coloring::Mean,
+        Coloring::PrincipalMin,
+        Coloring::PrincipalMax,
+        Coloring::NormalDeviation,
+        Coloring::Checker,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Coloring::Plain => "Plain",
+            Coloring::Gaussian => "Gaussian",
+            Coloring::Mean => "Mean",
+            Coloring::PrincipalMin => "Min principal",
+            Coloring::PrincipalMax => "Max principal",
+            Coloring::NormalDeviation => "Normal deviation",
+            Coloring::Checker => "UV checker",
+        }
+    }
+
+    // Colorings of scalar values, shown with the colormap and a legend
+    pub fn is_scalar(&self) -> bool {
+        !matches!(self, Coloring::Plain | Coloring::Checker)
+    }
+}
+
+pub struct Curvatures {
+    pub gaussian: f32,
+    pub mean: f32,
+    pub principal_min: f32,
+    pub principal_max: f32,
+}
+
+impl Curvatures {
+    // From the first (E, F, G) and second (L, M, N) fundamental forms
+    pub fn new(d: &SurfaceDerivatives) -> Self {
+        let normal = d.du.cross(&d.dw).normalize();
+
+        let (e, f, g) = (d.du.dot(&d.du), d.du.dot(&d.dw), d.dw.dot(&d.dw));
+        let (l, m, n) = (d.duu.dot(&normal), d.duw.dot(&normal), d.dww.dot(&normal));
+        let det = e * g - f * f;
+
+        let gaussian = (l * n - m * m) / det;
+        let mean = (e * n - 2.0 * f * m + g * l) / (2.0 * det);
+        // Rounding errors can make the discriminant slightly negative on umbilics
+        let discriminant = (mean * mean - gaussian).max(0.0).sqrt();
+
+        Self {
+            gaussian,
+            mean,
+            principal_min: mean - discriminant,
+            principal_max: mean + discriminant,
+        }
+    }
+}
+
+// Values of the coloring for every vertex of the tessellation
+pub fn coloring_values(surface: &dyn Surface, tessellation: &Tessellation, coloring: Coloring) -> Vec<f32> {
+    tessellation.uvs
+        .iter()
+        .zip(tessellation.normals.iter())
+        .map(|(uv, mesh_normal)| {
+            let derivatives = surface.derivatives(uv.x, uv.y);
+
+            match coloring {
+                Coloring::Plain | Coloring::Checker => 0.0,
+                Coloring::Gaussian => Curvatures::new(&derivatives).gaussian,
+                Coloring::Mean => Curvatures::new(&derivatives).mean,
+                Coloring::PrincipalMin => Curvatures::new(&derivatives).principal_min,
+                Coloring::PrincipalMax => Curvatures::new(&derivatives).principal_max,
+                // Angle in degrees between the analytic and the mesh normals
+                Coloring::NormalDeviation => {
+                    let normal = derivatives.du.cross(&derivatives.dw).normalize();
+                    normal.angle(mesh_normal).to_degrees()
+                }
+            }
+        })
+        .map(|value| if value.is_finite() { value } else { 0.0 })
+        .collect()
+}
+
+// Blue -> cyan -> green -> yellow -> red
+pub fn colormap(t: f32) -> (f32, f32, f32) {
+    let t = t.clamp(0.0, 1.0);
+    let r = (1.5 - (4.0 * t - 3.0).abs()).clamp(0.0, 1.0);
+    let g = (1.5 - (4.0 * t - 2.0).abs()).clamp(0.0, 1.0);
+    let b = (1.5 - (4.0 * t - 1.0).abs()).clamp(0.0, 1.0);
+
+    (r, g, b)
+}
+
+pub const COLORMAP_SIZE: u32 = 256;
+
+// Texture with the colormap along u, looked up by the vertex texture coordinates
+pub fn colormap_image() -> DynamicImage {
+    let image = RgbImage::from_fn(COLORMAP_SIZE, 1, |x, _| {
+        let (r, g, b) = colormap(x as f32 / (COLORMAP_SIZE - 1) as f32);
+        Rgb([(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8])
+    });
+
+    DynamicImage::ImageRgb8(image)
+}
+
+// Texture coordinate of a value inside the colormap texture, away from the texel borders
+pub fn colormap_uv(value: f32, min: f32, max: f32) -> f32 {
+    let t = if max - min > f32::EPSILON { (value - min) / (max - min) } else { 0.5 };
+    let half_texel = 0.5 / COLORMAP_SIZE as f32;
+
+    half_texel + t.clamp(0.0, 1.0) * (1.0 - 2.0 * half_texel)
+}
@@ -0,0 +1,117 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use nalgebra::Vector3;
+
+use crate::tessellation::Tessellation;
+
+#[derive(Clone, Copy)]
+pub enum MeshFormat {
+    Obj,
+    Stl,
+    Ply,
+}
+
+impl MeshFormat {
+    pub const ALL: [MeshFormat; 3] = [MeshFormat::Obj, MeshFormat::Stl, MeshFormat::Ply];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MeshFormat::Obj => "OBJ",
+            MeshFormat::Stl => "STL",
+            MeshFormat::Ply => "PLY",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MeshFormat::Obj => "obj",
+            MeshFormat::Stl => "stl",
+            MeshFormat::Ply => "ply",
+        }
+    }
+}
+
+pub fn export_mesh(path: &Path, format: MeshFormat, tessellation: &Tessellation) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    match format {
+        MeshFormat::Obj => write_obj(&mut out, tessellation)?,
+        MeshFormat::Stl => write_stl(&mut out, tessellation)?,
+        MeshFormat::Ply => write_ply(&mut out, tessellation)?,
+    }
+
+    out.flush()
+}
+
+// Wavefront OBJ with normals and (u, w) texture coordinates
+pub fn write_obj<W: Write>(out: &mut W, tessellation: &Tessellation) -> io::Result<()> {
+    writeln!(out, "# Surface {}x{}", tessellation.u_count, tessellation.w_count)?;
+
+    for v in tessellation.vertices.iter() {
+        writeln!(out, "v {} {} {}", v.x, v.y, v.z)?;
+    }
+    for uv in tessellation.uvs.iter() {
+        writeln!(out, "vt {} {}", uv.x, uv.y)?;
+    }
+    for n in tessellation.normals.iter() {
+        writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    // OBJ indices start from 1
+    for face in tessellation.faces() {
+        let [a, b, c] = face.map(|i| i + 1);
+        writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+
+    Ok(())
+}
+
+// Binary STL, facet normals are computed from the triangle itself
+pub fn write_stl<W: Write>(out: &mut W, tessellation: &Tessellation) -> io::Result<()> {
+    let faces = tessellation.faces();
+
+    let mut header = [0u8; 80];
+    let title = b"Surface";
+    header[..title.len()].copy_from_slice(title);
+    out.write_all(&header)?;
+    out.write_all(&(faces.len() as u32).to_le_bytes())?;
+
+    for face in faces {
+        let [a, b, c] = face.map(|i| tessellation.vertices[i]);
+        let normal = (b - a).cross(&(c - a)).try_normalize(f32::EPSILON).unwrap_or_else(Vector3::zeros);
+
+        for value in normal.iter().chain(a.iter()).chain(b.iter()).chain(c.iter()) {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        // Attribute byte count
+        out.write_all(&0u16.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+// ASCII PLY with normals and (u, w) texture coordinates
+pub fn write_ply<W: Write>(out: &mut W, tessellation: &Tessellation) -> io::Result<()> {
+    let faces = tessellation.faces();
+
+    writeln!(out, "ply")?;
+    writeln!(out, "format ascii 1.0")?;
+    writeln!(out, "element vertex {}", tessellation.vertices.len())?;
+    for property in ["x", "y", "z", "nx", "ny", "nz", "s", "t"] {
+        writeln!(out, "property float {}", property)?;
+    }
+    writeln!(out, "element face {}", faces.len())?;
+    writeln!(out, "property list uchar uint vertex_indices")?;
+    writeln!(out, "end_header")?;
+
+    for ((v, n), uv) in tessellation.vertices.iter().zip(tessellation.normals.iter()).zip(tessellation.uvs.iter()) {
+        writeln!(out, "{} {} {} {} {} {} {} {}", v.x, v.y, v.z, n.x, n.y, n.z, uv.x, uv.y)?;
+    }
+    for [a, b, c] in faces {
+        writeln!(out, "3 {} {} {}", a, b, c)?;
+    }
+
+    Ok(())
+}
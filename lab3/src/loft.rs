@@ -0,0 +1,138 @@
+use nalgebra::{Point3, Vector3};
+
+use crate::bezier::BezierCurve;
+use crate::support::{Surface, SurfaceDerivatives};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    CatmullRom,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 2] = [Interpolation::Linear, Interpolation::CatmullRom];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Interpolation::Linear => "Linear",
+            Interpolation::CatmullRom => "Catmull-Rom",
+        }
+    }
+}
+
+// Section curve with its first and second derivatives
+struct Section {
+    curves: [BezierCurve; 3],
+}
+
+impl Section {
+    fn new(control_points: &[Point3<f32>]) -> Self {
+        let curve = BezierCurve::new(control_points);
+        let first_derivative = curve.derivative();
+        let second_derivative = first_derivative.derivative();
+
+        Self { curves: [curve, first_derivative, second_derivative] }
+    }
+
+    // Value, first and second derivatives at u
+    fn evaluate(&self, u: f32) -> [Vector3<f32>; 3] {
+        self.curves.each_ref().map(|curve| curve.vector(u))
+    }
+}
+
+// Surface through N section curves: u runs along the sections, w across them.
+// Section k is passed at w = k / (N - 1).
+pub struct LoftSurface {
+    sections: Vec<Section>,
+    interpolation: Interpolation,
+}
+
+impl LoftSurface {
+    pub fn new(sections: &[Vec<Point3<f32>>], interpolation: Interpolation) -> Self {
+        assert!(sections.len() > 1, "Loft needs at least 2 sections");
+
+        Self {
+            sections: sections.iter().map(|control_points| Section::new(control_points)).collect(),
+            interpolation,
+        }
+    }
+
+    // Ruled surface between two curves: the generalisation of the bilinear surface
+    pub fn ruled(first: &[Point3<f32>], second: &[Point3<f32>]) -> Self {
+        Self::new(&[first.to_vec(), second.to_vec()], Interpolation::Linear)
+    }
+
+    // Section values, extrapolated linearly beyond the first and the last one
+    fn section(&self, index: isize, u: f32) -> [Vector3<f32>; 3] {
+        let last = self.sections.len() as isize - 1;
+
+        if index < 0 {
+            let [a, b] = [0, 1].map(|i| self.sections[i].evaluate(u));
+            [0, 1, 2].map(|d| a[d] * 2.0 - b[d])
+        } else if index > last {
+            let [a, b] = [last, last - 1].map(|i| self.sections[i as usize].evaluate(u));
+            [0, 1, 2].map(|d| a[d] * 2.0 - b[d])
+        } else {
+            self.sections[index as usize].evaluate(u)
+        }
+    }
+
+    // Sections involved at w with their weights for the value, the first and the second derivative along w
+    fn weights(&self, w: f32) -> Vec<(isize, [f32; 3])> {
+        let segments = self.sections.len() - 1;
+        let s = w * segments as f32;
+        let k = (s.floor() as usize).min(segments - 1) as isize;
+        let t = s - k as f32;
+        // d/dw = d/dt * segments
+        let n = segments as f32;
+
+        match self.interpolation {
+            Interpolation::Linear => vec![
+                (k, [1.0 - t, -n, 0.0]),
+                (k + 1, [t, n, 0.0]),
+            ],
+            Interpolation::CatmullRom => {
+                let (t2, t3) = (t * t, t * t * t);
+                vec![
+                    (k - 1, [0.5 * (-t3 + 2.0 * t2 - t), 0.5 * (-3.0 * t2 + 4.0 * t - 1.0) * n, 0.5 * (-6.0 * t + 4.0) * n * n]),
+                    (k, [0.5 * (3.0 * t3 - 5.0 * t2 + 2.0), 0.5 * (9.0 * t2 - 10.0 * t) * n, 0.5 * (18.0 * t - 10.0) * n * n]),
+                    (k + 1, [0.5 * (-3.0 * t3 + 4.0 * t2 + t), 0.5 * (-9.0 * t2 + 8.0 * t + 1.0) * n, 0.5 * (-18.0 * t + 8.0) * n * n]),
+                    (k + 2, [0.5 * (t3 - t2), 0.5 * (3.0 * t2 - 2.0 * t) * n, 0.5 * (6.0 * t - 2.0) * n * n]),
+                ]
+            }
+        }
+    }
+}
+
+impl Surface for LoftSurface {
+    fn point(&self, u: f32, w: f32) -> Point3<f32> {
+        let point: Vector3<f32> = self.weights(w)
+            .iter()
+            .map(|(index, weights)| self.section(*index, u)[0] * weights[0])
+            .sum();
+
+        Point3::from(point)
+    }
+
+    fn derivatives(&self, u: f32, w: f32) -> SurfaceDerivatives {
+        let mut derivatives = SurfaceDerivatives {
+            du: Vector3::zeros(),
+            dw: Vector3::zeros(),
+            duu: Vector3::zeros(),
+            duw: Vector3::zeros(),
+            dww: Vector3::zeros(),
+        };
+
+        for (index, [weight, d_weight, dd_weight]) in self.weights(w) {
+            let [c, dc, ddc] = self.section(index, u);
+
+            derivatives.du += dc * weight;
+            derivatives.duu += ddc * weight;
+            derivatives.dw += c * d_weight;
+            derivatives.duw += dc * d_weight;
+            derivatives.dww += c * dd_weight;
+        }
+
+        derivatives
+    }
+}
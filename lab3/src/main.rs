@@ -4,32 +4,242 @@ use kiss3d::scene::SceneNode;
 use kiss3d::window::Window;
 use kiss3d::camera::{FixedView};
 use kiss3d::conrod;
-use na::{Translation3, Point3, Vector3, UnitQuaternion};
+use kiss3d::resource::TextureManager;
+use kiss3d::event::{Action, WindowEvent};
+use na::{Translation3, Point2, Point3, Vector3, UnitQuaternion, Isometry3};
 
 use std::ops::{Add};
 use std::f32::consts::PI;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
 
 use conrod::{Borderable, Colorable, Sizeable, Positionable, Labelable, Widget, widget_ids, widget, UiCell};
 
 mod support;
+mod coons;
+mod tessellation;
+mod export;
+mod curvature;
+mod parametrization;
+mod bezier;
+mod sweep;
+mod loft;
+mod scene;
 use support::*;
+use coons::*;
+use tessellation::Tessellation;
+use export::*;
+use curvature::*;
+use parametrization::*;
+use sweep::*;
+use loft::*;
+use lab_common::history::{Command, History};
+use scene::{scene, Scene};
+use lab_common::scene_file::{self, SAVE_SCENE_KEY};
+use lab_common::screenshot::{timestamp, Screenshots};
 
 const UI_WIDTH_P: f64 = 150.;
 
-const AXE_LENGTH_N: f32 = 0.24;
+// kiss3d meshes use u16 indices, so a quad can't have more than 2^16 vertices
+const MAX_QUAD_POINTS_COUNT: usize = 256;
+
+const LEGEND_STEPS: usize = 16;
 
-const X_INIT_POS_N: f32 = 0.05;
-const Y_INIT_POS_N: f32 = -0.06;
-const Z_INIT_POS_N: f32 = -0.4;
+// Edits listed at the bottom of the rotation UI
+const HISTORY_LINES: usize = 10;
 
-const Y_INIT_ROT: f32 = -45.0 / 180.0 * PI;
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum SurfaceType {
+    Bilinear,
+    Coons,
+    Revolution,
+    Extrusion,
+    Sweep,
+    Ruled,
+    Loft,
+}
+
+impl SurfaceType {
+    const ALL: [SurfaceType; 7] = [
+        SurfaceType::Bilinear,
+        SurfaceType::Coons,
+        SurfaceType::Revolution,
+        SurfaceType::Extrusion,
+        SurfaceType::Sweep,
+        SurfaceType::Ruled,
+        SurfaceType::Loft,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SurfaceType::Bilinear => "Bilinear",
+            SurfaceType::Coons => "Coons",
+            SurfaceType::Revolution => "Revolution",
+            SurfaceType::Extrusion => "Extrusion",
+            SurfaceType::Sweep => "Sweep",
+            SurfaceType::Ruled => "Ruled",
+            SurfaceType::Loft => "Loft",
+        }
+    }
+}
+
+// Control point position and color
+type ControlPoint = (Point3<f32>, (f32, f32, f32));
+
+// Control point of a curve edited by the UI
+#[derive(Clone, PartialEq)]
+struct ControlPointSelection {
+    curve: usize,
+    point: usize,
+}
+
+// Profile and path of the surfaces built from a planar profile
+#[derive(Clone, PartialEq)]
+struct ProfileSettings {
+    kind: ProfileKind,
+    points: Vec<Point2<f32>>,
+    selected_point: usize,
+    axis: RevolutionAxis,
+    // degrees
+    angle: f32,
+    length: f32,
+    path: Vec<Point3<f32>>,
+    selected_path_point: usize,
+    frame_kind: FrameKind,
+}
+
+impl ProfileSettings {
+    fn profile(&self) -> ProfileCurve {
+        ProfileCurve::new(self.kind, &self.points)
+    }
+}
+
+// Section curves of the loft
+#[derive(Clone, PartialEq)]
+struct LoftSettings {
+    interpolation: Interpolation,
+    sections: Vec<Vec<Point3<f32>>>,
+    selection: ControlPointSelection,
+}
+
+// Number of surface points along each parameter
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Resolution {
+    u_count: usize,
+    w_count: usize,
+}
+
+// Everything the UI edits that undo and redo bring back, selections included
+#[derive(Clone, PartialEq)]
+struct Model {
+    rotation: Rotation,
+    surface_type: SurfaceType,
+    vertices: Vec<Point3<f32>>,
+    boundaries: [Vec<Point3<f32>>; 4],
+    ruled_curves: Vec<Vec<Point3<f32>>>,
+    loft_settings: LoftSettings,
+    profile_settings: ProfileSettings,
+    resolution: Resolution,
+    coloring: Coloring,
+    isoline_settings: IsolineSettings,
+}
+
+// Part of the model an edit changed, selecting another point alone isn't an edit
+#[derive(Clone, Copy, PartialEq)]
+enum ModelPart {
+    Rotation,
+    SurfaceType,
+    Corners,
+    Boundaries,
+    RuledCurves,
+    Loft,
+    Profile,
+    Resolution,
+    Coloring,
+    Isolines,
+}
+
+impl ModelPart {
+    fn changed(from: &Model, to: &Model) -> Option<ModelPart> {
+        let profile = |settings: &ProfileSettings| ProfileSettings { selected_point: 0, selected_path_point: 0, ..settings.clone() };
+
+        if from.rotation != to.rotation {
+            Some(ModelPart::Rotation)
+        } else if from.surface_type != to.surface_type {
+            Some(ModelPart::SurfaceType)
+        } else if from.vertices != to.vertices {
+            Some(ModelPart::Corners)
+        } else if from.boundaries != to.boundaries {
+            Some(ModelPart::Boundaries)
+        } else if from.ruled_curves != to.ruled_curves {
+            Some(ModelPart::RuledCurves)
+        } else if from.loft_settings.interpolation != to.loft_settings.interpolation || from.loft_settings.sections != to.loft_settings.sections {
+            Some(ModelPart::Loft)
+        } else if profile(&from.profile_settings) != profile(&to.profile_settings) {
+            Some(ModelPart::Profile)
+        } else if from.resolution != to.resolution {
+            Some(ModelPart::Resolution)
+        } else if from.coloring != to.coloring {
+            Some(ModelPart::Coloring)
+        } else if from.isoline_settings != to.isoline_settings {
+            Some(ModelPart::Isolines)
+        } else {
+            None
+        }
+    }
+}
+
+// Edit of one part of the model, kept as the whole model before and after it
+struct Edit {
+    part: ModelPart,
+    from: Model,
+    to: Model,
+}
 
-const CIRCLE_COLORS: [(f32, f32, f32); 4] = [
-    (1.0, 0.0, 0.0),
-    (0.0, 1.0, 0.0),
-    (0.0, 0.0, 1.0),
-    (1.0, 1.0, 0.0),
-];
+impl Command for Edit {
+    type Target = Model;
+
+    fn apply(&self, model: &mut Model) {
+        *model = self.to.clone();
+    }
+
+    fn revert(&self, model: &mut Model) {
+        *model = self.from.clone();
+    }
+
+    fn label(&self) -> String {
+        let to = &self.to;
+        match self.part {
+            ModelPart::Rotation => format!("Rotate {:.1}° {:.1}° {:.1}°", to.rotation.x_angle, to.rotation.y_angle, to.rotation.z_angle),
+            ModelPart::SurfaceType => format!("Surface {}", to.surface_type.label()),
+            ModelPart::Corners => "Bilinear corners".to_string(),
+            ModelPart::Boundaries => "Coons boundaries".to_string(),
+            ModelPart::RuledCurves => "Ruled curves".to_string(),
+            ModelPart::Loft => format!("Loft, {} sections", to.loft_settings.sections.len()),
+            ModelPart::Profile => "Profile".to_string(),
+            ModelPart::Resolution => format!("Mesh {} x {}", to.resolution.u_count, to.resolution.w_count),
+            ModelPart::Coloring => format!("Coloring {}", to.coloring.label()),
+            ModelPart::Isolines => "Isolines".to_string(),
+        }
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        if next.part != self.part {
+            return false;
+        }
+        self.to = next.to.clone();
+        true
+    }
+}
+
+// Mesh export settings, independent of the on-screen tessellation
+struct ExportSettings {
+    u_count: usize,
+    w_count: usize,
+    status: String,
+}
 
 widget_ids! {
     pub struct Ids {
@@ -48,6 +258,41 @@ widget_ids! {
         x3_dialog, y3_dialog, z3_dialog,
         point4_canvas,
         x4_dialog, y4_dialog, z4_dialog,
+        surface_type_list,
+        curve_dialer,
+        control_point_dialer,
+        control_point_canvas,
+        xc_dialog, yc_dialog, zc_dialog,
+        corner_warnings,
+        quad_u_dialer,
+        quad_w_dialer,
+        export_u_dialer,
+        export_w_dialer,
+        export_obj_button,
+        export_stl_button,
+        export_ply_button,
+        export_status,
+        coloring_list,
+        legend_colors[],
+        legend_min,
+        legend_max,
+        u_lines_toggle,
+        w_lines_toggle,
+        isoline_spacing_dialer,
+        profile_kind_list,
+        profile_point_dialer,
+        profile_point_canvas,
+        xp_dialog, yp_dialog,
+        axis_list,
+        angle_dialer,
+        length_dialer,
+        frame_list,
+        path_point_dialer,
+        path_point_canvas,
+        xs_dialog, ys_dialog, zs_dialog,
+        interpolation_list,
+        sections_dialer,
+        history_text,
     }
 }
 
@@ -56,6 +301,7 @@ fn draw_rotation_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
     let slider_h_p = 180.0;
     let sliders_gap_p = 33.0;
     let font_size = 11;
+    let [min_angle, max_angle] = scene().ui.rotation_range;
 
     widget::Canvas::new()
         .align_left()
@@ -64,7 +310,7 @@ fn draw_rotation_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
         .border_rgb(1.0, 1.0, 1.0)
         .set(ids.canvas, ui_cell);
 
-    for v in widget::Slider::new(rot.x_angle, -180.0, 180.0)
+    if let Some(v) = widget::Slider::new(rot.x_angle, min_angle, max_angle)
         .label("X")
         .w(slider_w_p)
         .h(slider_h_p)
@@ -80,7 +326,7 @@ fn draw_rotation_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
         .align_middle_x_of(ids.slider_x)
         .set(ids.angle_x, ui_cell);
 
-    for v in widget::Slider::new(rot.y_angle, -180.0, 180.0)
+    if let Some(v) = widget::Slider::new(rot.y_angle, min_angle, max_angle)
         .label("Y")
         .w(slider_w_p)
         .h(slider_h_p)
@@ -96,7 +342,7 @@ fn draw_rotation_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
         .align_middle_x_of(ids.slider_y)
         .set(ids.angle_y, ui_cell);
 
-    for v in widget::Slider::new(rot.z_angle, -180.0, 180.0)
+    if let Some(v) = widget::Slider::new(rot.z_angle, min_angle, max_angle)
         .label("Z")
         .w(slider_w_p)
         .h(slider_h_p)
@@ -113,15 +359,69 @@ fn draw_rotation_ui(ui_cell: &mut UiCell, ids: &Ids, rot: &mut Rotation) {
         .set(ids.angle_z, ui_cell);
 }
 
-fn draw_points_ui(ui_cell: &mut UiCell, ids: &Ids, vertices: &mut Vec<Point3<f32>>) {
-    let point_canvas_height = 35.0;
-    let point_canvas_width = 250.0;
-    let point_canvas_gap = 15.0;
+fn draw_surface_type_ui(ui_cell: &mut UiCell, ids: &Ids, surface_type: &mut SurfaceType) {
+    let list_width = 250.0;
+    let list_height = 25.0;
+    let list_gap = 15.0;
 
+    let labels: Vec<&str> = SurfaceType::ALL.iter().map(|t| t.label()).collect();
+    let selected = SurfaceType::ALL.iter().position(|t| t == surface_type);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(list_width, list_height)
+        .top_left_with_margin_on(ids.canvas, list_gap)
+        .set(ids.surface_type_list, ui_cell)
+    {
+        *surface_type = SurfaceType::ALL[index];
+    }
+}
+
+fn draw_history_ui(ui_cell: &mut UiCell, ids: &Ids, history: &History<Edit>) {
+    widget::Text::new(&history.text(HISTORY_LINES))
+        .font_size(11)
+        .bottom_left_with_margin_on(ids.canvas, 10.0)
+        .set(ids.history_text, ui_cell);
+}
+
+fn draw_point_dialers(ui_cell: &mut UiCell, canvas_id: widget::Id, dialer_ids: &[widget::Id], coordinates: &mut [f32]) {
     let number_dialer_width = 65.0;
     let number_dialer_height = 25.0;
     let number_dialer_gap = 15.0;
 
+    let dialer_labels = ["X", "Y", "Z"];
+
+    let to_dialer_format = |value: f32| {
+        (value + scene().axes.length / 2.0) / scene().axes.length * 100.0
+    };
+
+    let from_dialer_format = |value: f32| {
+        value / 100.0 * scene().axes.length - scene().axes.length / 2.0
+    };
+
+    for (dialer_index, coordinate) in coordinates.iter_mut().enumerate() {
+        let mut dialer = widget::NumberDialer::new(to_dialer_format(*coordinate), 0.0, 100.0, 0)
+            .w_h(number_dialer_width, number_dialer_height)
+            .border_rgb(1.0, 1.0, 1.0)
+            .label(dialer_labels[dialer_index]);
+
+        if dialer_index == 0 {
+            dialer = dialer.mid_left_with_margin_on(canvas_id, 10.0);
+        } else {
+            let prev_dialer_id = dialer_ids[dialer_index - 1];
+            dialer = dialer.right_from(prev_dialer_id, number_dialer_gap);
+        }
+
+        if let Some(value) = dialer.set(dialer_ids[dialer_index], ui_cell) {
+            *coordinate = from_dialer_format(value);
+        }
+    }
+}
+
+fn draw_points_ui(ui_cell: &mut UiCell, ids: &Ids, vertices: &mut [Point3<f32>]) {
+    let point_canvas_height = 35.0;
+    let point_canvas_width = 250.0;
+    let point_canvas_gap = 15.0;
+
     let canvas_ids = [
         ids.point1_canvas,
         ids.point2_canvas,
@@ -136,20 +436,16 @@ fn draw_points_ui(ui_cell: &mut UiCell, ids: &Ids, vertices: &mut Vec<Point3<f32
         [ids.x4_dialog, ids.y4_dialog, ids.z4_dialog],
     ];
 
-    let dialer_labels = ["X", "Y", "Z"];
-
     for (canvas_index, canvas_id) in canvas_ids.iter().enumerate() {
-        let canvas_vertice = &mut vertices[canvas_index];
-
         let canvas_widget = widget::Canvas::new()
             .h(point_canvas_height)
             .w(point_canvas_width)
             .rgb(1.0, 1.0, 1.0)
-            .border_rgb(CIRCLE_COLORS[canvas_index].0, CIRCLE_COLORS[canvas_index].1, CIRCLE_COLORS[canvas_index].2);
+            .border_rgb(curve_color(canvas_index).0, curve_color(canvas_index).1, curve_color(canvas_index).2);
             
         if canvas_index == 0 {
             canvas_widget
-                .top_left_with_margin_on(ids.canvas, point_canvas_gap)
+                .down_from(ids.surface_type_list, point_canvas_gap)
                 .set(*canvas_id, ui_cell);
         } else {
             let prev_canvas_id = canvas_ids[canvas_index - 1];
@@ -158,53 +454,440 @@ fn draw_points_ui(ui_cell: &mut UiCell, ids: &Ids, vertices: &mut Vec<Point3<f32
                 .set(*canvas_id, ui_cell);
         }
 
-        for dialer_index in 0..dialer_labels.len() {
-            let to_dialer_format = |value: f32| {
-                (value + AXE_LENGTH_N / 2.0) / AXE_LENGTH_N * 100.0
-            };
+        draw_point_dialers(ui_cell, *canvas_id, &dialer_ids[canvas_index], vertices[canvas_index].coords.as_mut_slice());
+    }
+}
 
-            let dialer_value = match dialer_labels[dialer_index] {
-                "X" => to_dialer_format(canvas_vertice.x),
-                "Y" => to_dialer_format(canvas_vertice.y),
-                "Z" => to_dialer_format(canvas_vertice.z),
-                _ => 0.0
-            };
+// Selected curve and control point with its coordinates, below the `anchor` widget
+fn draw_curves_ui(ui_cell: &mut UiCell, ids: &Ids, curves: &mut [Vec<Point3<f32>>], selection: &mut ControlPointSelection, anchor: widget::Id) {
+    let point_canvas_height = 35.0;
+    let point_canvas_width = 250.0;
+    let point_canvas_gap = 15.0;
 
-            let mut dialer = widget::NumberDialer::new(dialer_value, 0.0, 100.0, 0)
-                .w_h(number_dialer_width, number_dialer_height)
-                .border_rgb(1.0, 1.0, 1.0)
-                .label(dialer_labels[dialer_index]);
+    let number_dialer_width = 115.0;
+    let number_dialer_height = 25.0;
+    let number_dialer_gap = 20.0;
 
-            let from_dialer_format = |value: f32| {
-                value / 100.0 * AXE_LENGTH_N - AXE_LENGTH_N / 2.0
-            };
+    if let Some(value) = widget::NumberDialer::new(selection.curve as f32 + 1.0, 1.0, curves.len() as f32, 0)
+        .w_h(number_dialer_width, number_dialer_height)
+        .down_from(anchor, point_canvas_gap)
+        .align_left_of(anchor)
+        .label("Curve")
+        .set(ids.curve_dialer, ui_cell)
+    {
+        selection.curve = value as usize - 1;
+        selection.point = selection.point.min(curves[selection.curve].len() - 1);
+    }
+
+    let curve = &mut curves[selection.curve];
+
+    if let Some(value) = widget::NumberDialer::new(selection.point as f32 + 1.0, 1.0, curve.len() as f32, 0)
+        .w_h(number_dialer_width, number_dialer_height)
+        .right_from(ids.curve_dialer, number_dialer_gap)
+        .label("Point")
+        .set(ids.control_point_dialer, ui_cell)
+    {
+        selection.point = value as usize - 1;
+    }
+
+    let color = curve_color(selection.curve);
+    widget::Canvas::new()
+        .h(point_canvas_height)
+        .w(point_canvas_width)
+        .rgb(1.0, 1.0, 1.0)
+        .border_rgb(color.0, color.1, color.2)
+        .down_from(ids.curve_dialer, point_canvas_gap)
+        .set(ids.control_point_canvas, ui_cell);
+
+    let dialer_ids = [ids.xc_dialog, ids.yc_dialog, ids.zc_dialog];
+    draw_point_dialers(ui_cell, ids.control_point_canvas, &dialer_ids, curve[selection.point].coords.as_mut_slice());
+}
+
+fn draw_coons_ui(ui_cell: &mut UiCell, ids: &Ids, boundaries: &mut [Vec<Point3<f32>>], selection: &mut ControlPointSelection, mismatches: &[CornerMismatch]) {
+    let warnings_width = 250.0;
+    let warnings_gap = 15.0;
+
+    draw_curves_ui(ui_cell, ids, boundaries, selection, ids.surface_type_list);
+
+    let warnings: Vec<String> = mismatches
+        .iter()
+        .map(|m| format!("Corner ({}, {}) is open by {:.4}", m.u, m.w, m.distance))
+        .collect();
 
-            if dialer_index == 0 {
-                dialer = dialer.mid_left_with_margin_on(*canvas_id, 10.0);
-            } else {
-                let prev_dialer_id = dialer_ids[canvas_index][dialer_index - 1];
-                dialer = dialer.right_from(prev_dialer_id, number_dialer_gap);
+    widget::Text::new(&warnings.join("\n"))
+        .font_size(11)
+        .w(warnings_width)
+        .rgb(0.8, 0.0, 0.0)
+        .down_from(ids.control_point_canvas, warnings_gap)
+        .set(ids.corner_warnings, ui_cell);
+}
+
+fn draw_loft_ui(ui_cell: &mut UiCell, ids: &Ids, settings: &mut LoftSettings) {
+    let widget_width = 115.0;
+    let widget_height = 25.0;
+    let widgets_gap = 20.0;
+    let gap = 15.0;
+    let max_sections_count = 8.0;
+
+    let labels: Vec<&str> = Interpolation::ALL.iter().map(|i| i.label()).collect();
+    let selected = Interpolation::ALL.iter().position(|i| *i == settings.interpolation);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(widget_width, widget_height)
+        .down_from(ids.surface_type_list, gap)
+        .set(ids.interpolation_list, ui_cell)
+    {
+        settings.interpolation = Interpolation::ALL[index];
+    }
+
+    if let Some(value) = widget::NumberDialer::new(settings.sections.len() as f32, 2.0, max_sections_count, 0)
+        .w_h(widget_width, widget_height)
+        .right_from(ids.interpolation_list, widgets_gap)
+        .label("Sections")
+        .set(ids.sections_dialer, ui_cell)
+    {
+        resize_sections(&mut settings.sections, value as usize);
+        settings.selection.curve = settings.selection.curve.min(settings.sections.len() - 1);
+    }
+
+    draw_curves_ui(ui_cell, ids, &mut settings.sections, &mut settings.selection, ids.interpolation_list);
+}
+
+// New sections continue the last two ones
+fn resize_sections(sections: &mut Vec<Vec<Point3<f32>>>, count: usize) {
+    while sections.len() < count {
+        let last = &sections[sections.len() - 1];
+        let previous = &sections[sections.len() - 2];
+        let next = last
+            .iter()
+            .zip(previous.iter())
+            .map(|(l, p)| l + (l - p))
+            .collect();
+        sections.push(next);
+    }
+    sections.truncate(count);
+}
+
+fn draw_profile_ui(ui_cell: &mut UiCell, ids: &Ids, surface_type: SurfaceType, settings: &mut ProfileSettings) {
+    let point_canvas_height = 35.0;
+    let point_canvas_width = 250.0;
+    let gap = 15.0;
+
+    let widget_width = 115.0;
+    let widget_height = 25.0;
+    let widgets_gap = 20.0;
+
+    let kind_labels: Vec<&str> = ProfileKind::ALL.iter().map(|k| k.label()).collect();
+    let selected_kind = ProfileKind::ALL.iter().position(|k| *k == settings.kind);
+
+    if let Some(index) = widget::DropDownList::new(&kind_labels, selected_kind)
+        .w_h(widget_width, widget_height)
+        .down_from(ids.surface_type_list, gap)
+        .set(ids.profile_kind_list, ui_cell)
+    {
+        settings.kind = ProfileKind::ALL[index];
+    }
+
+    if let Some(value) = widget::NumberDialer::new(settings.selected_point as f32 + 1.0, 1.0, settings.points.len() as f32, 0)
+        .w_h(widget_width, widget_height)
+        .right_from(ids.profile_kind_list, widgets_gap)
+        .label("Point")
+        .set(ids.profile_point_dialer, ui_cell)
+    {
+        settings.selected_point = value as usize - 1;
+    }
+
+    widget::Canvas::new()
+        .h(point_canvas_height)
+        .w(point_canvas_width)
+        .rgb(1.0, 1.0, 1.0)
+        .border_rgb(curve_color(0).0, curve_color(0).1, curve_color(0).2)
+        .down_from(ids.profile_kind_list, gap)
+        .set(ids.profile_point_canvas, ui_cell);
+
+    let profile_point = &mut settings.points[settings.selected_point];
+    draw_point_dialers(ui_cell, ids.profile_point_canvas, &[ids.xp_dialog, ids.yp_dialog], profile_point.coords.as_mut_slice());
+
+    match surface_type {
+        SurfaceType::Revolution => {
+            let axis_labels: Vec<&str> = RevolutionAxis::ALL.iter().map(|a| a.label()).collect();
+            let selected_axis = RevolutionAxis::ALL.iter().position(|a| *a == settings.axis);
+
+            if let Some(index) = widget::DropDownList::new(&axis_labels, selected_axis)
+                .w_h(widget_width, widget_height)
+                .down_from(ids.profile_point_canvas, gap)
+                .set(ids.axis_list, ui_cell)
+            {
+                settings.axis = RevolutionAxis::ALL[index];
             }
 
-            for value in dialer.set(dialer_ids[canvas_index][dialer_index], ui_cell) {
-                match dialer_labels[dialer_index] {
-                    "X" => canvas_vertice.x = from_dialer_format(value),
-                    "Y" => canvas_vertice.y = from_dialer_format(value),
-                    "Z" => canvas_vertice.z = from_dialer_format(value),
-                    _ => {}
-                }
+            if let Some(value) = widget::NumberDialer::new(settings.angle, 0.0, 360.0, 0)
+                .w_h(widget_width, widget_height)
+                .right_from(ids.axis_list, widgets_gap)
+                .label("Angle")
+                .set(ids.angle_dialer, ui_cell)
+            {
+                settings.angle = value;
+            }
+        },
+        SurfaceType::Extrusion => {
+            if let Some(value) = widget::NumberDialer::new(settings.length, 0.0, scene().axes.length, 3)
+                .w_h(widget_width, widget_height)
+                .down_from(ids.profile_point_canvas, gap)
+                .label("Length")
+                .set(ids.length_dialer, ui_cell)
+            {
+                settings.length = value;
+            }
+        },
+        SurfaceType::Sweep => {
+            let frame_labels: Vec<&str> = FrameKind::ALL.iter().map(|f| f.label()).collect();
+            let selected_frame = FrameKind::ALL.iter().position(|f| *f == settings.frame_kind);
+
+            if let Some(index) = widget::DropDownList::new(&frame_labels, selected_frame)
+                .w_h(widget_width, widget_height)
+                .down_from(ids.profile_point_canvas, gap)
+                .set(ids.frame_list, ui_cell)
+            {
+                settings.frame_kind = FrameKind::ALL[index];
+            }
+
+            if let Some(value) = widget::NumberDialer::new(settings.selected_path_point as f32 + 1.0, 1.0, settings.path.len() as f32, 0)
+                .w_h(widget_width, widget_height)
+                .right_from(ids.frame_list, widgets_gap)
+                .label("Path point")
+                .set(ids.path_point_dialer, ui_cell)
+            {
+                settings.selected_path_point = value as usize - 1;
+            }
+
+            widget::Canvas::new()
+                .h(point_canvas_height)
+                .w(point_canvas_width)
+                .rgb(1.0, 1.0, 1.0)
+                .border_rgb(curve_color(0).0, curve_color(0).1, curve_color(0).2)
+                .down_from(ids.frame_list, gap)
+                .set(ids.path_point_canvas, ui_cell);
+
+            let path_point = &mut settings.path[settings.selected_path_point];
+            draw_point_dialers(ui_cell, ids.path_point_canvas, &[ids.xs_dialog, ids.ys_dialog, ids.zs_dialog], path_point.coords.as_mut_slice());
+        },
+        _ => {}
+    }
+}
+
+fn draw_resolution_ui(ui_cell: &mut UiCell, ids: &Ids, resolution: &mut Resolution) {
+    let number_dialer_width = 130.0;
+    let number_dialer_height = 25.0;
+    let gap = 15.0;
+
+    if let Some(value) = widget::NumberDialer::new(resolution.u_count as f32, 2.0, MAX_QUAD_POINTS_COUNT as f32, 0)
+        .w_h(number_dialer_width, number_dialer_height)
+        .top_right_with_margin(gap)
+        .label("Mesh U")
+        .set(ids.quad_u_dialer, ui_cell)
+    {
+        resolution.u_count = value as usize;
+    }
+
+    if let Some(value) = widget::NumberDialer::new(resolution.w_count as f32, 2.0, MAX_QUAD_POINTS_COUNT as f32, 0)
+        .w_h(number_dialer_width, number_dialer_height)
+        .down_from(ids.quad_u_dialer, gap / 2.0)
+        .label("Mesh W")
+        .set(ids.quad_w_dialer, ui_cell)
+    {
+        resolution.w_count = value as usize;
+    }
+}
+
+fn draw_export_ui(ui_cell: &mut UiCell, ids: &Ids, settings: &mut ExportSettings) -> Option<MeshFormat> {
+    let number_dialer_width = 130.0;
+    let number_dialer_height = 25.0;
+    let button_width = 40.0;
+    let gap = 15.0;
+    let max_points_count = 1000.0;
+
+    if let Some(value) = widget::NumberDialer::new(settings.u_count as f32, 2.0, max_points_count, 0)
+        .w_h(number_dialer_width, number_dialer_height)
+        .down_from(ids.quad_w_dialer, gap)
+        .label("Export U")
+        .set(ids.export_u_dialer, ui_cell)
+    {
+        settings.u_count = value as usize;
+    }
+
+    if let Some(value) = widget::NumberDialer::new(settings.w_count as f32, 2.0, max_points_count, 0)
+        .w_h(number_dialer_width, number_dialer_height)
+        .down_from(ids.export_u_dialer, gap / 2.0)
+        .label("Export W")
+        .set(ids.export_w_dialer, ui_cell)
+    {
+        settings.w_count = value as usize;
+    }
+
+    let button_ids = [ids.export_obj_button, ids.export_stl_button, ids.export_ply_button];
+    let mut clicked_format = None;
+
+    for (index, format) in MeshFormat::ALL.iter().enumerate() {
+        let mut button = widget::Button::new()
+            .w_h(button_width, number_dialer_height)
+            .label(format.label());
+
+        if index == 0 {
+            button = button.down_from(ids.export_w_dialer, gap / 2.0);
+        } else {
+            button = button.right_from(button_ids[index - 1], (number_dialer_width - 3.0 * button_width) / 2.0);
+        }
+
+        if button.set(button_ids[index], ui_cell).was_clicked() {
+            clicked_format = Some(*format);
+        }
+    }
+
+    widget::Text::new(&settings.status)
+        .font_size(11)
+        .w(number_dialer_width)
+        .down_from(ids.export_obj_button, gap / 2.0)
+        .set(ids.export_status, ui_cell);
+
+    clicked_format
+}
+
+fn draw_coloring_ui(ui_cell: &mut UiCell, ids: &Ids, coloring: &mut Coloring, range: (f32, f32)) {
+    let list_width = 130.0;
+    let list_height = 25.0;
+    let legend_step_width = 20.0;
+    let legend_step_height = 8.0;
+    let gap = 15.0;
+    let font_size = 11;
+
+    let labels: Vec<&str> = Coloring::ALL.iter().map(|c| c.label()).collect();
+    let selected = Coloring::ALL.iter().position(|c| c == coloring);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(list_width, list_height)
+        .bottom_right_with_margin(gap)
+        .set(ids.coloring_list, ui_cell)
+    {
+        *coloring = Coloring::ALL[index];
+    }
+
+    if !coloring.is_scalar() {
+        return;
+    }
+
+    // Color bar from min (bottom) to max (top)
+    for (step, legend_id) in ids.legend_colors.iter().enumerate() {
+        let (r, g, b) = colormap(step as f32 / (LEGEND_STEPS - 1) as f32);
+        let rectangle = widget::Rectangle::fill_with([legend_step_width, legend_step_height], conrod::color::rgb(r, g, b));
+
+        if step == 0 {
+            rectangle
+                .up_from(ids.coloring_list, gap)
+                .align_right_of(ids.coloring_list)
+                .set(*legend_id, ui_cell);
+        } else {
+            rectangle
+                .up_from(ids.legend_colors[step - 1], 0.0)
+                .set(*legend_id, ui_cell);
+        }
+    }
+
+    widget::Text::new(&format!("{:.3}", range.0))
+        .font_size(font_size)
+        .left_from(ids.legend_colors[0], 5.0)
+        .set(ids.legend_min, ui_cell);
+
+    widget::Text::new(&format!("{:.3}", range.1))
+        .font_size(font_size)
+        .left_from(ids.legend_colors[LEGEND_STEPS - 1], 5.0)
+        .set(ids.legend_max, ui_cell);
+}
+
+fn draw_isolines_ui(ui_cell: &mut UiCell, ids: &Ids, settings: &mut IsolineSettings) {
+    let number_dialer_width = 130.0;
+    let toggle_width = 60.0;
+    let widget_height = 25.0;
+    let gap = 15.0;
+
+    if let Some(value) = widget::Toggle::new(settings.u_lines)
+        .w_h(toggle_width, widget_height)
+        .label("U lines")
+        .down_from(ids.export_status, gap)
+        .align_left_of(ids.export_u_dialer)
+        .set(ids.u_lines_toggle, ui_cell)
+        .last()
+    {
+        settings.u_lines = value;
+    }
+
+    if let Some(value) = widget::Toggle::new(settings.w_lines)
+        .w_h(toggle_width, widget_height)
+        .label("W lines")
+        .right_from(ids.u_lines_toggle, number_dialer_width - 2.0 * toggle_width)
+        .set(ids.w_lines_toggle, ui_cell)
+        .last()
+    {
+        settings.w_lines = value;
+    }
+
+    if let Some(value) = widget::NumberDialer::new(settings.spacing, 0.02, 0.5, 2)
+        .w_h(number_dialer_width, widget_height)
+        .down_from(ids.u_lines_toggle, gap / 2.0)
+        .label("Spacing")
+        .set(ids.isoline_spacing_dialer, ui_cell)
+    {
+        settings.spacing = value;
+    }
+}
+
+fn draw_isolines(window: &mut Window, surface: &dyn Surface, settings: &IsolineSettings, transformation: &Isometry3<f32>) {
+    let color = Point3::from(scene().colors.isolines);
+
+    let mut parameters = vec![];
+    if settings.u_lines {
+        parameters.push(Isoparameter::U);
+    }
+    if settings.w_lines {
+        parameters.push(Isoparameter::W);
+    }
+
+    window.set_line_width(1.0);
+    for parameter in parameters {
+        for line in isolines(surface, parameter, settings.spacing) {
+            for segment in line.windows(2) {
+                window.draw_line(
+                    &transformation.transform_point(&segment[0]),
+                    &transformation.transform_point(&segment[1]),
+                    &color
+                );
             }
         }
     }
 }
 
+fn draw_control_polygons(window: &mut Window, polygons: &[Vec<Point3<f32>>], transformation: &Isometry3<f32>) {
+    window.set_line_width(1.0);
+    for (curve_index, curve) in polygons.iter().enumerate() {
+        let (r, g, b) = curve_color(curve_index);
+        let color = Point3::new(r, g, b);
+        for segment in curve.windows(2) {
+            window.draw_line(
+                &transformation.transform_point(&segment[0]),
+                &transformation.transform_point(&segment[1]),
+                &color
+            );
+        }
+    }
+}
+
 fn draw_axes(window: &mut Window) {
-    let color = Point3::new(0.0, 0.0, 0.0);
-    let init_shift = Vector3::new(X_INIT_POS_N, Y_INIT_POS_N, Z_INIT_POS_N);
-    let init_rot = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), Y_INIT_ROT);
+    let color = Point3::from(scene().axes.color);
+    let init_shift = Vector3::from(scene().camera.position);
+    let init_rot = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), scene().camera.y_rotation / 180.0 * PI);
 
     // Axes
-    let half_axe = AXE_LENGTH_N / 2.0;
+    let half_axe = scene().axes.length / 2.0;
 
     window.set_line_width(1.0);
     window.draw_line(
@@ -283,72 +966,321 @@ fn draw_axes(window: &mut Window) {
     );
 }
 
-fn move_points(points_spheres: &mut Vec<SceneNode>, vertices: &Vec<Point3<f32>>) {
+// The quad vertices follow the Tessellation layout: u along the rows, w across them
+fn add_surface_quad(group: &mut SceneNode, resolution: Resolution) -> SceneNode {
+    let mut quad = group.add_quad(1.0, 1.0, resolution.u_count - 1, resolution.w_count - 1);
+    let [r, g, b] = scene().colors.surface;
+    quad.set_color(r, g, b);
+
+    quad
+}
+
+fn move_points(points_spheres: &mut [SceneNode], vertices: &[Point3<f32>]) {
     for (sphere, vertice) in points_spheres.iter_mut().zip(vertices.iter()) {
         sphere.set_local_translation(Translation3::new(vertice.x, vertice.y, vertice.z));
     }
 }
 
+fn add_points_spheres(group: &mut SceneNode, control_points: &[ControlPoint]) -> Vec<SceneNode> {
+    let radius = 0.004;
+    let mut points_spheres = <Vec<SceneNode>>::with_capacity(control_points.len());
+    for (_, color) in control_points.iter() {
+        let mut sphere = group.add_sphere(radius);
+        sphere.set_color(color.0, color.1, color.2);
+        points_spheres.push(sphere);
+    }
+
+    points_spheres
+}
+
+// Control polygons of the curves defining the surface
+fn control_polygons(surface_type: SurfaceType, boundaries: &[Vec<Point3<f32>>], ruled_curves: &[Vec<Point3<f32>>], loft_settings: &LoftSettings, profile_settings: &ProfileSettings) -> Vec<Vec<Point3<f32>>> {
+    match surface_type {
+        SurfaceType::Bilinear => vec![],
+        SurfaceType::Coons => boundaries.to_vec(),
+        SurfaceType::Ruled => ruled_curves.to_vec(),
+        SurfaceType::Loft => loft_settings.sections.clone(),
+        SurfaceType::Revolution | SurfaceType::Extrusion => vec![
+            profile_settings.points.iter().map(|p| Point3::new(p.x, p.y, 0.0)).collect()
+        ],
+        SurfaceType::Sweep => vec![profile_settings.path.clone()],
+    }
+}
+
+fn curve_color(curve_index: usize) -> (f32, f32, f32) {
+    let colors = &scene().colors.control_points;
+    let [r, g, b] = colors[curve_index % colors.len()];
+    (r, g, b)
+}
+
+fn control_points(surface_type: SurfaceType, vertices: &[Point3<f32>], boundaries: &[Vec<Point3<f32>>], ruled_curves: &[Vec<Point3<f32>>], loft_settings: &LoftSettings, profile_settings: &ProfileSettings) -> Vec<ControlPoint> {
+    match surface_type {
+        SurfaceType::Bilinear => vertices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (*v, curve_color(i)))
+            .collect(),
+        SurfaceType::Coons | SurfaceType::Ruled | SurfaceType::Loft => control_polygons(surface_type, boundaries, ruled_curves, loft_settings, profile_settings)
+            .iter()
+            .enumerate()
+            .flat_map(|(i, curve)| curve.iter().map(move |v| (*v, curve_color(i))))
+            .collect(),
+        SurfaceType::Revolution | SurfaceType::Extrusion => profile_settings.points
+            .iter()
+            .map(|p| (Point3::new(p.x, p.y, 0.0), curve_color(0)))
+            .collect(),
+        SurfaceType::Sweep => profile_settings.path
+            .iter()
+            .map(|p| (*p, curve_color(0)))
+            .collect(),
+    }
+}
+
 fn main() {
+    // Screenshots and frame recording
+    let mut screenshots = Screenshots::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    // Scene
+    let (loaded_scene, scene_path) = scene_file::load_from_args::<Scene>().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    scene::install(loaded_scene);
+
     // Window
     let mut window = Window::new("Kiss3d: obj");
-    let mut scene = window.add_group();
+    let mut group = window.add_group();
     window.set_light(Light::StickToCamera);
     window.set_background_color(1.0, 1.0, 1.0);
 
     // State
-    let init_translation = Translation3::new(X_INIT_POS_N, Y_INIT_POS_N, Z_INIT_POS_N);
-    let mut rotation = Rotation {x_angle: 0.0, y_angle: 0.0, z_angle: 0.0};
-    let mut vertices = vec![
-        Point3::new(0.1, 0.1, 0.0),
-        Point3::new(-0.1, 0.0, 0.1),
-        Point3::new(0.0, 0.1, -0.1),
-        Point3::new(0.1, -0.1, 0.1),
-    ];
+    let init_translation = Translation3::from(Vector3::from(scene().camera.position));
+    let [x_angle, y_angle, z_angle] = scene().rotation;
+    let vertices: Vec<Point3<f32>> = scene().vertices.iter().map(|v| Point3::from(*v)).collect();
+    let boundaries = boundaries_from_corners(&vertices);
+    let mut coons_selection = ControlPointSelection { curve: 0, point: 0 };
+    let mut ruled_selection = ControlPointSelection { curve: 0, point: 0 };
+    let loft_settings = LoftSettings {
+        interpolation: Interpolation::CatmullRom,
+        sections: (0..4)
+            .map(|i| {
+                let z = -0.1 + 0.2 * i as f32 / 3.0;
+                let bulge = if i % 2 == 0 { 0.05 } else { -0.03 };
+                vec![
+                    Point3::new(-0.1, 0.0, z),
+                    Point3::new(-0.03, bulge, z),
+                    Point3::new(0.03, bulge, z),
+                    Point3::new(0.1, 0.0, z),
+                ]
+            })
+            .collect(),
+        selection: ControlPointSelection { curve: 0, point: 0 },
+    };
+    let profile_settings = ProfileSettings {
+        kind: ProfileKind::Bezier,
+        points: scene().profile.points.iter().map(|p| Point2::from(*p)).collect(),
+        selected_point: 0,
+        axis: RevolutionAxis::Y,
+        angle: 360.0,
+        length: 0.1,
+        path: scene().profile.path.iter().map(|p| Point3::from(*p)).collect(),
+        selected_path_point: 0,
+        frame_kind: FrameKind::RotationMinimising,
+    };
+    let mut export_settings = ExportSettings { u_count: 100, w_count: 100, status: String::new() };
+    let mut model = Model {
+        rotation: Rotation {x_angle, y_angle, z_angle},
+        surface_type: scene().surface,
+        ruled_curves: boundaries[..2].to_vec(),
+        vertices,
+        boundaries,
+        loft_settings,
+        profile_settings,
+        resolution: scene().resolution,
+        coloring: Coloring::Plain,
+        isoline_settings: IsolineSettings { u_lines: false, w_lines: false, spacing: 0.1 },
+    };
+    let mut history = History::new();
 
-    // Bilinear surface
-    let points_count = 50;
-    let mut quad = scene.add_quad(100.0, 100.0, points_count - 1, points_count - 1);
-    quad.set_color(0.7, 0.3, 0.7);
+    // Surface
+    let mut quad = add_surface_quad(&mut group, model.resolution);
+    let mut quad_resolution = model.resolution;
+    let mut coloring_range = (0.0, 0.0);
+
+    // Textures
+    let default_texture = TextureManager::get_global_manager(|tm| tm.get_default());
+    let colormap_texture = TextureManager::get_global_manager(|tm| tm.add_image(colormap_image(), "colormap"));
+    let checker_texture = TextureManager::get_global_manager(|tm| tm.add_image(checker_image(), "checker"));
 
     // Control points
-    let radius = 0.004;
-    let mut points_spheres = <Vec<SceneNode>>::with_capacity(vertices.len());
-    for i in 0..vertices.len() {
-        let mut sphere = scene.add_sphere(radius);
-        sphere.set_color(CIRCLE_COLORS[i].0, CIRCLE_COLORS[i].1, CIRCLE_COLORS[i].2);
-        points_spheres.push(sphere);
-    }
+    let mut points_spheres = add_points_spheres(&mut group, &control_points(model.surface_type, &model.vertices, &model.boundaries, &model.ruled_curves, &model.loft_settings, &model.profile_settings));
+    let mut spheres_surface_type = model.surface_type;
 
     // Camera
     let mut camera = FixedView::new();
 
     // UI
-    let ids = Ids::new(window.conrod_ui_mut().widget_id_generator());
+    let mut ids = Ids::new(window.conrod_ui_mut().widget_id_generator());
+    ids.legend_colors.resize(LEGEND_STEPS, &mut window.conrod_ui_mut().widget_id_generator());
     
     while window.render_with_camera(&mut camera) {
     //while window.render() {
-        let surface = BilinearSurface::new(&vertices);
+        screenshots.capture(&window);
 
-        quad.modify_vertices(&mut |coords| {
-            for (i, v) in coords.iter_mut().enumerate() {
-                let u = (i % points_count + 1) as f32 / points_count as f32;
-                let w = (i / points_count + 1) as f32 / points_count as f32;
-
-                *v = surface.point(u, w);
+        // Rebuild control points when the surface type or the number of points changes
+        let surface_control_points = control_points(model.surface_type, &model.vertices, &model.boundaries, &model.ruled_curves, &model.loft_settings, &model.profile_settings);
+        if spheres_surface_type != model.surface_type || points_spheres.len() != surface_control_points.len() {
+            for sphere in points_spheres.iter_mut() {
+                sphere.unlink();
             }
+            points_spheres = add_points_spheres(&mut group, &surface_control_points);
+            spheres_surface_type = model.surface_type;
+        }
+
+        let (surface, corner_mismatches): (Box<dyn Surface>, Vec<CornerMismatch>) = match model.surface_type {
+            SurfaceType::Bilinear => (Box::new(BilinearSurface::new(&model.vertices)), vec![]),
+            SurfaceType::Coons => {
+                let coons = CoonsSurface::new(&model.boundaries);
+                let mismatches = coons.corner_mismatches();
+                (Box::new(coons), mismatches)
+            },
+            SurfaceType::Revolution => (
+                Box::new(RevolutionSurface::new(model.profile_settings.profile(), model.profile_settings.axis, model.profile_settings.angle.to_radians())),
+                vec![]
+            ),
+            SurfaceType::Extrusion => (
+                Box::new(ExtrusionSurface::new(model.profile_settings.profile(), model.profile_settings.length)),
+                vec![]
+            ),
+            SurfaceType::Sweep => (
+                Box::new(SweepSurface::new(model.profile_settings.profile(), &model.profile_settings.path, model.profile_settings.frame_kind)),
+                vec![]
+            ),
+            SurfaceType::Ruled => (Box::new(LoftSurface::ruled(&model.ruled_curves[0], &model.ruled_curves[1])), vec![]),
+            SurfaceType::Loft => (Box::new(LoftSurface::new(&model.loft_settings.sections, model.loft_settings.interpolation)), vec![]),
+        };
+
+        // Rebuild the quad when the resolution changes
+        if quad_resolution != model.resolution {
+            quad.unlink();
+            quad = add_surface_quad(&mut group, model.resolution);
+            quad_resolution = model.resolution;
+        }
+
+        let tessellation = Tessellation::new(surface.as_ref(), model.resolution.u_count, model.resolution.w_count);
+        quad.modify_vertices(&mut |coords| {
+            coords.copy_from_slice(&tessellation.vertices);
         });
         quad.recompute_normals();
-        
-        move_points(&mut points_spheres, &vertices);
+
+        // Plain color, UV checker or false colors looked up in the colormap texture
+        match model.coloring {
+            Coloring::Plain => {
+                quad.set_texture(default_texture.clone());
+                let [r, g, b] = scene().colors.surface;
+                quad.set_color(r, g, b);
+            },
+            Coloring::Checker => {
+                quad.set_texture(checker_texture.clone());
+                quad.set_color(1.0, 1.0, 1.0);
+                quad.modify_uvs(&mut |uvs| {
+                    uvs.copy_from_slice(&tessellation.uvs);
+                });
+            },
+            _ => {
+                let values = coloring_values(surface.as_ref(), &tessellation, model.coloring);
+                let min = values.iter().cloned().fold(f32::INFINITY, f32::min);
+                let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+                coloring_range = (min, max);
+
+                quad.set_texture(colormap_texture.clone());
+                quad.set_color(1.0, 1.0, 1.0);
+                quad.modify_uvs(&mut |uvs| {
+                    for (uv, value) in uvs.iter_mut().zip(values.iter()) {
+                        *uv = Point2::new(colormap_uv(*value, min, max), 0.5);
+                    }
+                });
+            }
+        }
+
+        let points: Vec<Point3<f32>> = surface_control_points
+            .iter()
+            .map(|(point, _)| *point)
+            .collect();
+        move_points(&mut points_spheres, &points);
         
         draw_axes(&mut window);
         
+        let model_before_ui = model.clone();
         let mut ui_cell = window.conrod_ui_mut().set_widgets();
-        draw_rotation_ui(&mut ui_cell, &ids, &mut rotation);
-        draw_points_ui(&mut ui_cell, &ids, &mut vertices);
+        draw_rotation_ui(&mut ui_cell, &ids, &mut model.rotation);
+        draw_history_ui(&mut ui_cell, &ids, &history);
+        draw_surface_type_ui(&mut ui_cell, &ids, &mut model.surface_type);
+        match model.surface_type {
+            SurfaceType::Bilinear => draw_points_ui(&mut ui_cell, &ids, &mut model.vertices),
+            SurfaceType::Coons => draw_coons_ui(&mut ui_cell, &ids, &mut model.boundaries, &mut coons_selection, &corner_mismatches),
+            SurfaceType::Ruled => draw_curves_ui(&mut ui_cell, &ids, &mut model.ruled_curves, &mut ruled_selection, ids.surface_type_list),
+            SurfaceType::Loft => draw_loft_ui(&mut ui_cell, &ids, &mut model.loft_settings),
+            SurfaceType::Revolution | SurfaceType::Extrusion | SurfaceType::Sweep => {
+                draw_profile_ui(&mut ui_cell, &ids, model.surface_type, &mut model.profile_settings)
+            },
+        }
+        draw_resolution_ui(&mut ui_cell, &ids, &mut model.resolution);
+        draw_coloring_ui(&mut ui_cell, &ids, &mut model.coloring, coloring_range);
+        draw_isolines_ui(&mut ui_cell, &ids, &mut model.isoline_settings);
+        let export_format = draw_export_ui(&mut ui_cell, &ids, &mut export_settings);
+        drop(ui_cell);
+
+        if let Some(part) = ModelPart::changed(&model_before_ui, &model) {
+            history.record(Edit { part, from: model_before_ui, to: model.clone() });
+        }
+
+        if let Some(format) = export_format {
+            let tessellation = Tessellation::new(surface.as_ref(), export_settings.u_count, export_settings.w_count);
+            let file_name = format!("surface-{}.{}", timestamp(), format.extension());
+
+            export_settings.status = match export_mesh(Path::new(&file_name), format, &tessellation) {
+                Ok(()) => format!("Saved {}", file_name),
+                Err(error) => format!("Failed to save {}: {}", file_name, error),
+            };
+        }
+
+        let scene_rotation = model.rotation.x() * model.rotation.y() * model.rotation.z();
+        group.set_local_translation(init_translation);
+        group.set_local_rotation(scene_rotation);
+
+        let scene_transformation = Isometry3::from_parts(init_translation, scene_rotation);
+        draw_isolines(&mut window, surface.as_ref(), &model.isoline_settings, &scene_transformation);
+        draw_control_polygons(&mut window, &control_polygons(model.surface_type, &model.boundaries, &model.ruled_curves, &model.loft_settings, &model.profile_settings), &scene_transformation);
+
+        for event in window.events().iter() {
+            screenshots.handle_event(&event.value);
+            if let Some(action) = history.handle_event(&event.value) {
+                history.perform(action, &mut model);
+            }
 
-        scene.set_local_translation(init_translation);
-        scene.set_local_rotation(rotation.x() * rotation.y() * rotation.z());
+            if let WindowEvent::Key(key, Action::Press, _) = event.value {
+                if key == SAVE_SCENE_KEY {
+                    let current_scene = Scene {
+                        rotation: [model.rotation.x_angle, model.rotation.y_angle, model.rotation.z_angle],
+                        surface: model.surface_type,
+                        vertices: [0, 1, 2, 3].map(|i| model.vertices[i].into()),
+                        profile: scene::Profile {
+                            points: model.profile_settings.points.iter().map(|p| [p.x, p.y]).collect(),
+                            path: model.profile_settings.path.iter().map(|p| p.coords.into()).collect(),
+                        },
+                        resolution: model.resolution,
+                        ..scene().clone()
+                    };
+                    match scene_file::save(&scene_path, &current_scene) {
+                        Ok(()) => println!("Saved {}", scene_path.display()),
+                        Err(error) => eprintln!("{}", error),
+                    }
+                }
+            }
+        }
     }
 }
\ No newline at end of file
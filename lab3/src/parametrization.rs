@@ -0,0 +1,83 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use nalgebra::{Point3, Vector3};
+
+use crate::support::Surface;
+
+const ISOLINE_SEGMENTS: usize = 100;
+
+// Offset of the isolines from the surface, so they aren't hidden by the shaded quad
+const ISOLINE_OFFSET: f32 = 0.0005;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Isoparameter {
+    U,
+    W,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct IsolineSettings {
+    pub u_lines: bool,
+    pub w_lines: bool,
+    pub spacing: f32,
+}
+
+// Lines of constant u (or w) about every `spacing`, spread evenly so that both borders are included.
+// Each line is returned twice, shifted to both sides of the surface.
+pub fn isolines(surface: &dyn Surface, parameter: Isoparameter, spacing: f32) -> Vec<Vec<Point3<f32>>> {
+    let lines_count = (1.0 / spacing).round().max(1.0) as usize;
+    let mut lines = <Vec<Vec<Point3<f32>>>>::with_capacity(2 * (lines_count + 1));
+
+    for line_index in 0..=lines_count {
+        let constant = line_index as f32 / lines_count as f32;
+
+        for side in [1.0, -1.0] {
+            let line = (0..=ISOLINE_SEGMENTS)
+                .map(|segment| {
+                    let t = segment as f32 / ISOLINE_SEGMENTS as f32;
+                    let (u, w) = match parameter {
+                        Isoparameter::U => (constant, t),
+                        Isoparameter::W => (t, constant),
+                    };
+
+                    let derivatives = surface.derivatives(u, w);
+                    let normal = derivatives.du.cross(&derivatives.dw).try_normalize(f32::EPSILON).unwrap_or_else(Vector3::zeros);
+
+                    surface.point(u, w) + normal * ISOLINE_OFFSET * side
+                })
+                .collect();
+
+            lines.push(line);
+        }
+    }
+
+    lines
+}
+
+const CHECKER_SIZE: u32 = 512;
+const CHECKER_CELLS: u32 = 8;
+
+// Checkerboard with a grid, tinted by (u, w) to show the parameter directions
+pub fn checker_image() -> DynamicImage {
+    let cell_size = CHECKER_SIZE / CHECKER_CELLS;
+
+    let image = RgbImage::from_fn(CHECKER_SIZE, CHECKER_SIZE, |x, y| {
+        let (cell_x, cell_y) = (x / cell_size, y / cell_size);
+        let on_grid = x % cell_size < 2 || y % cell_size < 2;
+
+        let brightness = if on_grid {
+            0.2
+        } else if (cell_x + cell_y) % 2 == 0 {
+            1.0
+        } else {
+            0.6
+        };
+
+        let u = x as f32 / (CHECKER_SIZE - 1) as f32;
+        let w = y as f32 / (CHECKER_SIZE - 1) as f32;
+        let tint = [0.6 + 0.4 * u, 0.6 + 0.4 * w, 0.8];
+
+        Rgb(tint.map(|channel| (channel * brightness * 255.0) as u8))
+    });
+
+    DynamicImage::ImageRgb8(image)
+}
@@ -0,0 +1,164 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use lab_common::scene_file::{Color, Errors, Validate};
+use crate::{Resolution, SurfaceType, MAX_QUAD_POINTS_COUNT};
+
+static SCENE: OnceLock<Scene> = OnceLock::new();
+
+// The surface shown at startup, its control points, colors and where it is seen from
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    pub camera: Camera,
+    // Degrees around x, y and z, as set by the sliders
+    pub rotation: [f32; 3],
+    pub surface: SurfaceType,
+    // Corners of the bilinear surface, the Coons boundaries and the ruled curves start from them
+    pub vertices: [[f32; 3]; 4],
+    pub profile: Profile,
+    pub resolution: Resolution,
+    pub colors: Colors,
+    pub axes: Axes,
+    pub ui: Ui,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Camera {
+    pub position: [f32; 3],
+    // Degrees around y, the axes turned to be seen from aside
+    pub y_rotation: f32,
+}
+
+// Planar profile of the revolution, extrusion and sweep surfaces and the path it is swept along
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub points: Vec<[f32; 2]>,
+    pub path: Vec<[f32; 3]>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub surface: Color,
+    pub isolines: Color,
+    // One per corner and curve, repeated when there are more curves
+    pub control_points: Vec<Color>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Axes {
+    // Also the range of the corner coordinates
+    pub length: f32,
+    pub color: Color,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ui {
+    pub rotation_range: [f32; 2],
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            camera: Camera::default(),
+            rotation: [0.0, 0.0, 0.0],
+            surface: SurfaceType::Bilinear,
+            vertices: [
+                [0.1, 0.1, 0.0],
+                [-0.1, 0.0, 0.1],
+                [0.0, 0.1, -0.1],
+                [0.1, -0.1, 0.1],
+            ],
+            profile: Profile::default(),
+            resolution: Resolution { u_count: 50, w_count: 50 },
+            colors: Colors::default(),
+            axes: Axes::default(),
+            ui: Ui::default(),
+        }
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera { position: [0.05, -0.06, -0.4], y_rotation: -45.0 }
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            points: vec![[0.02, -0.08], [0.09, -0.03], [0.02, 0.03], [0.05, 0.08]],
+            path: vec![[-0.1, 0.0, 0.0], [-0.03, 0.08, 0.0], [0.03, -0.08, 0.05], [0.1, 0.0, 0.0]],
+        }
+    }
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            surface: [0.7, 0.3, 0.7],
+            isolines: [0.1, 0.1, 0.1],
+            control_points: vec![
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [1.0, 1.0, 0.0],
+            ],
+        }
+    }
+}
+
+impl Default for Axes {
+    fn default() -> Self {
+        Axes { length: 0.24, color: [0.0, 0.0, 0.0] }
+    }
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Ui { rotation_range: [-180.0, 180.0] }
+    }
+}
+
+impl Validate for Scene {
+    fn validate(&self, errors: &mut Errors) {
+        errors.range("ui.rotation_range", &self.ui.rotation_range);
+        let [min, max] = self.ui.rotation_range;
+        errors.check("rotation", self.rotation.iter().all(|angle| (min..=max).contains(angle)), "angles must be within ui.rotation_range");
+
+        let half_axe = self.axes.length / 2.0;
+        for (i, vertex) in self.vertices.iter().enumerate() {
+            errors.check(&format!("vertices[{}]", i), vertex.iter().all(|c| c.abs() <= half_axe), "coordinates must be within half of axes.length");
+        }
+        errors.check("profile.points", self.profile.points.len() >= 2, "a profile needs at least 2 points");
+        errors.check("profile.path", self.profile.path.len() >= 2, "a path needs at least 2 points");
+
+        for (key, count) in [("resolution.u_count", self.resolution.u_count), ("resolution.w_count", self.resolution.w_count)] {
+            errors.check(key, (2..=MAX_QUAD_POINTS_COUNT).contains(&count), &format!("must be between 2 and {}", MAX_QUAD_POINTS_COUNT));
+        }
+
+        errors.color("colors.surface", &self.colors.surface);
+        errors.color("colors.isolines", &self.colors.isolines);
+        errors.check("colors.control_points", !self.colors.control_points.is_empty(), "at least one color is needed");
+        for (i, color) in self.colors.control_points.iter().enumerate() {
+            errors.color(&format!("colors.control_points[{}]", i), color);
+        }
+        errors.check("axes.length", self.axes.length > 0.0, "must be positive");
+        errors.color("axes.color", &self.axes.color);
+    }
+}
+
+// Set once at startup, before anything reads the scene
+pub fn install(scene: Scene) {
+    let _ = SCENE.set(scene);
+}
+
+pub fn scene() -> &'static Scene {
+    SCENE.get_or_init(Scene::default)
+}
@@ -1,6 +1,7 @@
 use std::f32::consts::PI;
 use nalgebra::{UnitQuaternion, Vector3, Point3};
 
+#[derive(Clone, PartialEq)]
 pub struct Rotation {
     pub x_angle: f32,
     pub y_angle: f32,
@@ -21,6 +22,20 @@ impl Rotation {
     }
 }
 
+// First and second partial derivatives of a surface at (u, w)
+pub struct SurfaceDerivatives {
+    pub du: Vector3<f32>,
+    pub dw: Vector3<f32>,
+    pub duu: Vector3<f32>,
+    pub duw: Vector3<f32>,
+    pub dww: Vector3<f32>,
+}
+
+pub trait Surface {
+    fn point(&self, u: f32, w: f32) -> Point3<f32>;
+    fn derivatives(&self, u: f32, w: f32) -> SurfaceDerivatives;
+}
+
 pub struct BilinearSurface {
     vertices_v: [Vector3<f32>; 4],
 }
@@ -37,8 +52,10 @@ impl BilinearSurface {
             vertices_v: vertices_v.try_into().unwrap()
         }
     }
+}
 
-    pub fn point(&self, u: f32, w: f32) -> Point3<f32>{
+impl Surface for BilinearSurface {
+    fn point(&self, u: f32, w: f32) -> Point3<f32>{
         let p1 = self.vertices_v[0];
         let p2 = self.vertices_v[1];
         let p3 = self.vertices_v[2];
@@ -51,4 +68,19 @@ impl BilinearSurface {
 
         Point3::from(t)
     }
-}
\ No newline at end of file
+    fn derivatives(&self, u: f32, w: f32) -> SurfaceDerivatives {
+        let p1 = self.vertices_v[0];
+        let p2 = self.vertices_v[1];
+        let p3 = self.vertices_v[2];
+        let p4 = self.vertices_v[3];
+
+        // The surface is linear along each parameter, so only the twist is left
+        SurfaceDerivatives {
+            du: (p3 - p1) * (1.0 - w) + (p4 - p2) * w,
+            dw: (p2 - p1) * (1.0 - u) + (p4 - p3) * u,
+            duu: Vector3::zeros(),
+            duw: p1 - p2 - p3 + p4,
+            dww: Vector3::zeros(),
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use nalgebra::{Point2, Point3, Unit, UnitQuaternion, Vector3};
+
+use crate::bezier::BezierCurve;
+use crate::support::{Surface, SurfaceDerivatives};
+
+// Step of the finite differences along the sweep path
+const PATH_STEP: f32 = 1e-3;
+
+// Number of rotation-minimising frames computed along the path
+const RMF_SAMPLES: usize = 128;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ProfileKind {
+    Bezier,
+    Polyline,
+}
+
+impl ProfileKind {
+    pub const ALL: [ProfileKind; 2] = [ProfileKind::Bezier, ProfileKind::Polyline];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProfileKind::Bezier => "Bezier",
+            ProfileKind::Polyline => "Polyline",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RevolutionAxis {
+    X,
+    Y,
+}
+
+impl RevolutionAxis {
+    pub const ALL: [RevolutionAxis; 2] = [RevolutionAxis::X, RevolutionAxis::Y];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RevolutionAxis::X => "Axis X",
+            RevolutionAxis::Y => "Axis Y",
+        }
+    }
+
+    fn vector(&self) -> Unit<Vector3<f32>> {
+        match self {
+            RevolutionAxis::X => Vector3::x_axis(),
+            RevolutionAxis::Y => Vector3::y_axis(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FrameKind {
+    Frenet,
+    RotationMinimising,
+}
+
+impl FrameKind {
+    pub const ALL: [FrameKind; 2] = [FrameKind::Frenet, FrameKind::RotationMinimising];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FrameKind::Frenet => "Frenet",
+            FrameKind::RotationMinimising => "Rotation min.",
+        }
+    }
+}
+
+// Planar profile in the XY plane, with its first and second derivatives
+pub enum ProfileCurve {
+    Bezier([BezierCurve; 3]),
+    Polyline(Vec<Vector3<f32>>),
+}
+
+impl ProfileCurve {
+    pub fn new(kind: ProfileKind, points: &[Point2<f32>]) -> Self {
+        let points: Vec<Point3<f32>> = points.iter().map(|p| Point3::new(p.x, p.y, 0.0)).collect();
+
+        match kind {
+            ProfileKind::Bezier => {
+                let curve = BezierCurve::new(&points);
+                let first_derivative = curve.derivative();
+                let second_derivative = first_derivative.derivative();
+                ProfileCurve::Bezier([curve, first_derivative, second_derivative])
+            },
+            ProfileKind::Polyline => ProfileCurve::Polyline(points.iter().map(|p| p.coords).collect()),
+        }
+    }
+
+    // Value, first and second derivatives at t
+    pub fn evaluate(&self, t: f32) -> [Vector3<f32>; 3] {
+        match self {
+            ProfileCurve::Bezier(curves) => curves.each_ref().map(|curve| curve.vector(t)),
+            ProfileCurve::Polyline(points) => {
+                // Segments share [0, 1] evenly
+                let segments = points.len() - 1;
+                let s = t * segments as f32;
+                let segment = (s.floor() as usize).min(segments - 1);
+                let local_t = s - segment as f32;
+                let direction = points[segment + 1] - points[segment];
+
+                [
+                    points[segment] + direction * local_t,
+                    direction * segments as f32,
+                    Vector3::zeros(),
+                ]
+            }
+        }
+    }
+}
+
+// Profile rotated around an axis by `angle` (radians) as w goes from 0 to 1
+pub struct RevolutionSurface {
+    profile: ProfileCurve,
+    axis: Unit<Vector3<f32>>,
+    angle: f32,
+}
+
+impl RevolutionSurface {
+    pub fn new(profile: ProfileCurve, axis: RevolutionAxis, angle: f32) -> Self {
+        Self { profile, axis: axis.vector(), angle }
+    }
+
+    fn rotation(&self, w: f32) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(&self.axis, w * self.angle)
+    }
+}
+
+impl Surface for RevolutionSurface {
+    fn point(&self, u: f32, w: f32) -> Point3<f32> {
+        let [p, _, _] = self.profile.evaluate(u);
+        Point3::from(self.rotation(w) * p)
+    }
+
+    // d/dw R(w) v = angle * (axis × R(w) v)
+    fn derivatives(&self, u: f32, w: f32) -> SurfaceDerivatives {
+        let [p, dp, ddp] = self.profile.evaluate(u);
+        let rotation = self.rotation(w);
+        let (r, dr, ddr) = (rotation * p, rotation * dp, rotation * ddp);
+        let a = self.axis.into_inner();
+
+        SurfaceDerivatives {
+            du: dr,
+            dw: a.cross(&r) * self.angle,
+            duu: ddr,
+            duw: a.cross(&dr) * self.angle,
+            dww: a.cross(&a.cross(&r)) * self.angle.powi(2),
+        }
+    }
+}
+
+// Profile moved along the Z axis by `length` as w goes from 0 to 1
+pub struct ExtrusionSurface {
+    profile: ProfileCurve,
+    length: f32,
+}
+
+impl ExtrusionSurface {
+    pub fn new(profile: ProfileCurve, length: f32) -> Self {
+        Self { profile, length }
+    }
+}
+
+impl Surface for ExtrusionSurface {
+    fn point(&self, u: f32, w: f32) -> Point3<f32> {
+        let [p, _, _] = self.profile.evaluate(u);
+        Point3::from(p + Vector3::z() * self.length * w)
+    }
+
+    fn derivatives(&self, u: f32, _w: f32) -> SurfaceDerivatives {
+        let [_, dp, ddp] = self.profile.evaluate(u);
+
+        SurfaceDerivatives {
+            du: dp,
+            dw: Vector3::z() * self.length,
+            duu: ddp,
+            duw: Vector3::zeros(),
+            dww: Vector3::zeros(),
+        }
+    }
+}
+
+// Moving frame of the path: tangent, normal and binormal
+#[derive(Clone, Copy)]
+struct Frame {
+    tangent: Vector3<f32>,
+    normal: Vector3<f32>,
+    binormal: Vector3<f32>,
+}
+
+impl Frame {
+    // Any normal will do where the curvature vanishes
+    fn from_tangent(tangent: Vector3<f32>, normal_hint: Vector3<f32>) -> Self {
+        let tangent = tangent.try_normalize(f32::EPSILON).unwrap_or_else(Vector3::x);
+        let binormal = tangent
+            .cross(&normal_hint)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| {
+                let helper = if tangent.x.abs() < 0.9 { Vector3::x() } else { Vector3::y() };
+                tangent.cross(&helper).normalize()
+            });
+
+        Self { tangent, normal: binormal.cross(&tangent), binormal }
+    }
+}
+
+// Profile swept along a 3D path; the profile X goes along the frame normal, Y along the binormal.
+// The derivatives along the path are computed with finite differences of the frames.
+pub struct SweepSurface {
+    profile: ProfileCurve,
+    path: [BezierCurve; 3],
+    rotation_minimising_frames: Option<Vec<Frame>>,
+}
+
+impl SweepSurface {
+    pub fn new(profile: ProfileCurve, path: &[Point3<f32>], frame_kind: FrameKind) -> Self {
+        let curve = BezierCurve::new(path);
+        let first_derivative = curve.derivative();
+        let second_derivative = first_derivative.derivative();
+
+        let mut surface = Self {
+            profile,
+            path: [curve, first_derivative, second_derivative],
+            rotation_minimising_frames: None,
+        };
+
+        if frame_kind == FrameKind::RotationMinimising {
+            surface.rotation_minimising_frames = Some(surface.double_reflection_frames());
+        }
+
+        surface
+    }
+
+    fn frenet_frame(&self, w: f32) -> Frame {
+        let [_, d1, d2] = &self.path;
+        Frame::from_tangent(d1.vector(w), d2.vector(w))
+    }
+
+    // Wang et al., "Computation of rotation minimizing frames", 2008
+    fn double_reflection_frames(&self) -> Vec<Frame> {
+        let [curve, d1, _] = &self.path;
+        let mut frames = vec![self.frenet_frame(0.0)];
+
+        for i in 1..RMF_SAMPLES {
+            let (w0, w1) = ((i - 1) as f32 / (RMF_SAMPLES - 1) as f32, i as f32 / (RMF_SAMPLES - 1) as f32);
+            let previous = frames[i - 1];
+
+            let v1 = curve.vector(w1) - curve.vector(w0);
+            let c1 = v1.dot(&v1);
+            if c1 < f32::EPSILON {
+                frames.push(previous);
+                continue;
+            }
+            let normal_l = previous.normal - v1 * (2.0 / c1 * v1.dot(&previous.normal));
+            let tangent_l = previous.tangent - v1 * (2.0 / c1 * v1.dot(&previous.tangent));
+
+            let tangent = d1.vector(w1).try_normalize(f32::EPSILON).unwrap_or(previous.tangent);
+            let v2 = tangent - tangent_l;
+            let c2 = v2.dot(&v2);
+            let normal = if c2 < f32::EPSILON {
+                normal_l
+            } else {
+                normal_l - v2 * (2.0 / c2 * v2.dot(&normal_l))
+            };
+
+            frames.push(Frame::from_tangent(tangent, normal));
+        }
+
+        frames
+    }
+
+    fn frame(&self, w: f32) -> Frame {
+        match &self.rotation_minimising_frames {
+            None => self.frenet_frame(w),
+            Some(frames) => {
+                let s = w.clamp(0.0, 1.0) * (frames.len() - 1) as f32;
+                let i = (s.floor() as usize).min(frames.len() - 2);
+                let t = s - i as f32;
+                let normal = frames[i].normal * (1.0 - t) + frames[i + 1].normal * t;
+
+                Frame::from_tangent(self.path[1].vector(w), normal)
+            }
+        }
+    }
+
+    fn point_and_du(&self, u: f32, w: f32) -> (Vector3<f32>, Vector3<f32>) {
+        let [p, dp, _] = self.profile.evaluate(u);
+        let frame = self.frame(w);
+
+        (
+            self.path[0].vector(w) + frame.normal * p.x + frame.binormal * p.y,
+            frame.normal * dp.x + frame.binormal * dp.y,
+        )
+    }
+}
+
+impl Surface for SweepSurface {
+    fn point(&self, u: f32, w: f32) -> Point3<f32> {
+        Point3::from(self.point_and_du(u, w).0)
+    }
+
+    fn derivatives(&self, u: f32, w: f32) -> SurfaceDerivatives {
+        let [_, _, ddp] = self.profile.evaluate(u);
+        let frame = self.frame(w);
+
+        // Central differences, moved inside the path near its ends
+        let w_center = w.clamp(PATH_STEP, 1.0 - PATH_STEP);
+        let (before, du_before) = self.point_and_du(u, w_center - PATH_STEP);
+        let (center, _) = self.point_and_du(u, w_center);
+        let (after, du_after) = self.point_and_du(u, w_center + PATH_STEP);
+        let (_, du) = self.point_and_du(u, w);
+
+        SurfaceDerivatives {
+            du,
+            dw: (after - before) / (2.0 * PATH_STEP),
+            duu: frame.normal * ddp.x + frame.binormal * ddp.y,
+            duw: (du_after - du_before) / (2.0 * PATH_STEP),
+            dww: (after - center * 2.0 + before) / PATH_STEP.powi(2),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use nalgebra::{Point2, Point3, Vector3};
+
+use crate::support::Surface;
+
+// Regular grid over [0, 1]² of the surface parameters.
+// Vertex (i, j) has u = i / (u_count - 1), w = j / (w_count - 1)
+// and is stored at index j * u_count + i, so u runs along the rows.
+pub struct Tessellation {
+    pub u_count: usize,
+    pub w_count: usize,
+    pub vertices: Vec<Point3<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    pub uvs: Vec<Point2<f32>>,
+}
+
+impl Tessellation {
+    pub fn new(surface: &dyn Surface, u_count: usize, w_count: usize) -> Self {
+        assert!(u_count > 1 && w_count > 1, "Tessellation needs at least 2 points along each parameter");
+
+        let mut vertices = <Vec<Point3<f32>>>::with_capacity(u_count * w_count);
+        let mut uvs = <Vec<Point2<f32>>>::with_capacity(u_count * w_count);
+
+        for j in 0..w_count {
+            for i in 0..u_count {
+                let u = i as f32 / (u_count - 1) as f32;
+                let w = j as f32 / (w_count - 1) as f32;
+
+                vertices.push(surface.point(u, w));
+                uvs.push(Point2::new(u, w));
+            }
+        }
+
+        let mut tessellation = Self {
+            u_count,
+            w_count,
+            vertices,
+            normals: vec![],
+            uvs,
+        };
+        tessellation.normals = tessellation.grid_normals();
+
+        tessellation
+    }
+
+    pub fn index(&self, i: usize, j: usize) -> usize {
+        j * self.u_count + i
+    }
+
+    // Two triangles per grid cell, oriented along du × dw
+    pub fn faces(&self) -> Vec<[usize; 3]> {
+        let mut faces = <Vec<[usize; 3]>>::with_capacity(2 * (self.u_count - 1) * (self.w_count - 1));
+
+        for j in 0..self.w_count - 1 {
+            for i in 0..self.u_count - 1 {
+                let a = self.index(i, j);
+                let b = self.index(i + 1, j);
+                let c = self.index(i + 1, j + 1);
+                let d = self.index(i, j + 1);
+
+                faces.push([a, b, c]);
+                faces.push([a, c, d]);
+            }
+        }
+
+        faces
+    }
+
+    // Normals from central differences on the grid (one-sided on the border)
+    fn grid_normals(&self) -> Vec<Vector3<f32>> {
+        let mut normals = <Vec<Vector3<f32>>>::with_capacity(self.vertices.len());
+
+        for j in 0..self.w_count {
+            for i in 0..self.u_count {
+                let du = self.vertices[self.index((i + 1).min(self.u_count - 1), j)]
+                    - self.vertices[self.index(i.saturating_sub(1), j)];
+                let dw = self.vertices[self.index(i, (j + 1).min(self.w_count - 1))]
+                    - self.vertices[self.index(i, j.saturating_sub(1))];
+
+                normals.push(du.cross(&dw).try_normalize(f32::EPSILON).unwrap_or_else(Vector3::z));
+            }
+        }
+
+        normals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coons::{boundaries_from_corners, CoonsSurface};
+    use crate::support::BilinearSurface;
+
+    const RESOLUTIONS: [(usize, usize); 5] = [(2, 2), (2, 7), (3, 3), (10, 4), (47, 33)];
+
+    // In the order of BilinearSurface: (0, 0), (0, 1), (1, 0), (1, 1) in (u, w)
+    fn corners() -> [Point3<f32>; 4] {
+        [
+            Point3::new(-1.0, 0.0, -1.0),
+            Point3::new(-1.0, 0.5, 1.0),
+            Point3::new(1.0, -0.5, -1.0),
+            Point3::new(1.0, 0.2, 1.0),
+        ]
+    }
+
+    // Tessellated corners in the order of corners()
+    fn tessellated_corners(tessellation: &Tessellation) -> [Point3<f32>; 4] {
+        let (u_count, w_count) = (tessellation.u_count, tessellation.w_count);
+        let vertices = &tessellation.vertices;
+
+        [
+            vertices[0],
+            vertices[(w_count - 1) * u_count],
+            vertices[u_count - 1],
+            vertices[vertices.len() - 1],
+        ]
+    }
+
+    #[test]
+    fn bilinear_tessellation_starts_and_ends_at_the_corners() {
+        let corners = corners();
+        let surface = BilinearSurface::new(&corners);
+
+        for (u_count, w_count) in RESOLUTIONS {
+            let tessellation = Tessellation::new(&surface, u_count, w_count);
+            assert_eq!(tessellation.vertices.len(), u_count * w_count);
+            assert_eq!(tessellated_corners(&tessellation), corners, "{}x{}", u_count, w_count);
+        }
+    }
+
+    #[test]
+    fn coons_tessellation_starts_and_ends_at_the_corners() {
+        let corners = corners();
+        let surface = CoonsSurface::new(&boundaries_from_corners(&corners));
+
+        for (u_count, w_count) in RESOLUTIONS {
+            let tessellation = Tessellation::new(&surface, u_count, w_count);
+            for (vertex, corner) in tessellated_corners(&tessellation).iter().zip(corners.iter()) {
+                assert!((vertex - corner).norm() < 1e-5, "{}x{}: {} != {}", u_count, w_count, vertex, corner);
+            }
+        }
+    }
+
+    #[test]
+    fn parameters_cover_exactly_zero_to_one() {
+        let surface = BilinearSurface::new(&corners());
+
+        for (u_count, w_count) in RESOLUTIONS {
+            let tessellation = Tessellation::new(&surface, u_count, w_count);
+            let uvs = &tessellation.uvs;
+
+            assert_eq!(uvs[0], Point2::new(0.0, 0.0));
+            assert_eq!(uvs[u_count - 1], Point2::new(1.0, 0.0));
+            assert_eq!(uvs[(w_count - 1) * u_count], Point2::new(0.0, 1.0));
+            assert_eq!(uvs[uvs.len() - 1], Point2::new(1.0, 1.0));
+
+            for j in 0..w_count {
+                for i in 0..u_count {
+                    let uv = uvs[tessellation.index(i, j)];
+                    assert_eq!(uv.x, i as f32 / (u_count - 1) as f32);
+                    assert_eq!(uv.y, j as f32 / (w_count - 1) as f32);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// Throughput of every rectangle clipping algorithm, with and without the line index.
+// Runs headless: cargo bench --bench clipping
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use lab4::clipping::{ClippingAlgorithm, OperationCounters};
+use lab4::common::{Point, Line, LineClipping};
+use lab4::line_index::LineIndex;
+use lab4::line_set::{generate_lines, Distribution};
+use lab4::selection::RectangleSelection;
+
+const SEED: u64 = 42;
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+const AREA_HALF_SIZE: f32 = 500.0;
+
+// Line set and selection giving mostly one kind of clipping result
+struct Scenario {
+    name: &'static str,
+    distribution: Distribution,
+    selection_half_size: f32,
+}
+
+const SCENARIOS: [Scenario; 3] = [
+    Scenario { name: "mostly_inside", distribution: Distribution::Short, selection_half_size: 480.0 },
+    Scenario { name: "mostly_outside", distribution: Distribution::Short, selection_half_size: 50.0 },
+    Scenario { name: "mostly_crossing", distribution: Distribution::Long, selection_half_size: 400.0 },
+];
+
+fn clip_all(lines: &[Line], selection: &RectangleSelection, algorithm: ClippingAlgorithm) -> usize {
+    let mut counters = OperationCounters::default();
+    lines
+        .iter()
+        .filter(|line| !matches!(selection.clipping_check(line, algorithm.clipper(), &mut counters), LineClipping::Outside))
+        .count()
+}
+
+// Lines the index can't classify go to the clipper, like in the application
+fn clip_indexed(lines: &[Line], index: &LineIndex, selection: &RectangleSelection, algorithm: ClippingAlgorithm) -> usize {
+    let mut counters = OperationCounters::default();
+    let query = index.query(selection.borders());
+    let crossing = query.crossing
+        .iter()
+        .filter(|i| !matches!(selection.clipping_check(&lines[**i], algorithm.clipper(), &mut counters), LineClipping::Outside))
+        .count();
+
+    query.inside.len() + crossing
+}
+
+fn clipping_benchmark(c: &mut Criterion) {
+    let range = -AREA_HALF_SIZE..AREA_HALF_SIZE;
+
+    for scenario in &SCENARIOS {
+        let mut group = c.benchmark_group(scenario.name);
+        let half = scenario.selection_half_size;
+        let selection = RectangleSelection::new(Point::new(-half, half), Point::new(half, -half));
+
+        for size in SIZES {
+            let lines = generate_lines(SEED, scenario.distribution, size, range.clone(), range.clone());
+            let index = LineIndex::new(&lines);
+            group.throughput(Throughput::Elements(size as u64));
+
+            for algorithm in ClippingAlgorithm::ALL {
+                group.bench_with_input(BenchmarkId::new(algorithm.label(), size), &lines, |b, lines| {
+                    b.iter(|| clip_all(lines, &selection, algorithm))
+                });
+                group.bench_with_input(BenchmarkId::new(format!("{} + index", algorithm.label()), size), &lines, |b, lines| {
+                    b.iter(|| clip_indexed(lines, &index, &selection, algorithm))
+                });
+            }
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, clipping_benchmark);
+criterion_main!(benches);
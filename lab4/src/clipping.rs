@@ -0,0 +1,480 @@
+use nalgebra::Vector2;
+
+use crate::common::{Point, Line, LineClipping, Borders};
+
+// Distance in pixels under which a point is considered lying on a border
+pub const CLIPPING_EPSILON: f32 = 1e-3;
+
+const LEFT_CODE: u8 = 1u8 << 3;
+const RIGHT_CODE: u8 = 1u8 << 2;
+const BOTTOM_CODE: u8 = 1u8 << 1;
+const TOP_CODE: u8 = 1u8;
+
+// Operations performed by a clipping algorithm, accumulated over the lines set
+#[derive(Default, Clone, Copy)]
+pub struct OperationCounters {
+    // additions, subtractions, multiplications and divisions
+    pub arithmetic: u32,
+    pub comparisons: u32,
+    // line / border intersection computations
+    pub intersection_tests: u32,
+}
+
+pub trait LineClipper {
+    fn clip(&self, borders: Borders, line: &Line, counters: &mut OperationCounters) -> LineClipping;
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ClippingAlgorithm {
+    CohenSutherland,
+    LiangBarsky,
+    CyrusBeck,
+}
+
+impl ClippingAlgorithm {
+    pub const ALL: [ClippingAlgorithm; 3] = [
+        ClippingAlgorithm::CohenSutherland,
+        ClippingAlgorithm::LiangBarsky,
+        ClippingAlgorithm::CyrusBeck,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClippingAlgorithm::CohenSutherland => "Cohen-Sutherland",
+            ClippingAlgorithm::LiangBarsky => "Liang-Barsky",
+            ClippingAlgorithm::CyrusBeck => "Cyrus-Beck",
+        }
+    }
+
+    pub fn clipper(&self) -> &'static dyn LineClipper {
+        match self {
+            ClippingAlgorithm::CohenSutherland => &CohenSutherland,
+            ClippingAlgorithm::LiangBarsky => &LiangBarsky,
+            ClippingAlgorithm::CyrusBeck => &CyrusBeck,
+        }
+    }
+}
+
+// Region codes for the trivial checks, then the outside end points are moved onto the borders
+pub struct CohenSutherland;
+
+impl LineClipper for CohenSutherland {
+    fn clip(&self, borders: Borders, line: &Line, counters: &mut OperationCounters) -> LineClipping {
+        if let Some(clipping) = self.trivial_clipping_check(borders, line, counters) {
+            clipping
+        } else {
+            self.complex_clipping_check(borders, line, counters)
+        }
+    }
+}
+
+impl CohenSutherland {
+    // One border at a time; a border is only intersected when the line crosses it,
+    // so the divisor is never zero and the moved point lies exactly on the border.
+    fn complex_clipping_check(&self, borders: Borders, line: &Line, counters: &mut OperationCounters) -> LineClipping {
+        let (l, r, t, b) = borders;
+        let (mut start, mut end) = *line;
+        let mut start_code = self.clipping_byte_code(borders, &start, counters);
+        let mut end_code = self.clipping_byte_code(borders, &end, counters);
+
+        loop {
+            counters.comparisons += 2;
+            if start_code | end_code == 0u8 {
+                return LineClipping::PartlyInside(vec![(start, end)]);
+            } else if start_code & end_code != 0u8 {
+                return LineClipping::Outside;
+            }
+
+            let code = if start_code != 0u8 { start_code } else { end_code };
+            let (dx, dy) = (end.x - start.x, end.y - start.y);
+
+            let point = if code & LEFT_CODE != 0u8 {
+                Point::new(l, start.y + dy * (l - start.x) / dx)
+            } else if code & RIGHT_CODE != 0u8 {
+                Point::new(r, start.y + dy * (r - start.x) / dx)
+            } else if code & BOTTOM_CODE != 0u8 {
+                Point::new(start.x + dx * (b - start.y) / dy, b)
+            } else {
+                Point::new(start.x + dx * (t - start.y) / dy, t)
+            };
+            counters.arithmetic += 2 + 4;
+            counters.comparisons += 4;
+            counters.intersection_tests += 1;
+
+            if start_code != 0u8 {
+                start = point;
+                start_code = self.clipping_byte_code(borders, &start, counters);
+            } else {
+                end = point;
+                end_code = self.clipping_byte_code(borders, &end, counters);
+            }
+        }
+    }
+
+    fn trivial_clipping_check(&self, borders: Borders, line: &Line, counters: &mut OperationCounters) -> Option<LineClipping> {
+        let (start, end) = line;
+
+        let start_byte_code = self.clipping_byte_code(borders, start, counters);
+        let end_byte_code = self.clipping_byte_code(borders, end, counters);
+
+        counters.comparisons += 2;
+        if start_byte_code | end_byte_code == 0u8 {
+            Some(LineClipping::Inside)
+        } else if start_byte_code & end_byte_code != 0u8 {
+            Some(LineClipping::Outside)
+        } else {
+            None
+        }
+    }
+
+    fn clipping_byte_code(&self, borders: Borders, point: &Point, counters: &mut OperationCounters) -> u8 {
+        let mut code = 0u8;
+        let (l, r, t, b) = borders;
+        let x = point.x;
+        let y = point.y;
+
+        // Points closer to a border than the tolerance are on it
+        if x < l - CLIPPING_EPSILON { code |= LEFT_CODE; }
+        if x > r + CLIPPING_EPSILON { code |= RIGHT_CODE; }
+        if y < b - CLIPPING_EPSILON { code |= BOTTOM_CODE; }
+        if y > t + CLIPPING_EPSILON { code |= TOP_CODE; }
+        counters.comparisons += 4;
+
+        code
+    }
+}
+
+// Parametric clipping: the line is kept between the latest entering and the earliest leaving border
+pub struct LiangBarsky;
+
+impl LineClipper for LiangBarsky {
+    fn clip(&self, borders: Borders, line: &Line, counters: &mut OperationCounters) -> LineClipping {
+        let (l, r, t, b) = borders;
+        let (start, end) = line;
+        let (dx, dy) = (end.x - start.x, end.y - start.y);
+
+        // p_i * t <= q_i for the left, right, bottom and top borders
+        let p = [-dx, dx, -dy, dy];
+        let q = [start.x - l, r - start.x, start.y - b, t - start.y];
+        counters.arithmetic += 2 + 4;
+
+        let (mut t_enter, mut t_leave) = (0.0f32, 1.0f32);
+        for (p, q) in p.iter().zip(q.iter()) {
+            counters.intersection_tests += 1;
+            counters.comparisons += 1;
+            if *p == 0.0 {
+                // Parallel to the border
+                counters.comparisons += 1;
+                if *q < -CLIPPING_EPSILON {
+                    return LineClipping::Outside;
+                }
+                continue;
+            }
+
+            let ratio = q / p;
+            counters.arithmetic += 1;
+            counters.comparisons += 2;
+            if *p < 0.0 {
+                t_enter = t_enter.max(ratio);
+            } else {
+                t_leave = t_leave.min(ratio);
+            }
+
+            counters.comparisons += 1;
+            if t_enter > t_leave {
+                return LineClipping::Outside;
+            }
+        }
+
+        counters.comparisons += 2;
+        if t_enter == 0.0 && t_leave == 1.0 {
+            return LineClipping::Inside;
+        }
+
+        let point = |t: f32| Point::new(start.x + dx * t, start.y + dy * t);
+        counters.arithmetic += 8;
+
+        LineClipping::PartlyInside(vec![(point(t_enter), point(t_leave))])
+    }
+}
+
+// Parametric clipping against a convex polygon, the edges play the role of the rectangle borders
+pub struct CyrusBeck;
+
+impl LineClipper for CyrusBeck {
+    fn clip(&self, borders: Borders, line: &Line, counters: &mut OperationCounters) -> LineClipping {
+        let (l, r, t, b) = borders;
+        let polygon = [Point::new(l, b), Point::new(r, b), Point::new(r, t), Point::new(l, t)];
+
+        self.clip_polygon(&polygon, line, counters)
+    }
+}
+
+impl CyrusBeck {
+    // `polygon` must be convex and counter-clockwise
+    pub fn clip_polygon(&self, polygon: &[Point], line: &Line, counters: &mut OperationCounters) -> LineClipping {
+        let (start, end) = line;
+        let direction = end - start;
+        counters.arithmetic += 2;
+
+        let (mut t_enter, mut t_leave) = (0.0f32, 1.0f32);
+        for (i, vertex) in polygon.iter().enumerate() {
+            let edge = polygon[(i + 1) % polygon.len()] - vertex;
+            let inner_normal = Vector2::new(-edge.y, edge.x);
+
+            // n * (P(t) - vertex) >= 0 inside: numerator + denominator * t >= 0
+            let numerator = inner_normal.dot(&(start - vertex));
+            let denominator = inner_normal.dot(&direction);
+            counters.arithmetic += 2 + 2 + 3 + 3;
+            counters.intersection_tests += 1;
+
+            counters.comparisons += 1;
+            if denominator == 0.0 {
+                // Parallel to the edge, the numerator is the distance scaled by the edge length
+                counters.comparisons += 1;
+                if numerator < -CLIPPING_EPSILON * edge.norm() {
+                    return LineClipping::Outside;
+                }
+                continue;
+            }
+
+            let ratio = -numerator / denominator;
+            counters.arithmetic += 1;
+            counters.comparisons += 2;
+            if denominator > 0.0 {
+                t_enter = t_enter.max(ratio);
+            } else {
+                t_leave = t_leave.min(ratio);
+            }
+
+            counters.comparisons += 1;
+            if t_enter > t_leave {
+                return LineClipping::Outside;
+            }
+        }
+
+        counters.comparisons += 2;
+        if t_enter == 0.0 && t_leave == 1.0 {
+            return LineClipping::Inside;
+        }
+
+        counters.arithmetic += 8;
+        LineClipping::PartlyInside(vec![(start + direction * t_enter, start + direction * t_leave)])
+    }
+}
+
+// Twice the signed area, positive for counter-clockwise polygons
+pub fn signed_double_area(polygon: &[Point]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum()
+}
+
+// Every turn goes the same way and the boundary winds around only once
+pub fn is_convex(polygon: &[Point]) -> bool {
+    if polygon.len() < 3 || signed_double_area(polygon).abs() <= f32::EPSILON {
+        return false;
+    }
+
+    let count = polygon.len();
+    let mut turn_sign = 0.0f32;
+    let mut total_angle = 0.0f32;
+
+    for i in 0..count {
+        let incoming = polygon[(i + 1) % count] - polygon[i];
+        let outgoing = polygon[(i + 2) % count] - polygon[(i + 1) % count];
+        let cross = incoming.perp(&outgoing);
+
+        if cross != 0.0 {
+            if turn_sign * cross < 0.0 {
+                return false;
+            }
+            turn_sign = cross.signum();
+        }
+        total_angle += cross.atan2(incoming.dot(&outgoing));
+    }
+
+    (total_angle.abs() - 2.0 * std::f32::consts::PI).abs() < 1e-3
+}
+
+// Lines known to break naive implementations: axis-aligned, along the borders,
+// through the corners, touching the window in a single point and of zero length
+pub fn degenerate_lines(borders: Borders) -> Vec<Line> {
+    let (l, r, t, b) = borders;
+    let (cx, cy) = ((l + r) / 2.0, (t + b) / 2.0);
+    let (w, h) = (r - l, t - b);
+
+    vec![
+        // vertical and horizontal
+        (Point::new(cx, b - h), Point::new(cx, t + h)),
+        (Point::new(l - w, cy), Point::new(r + w, cy)),
+        (Point::new(cx, cy), Point::new(cx, t + h)),
+        (Point::new(l - w, t + h), Point::new(r + w, t + h)),
+        // along the borders
+        (Point::new(l, b - h), Point::new(l, t + h)),
+        (Point::new(l - w, t), Point::new(r + w, t)),
+        (Point::new(r, b), Point::new(r, t)),
+        (Point::new(r + w, b), Point::new(r + 2.0 * w, b)),
+        // through the corners
+        (Point::new(l - w, b - h), Point::new(r + w, t + h)),
+        (Point::new(l, t), Point::new(r, b)),
+        (Point::new(l - w / 2.0, t - h / 2.0), Point::new(l + w / 2.0, t + h / 2.0)),
+        // ending on a border
+        (Point::new(l, cy), Point::new(l - w, cy + h)),
+        (Point::new(cx, t), Point::new(cx + w, t + h)),
+        // zero length
+        (Point::new(cx, cy), Point::new(cx, cy)),
+        (Point::new(l - w, cy), Point::new(l - w, cy)),
+        (Point::new(l, b), Point::new(l, b)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Samples along the line for the brute-force reference
+    const REFERENCE_SAMPLES: usize = 4096;
+    const REFERENCE_BISECTIONS: usize = 24;
+
+    // Brute-force clipping of a line against any window given by its inside test:
+    // the line is sampled densely and the ends of every inside run are refined by bisection
+    fn reference_clipping(line: &Line, contains: &dyn Fn(&Point) -> bool) -> LineClipping {
+        let (start, end) = line;
+        let point = |t: f32| start + (end - start) * t;
+        let samples: Vec<bool> = (0..=REFERENCE_SAMPLES)
+            .map(|i| contains(&point(i as f32 / REFERENCE_SAMPLES as f32)))
+            .collect();
+
+        if samples.iter().all(|inside| *inside) {
+            return LineClipping::Inside;
+        }
+
+        // Between an outside and an inside parameter
+        let refine = |mut outside: f32, mut inside: f32| {
+            for _ in 0..REFERENCE_BISECTIONS {
+                let middle = (outside + inside) / 2.0;
+                if contains(&point(middle)) {
+                    inside = middle;
+                } else {
+                    outside = middle;
+                }
+            }
+            inside
+        };
+        let step = 1.0 / REFERENCE_SAMPLES as f32;
+
+        let mut parts = vec![];
+        let mut first = 0;
+        while let Some(offset) = samples[first..].iter().position(|inside| *inside) {
+            let run_start = first + offset;
+            let run_end = samples[run_start..].iter().position(|inside| !*inside).map_or(REFERENCE_SAMPLES, |length| run_start + length - 1);
+
+            let t_enter = if run_start == 0 { 0.0 } else { refine((run_start - 1) as f32 * step, run_start as f32 * step) };
+            let t_leave = if run_end == REFERENCE_SAMPLES { 1.0 } else { refine((run_end + 1) as f32 * step, run_end as f32 * step) };
+            parts.push((point(t_enter), point(t_leave)));
+
+            first = run_end + 1;
+            if first > REFERENCE_SAMPLES {
+                break;
+            }
+        }
+
+        if parts.is_empty() {
+            LineClipping::Outside
+        } else {
+            LineClipping::PartlyInside(parts)
+        }
+    }
+
+    // Equal results up to `tolerance` pixels; a part shorter than it counts as no part at all
+    fn same_clipping(line: &Line, first: &LineClipping, second: &LineClipping, tolerance: f32) -> bool {
+        let direction = line.1 - line.0;
+        let parameter = |point: &Point| (point - line.0).dot(&direction) / direction.norm_squared().max(f32::EPSILON);
+        // Parts ordered along the line, each going the way of the line
+        let parts = |clipping: &LineClipping, min_length: f32| -> Vec<Line> {
+            let mut parts: Vec<Line> = match clipping {
+                LineClipping::Inside => vec![*line],
+                LineClipping::Outside => vec![],
+                LineClipping::PartlyInside(parts) => parts.clone(),
+            };
+            parts.retain(|part| (part.1 - part.0).norm() > min_length);
+            for part in parts.iter_mut() {
+                if parameter(&part.0) > parameter(&part.1) {
+                    *part = (part.1, part.0);
+                }
+            }
+            parts.sort_by(|a, b| parameter(&a.0).total_cmp(&parameter(&b.0)));
+            parts
+        };
+        let same_parts = |a: &[Line], b: &[Line]| {
+            a.len() == b.len()
+                && a.iter().zip(b.iter()).all(|(a, b)| (a.0 - b.0).norm() <= tolerance && (a.1 - b.1).norm() <= tolerance)
+        };
+
+        // Short parts near the threshold may be found by only one of the two
+        same_parts(&parts(first, tolerance), &parts(second, tolerance))
+            || same_parts(&parts(first, 2.0 * tolerance), &parts(second, 2.0 * tolerance))
+    }
+
+    // Distance in pixels under which clipped parts match the reference
+    const REFERENCE_TOLERANCE: f32 = 0.5;
+
+    const WINDOWS: [Borders; 3] = [
+        (100.0, 300.0, 250.0, 50.0),
+        (-40.5, 17.25, 3.75, -90.0),
+        (0.0, 1000.0, 20.0, 10.0),
+    ];
+
+    fn contains(borders: Borders, point: &Point) -> bool {
+        let (l, r, t, b) = borders;
+        let e = CLIPPING_EPSILON;
+
+        l - e <= point.x && point.x <= r + e && b - e <= point.y && point.y <= t + e
+    }
+
+    fn assert_matches_reference(borders: Borders, lines: &[Line]) {
+        for algorithm in ClippingAlgorithm::ALL {
+            for line in lines {
+                let clipping = algorithm.clipper().clip(borders, line, &mut OperationCounters::default());
+                let reference = reference_clipping(line, &|point| contains(borders, point));
+
+                assert!(
+                    same_clipping(line, &clipping, &reference, REFERENCE_TOLERANCE),
+                    "{} clips {:?} against {:?} to {:?}, the reference to {:?}",
+                    algorithm.label(), line, borders, clipping, reference
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_lines_match_the_reference() {
+        for borders in WINDOWS {
+            assert_matches_reference(borders, &degenerate_lines(borders));
+        }
+    }
+
+    #[test]
+    fn lines_between_points_around_the_window_match_the_reference() {
+        for borders in WINDOWS {
+            let (l, r, t, b) = borders;
+            let (cx, cy) = ((l + r) / 2.0, (t + b) / 2.0);
+            let ends: Vec<Point> = (0..16)
+                .map(|i| {
+                    let angle = i as f32 * std::f32::consts::TAU / 16.0;
+                    let radius = if i % 2 == 0 { 0.8 } else { 1.5 };
+                    Point::new(cx + angle.cos() * (r - l) * radius, cy + angle.sin() * (t - b) * radius)
+                })
+                .collect();
+
+            let lines: Vec<Line> = ends
+                .iter()
+                .flat_map(|start| ends.iter().map(move |end| (*start, *end)))
+                .collect();
+            assert_matches_reference(borders, &lines);
+        }
+    }
+}
@@ -2,10 +2,14 @@ use nalgebra::Point2;
 
 pub type Point = Point2<f32>;
 pub type Line = (Point, Point);
+pub type Polygon = Vec<Point>;
+// left, right, top, bottom
+pub type Borders = (f32, f32, f32, f32);
 
-#[derive(Debug)]
+// Parts inside the window are ordered along the line
+#[derive(Clone, Debug)]
 pub enum LineClipping {
     Inside,
     Outside,
-    PartlyInside(Line)
+    PartlyInside(Vec<Line>)
 }
\ No newline at end of file
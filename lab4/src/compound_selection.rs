@@ -0,0 +1,385 @@
+use kiss3d::window::Window;
+
+use nalgebra::Point3;
+
+use crate::common::{Point, Line, LineClipping, Borders};
+use crate::clipping::{ClippingAlgorithm, OperationCounters, CLIPPING_EPSILON};
+use crate::selection::RectangleSelection;
+use crate::scene::scene;
+use lab_common::scene_file::color;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref ADD_COLOR: Point3<f32> = color(scene().colors.add);
+    static ref SUBTRACT_COLOR: Point3<f32> = color(scene().colors.subtract);
+    static ref INTERSECT_COLOR: Point3<f32> = color(scene().colors.intersect);
+}
+
+// How a window changes the region made by the windows before it
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum CombineMode {
+    #[default]
+    Add,
+    Subtract,
+    Intersect,
+}
+
+impl CombineMode {
+    pub const ALL: [CombineMode; 3] = [CombineMode::Add, CombineMode::Subtract, CombineMode::Intersect];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CombineMode::Add => "Add",
+            CombineMode::Subtract => "Subtract",
+            CombineMode::Intersect => "Intersect",
+        }
+    }
+
+    fn color(&self) -> &'static Point3<f32> {
+        match self {
+            CombineMode::Add => &ADD_COLOR,
+            CombineMode::Subtract => &SUBTRACT_COLOR,
+            CombineMode::Intersect => &INTERSECT_COLOR,
+        }
+    }
+}
+
+// Sorted disjoint parameter ranges along a line
+type Intervals = Vec<(f32, f32)>;
+
+// Rectangles applied in order to an empty region
+#[derive(Clone, PartialEq)]
+pub struct CompoundSelection {
+    windows: Vec<(RectangleSelection, CombineMode)>,
+}
+
+impl CompoundSelection {
+    pub fn windows(&self) -> impl Iterator<Item = &RectangleSelection> {
+        self.windows.iter().map(|(window, _)| window)
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        self.windows.iter().fold(false, |inside, (window, mode)| match mode {
+            CombineMode::Add => inside || window.contains(point),
+            CombineMode::Subtract => inside && !window.contains(point),
+            CombineMode::Intersect => inside && window.contains(point),
+        })
+    }
+
+    // Only the added windows can grow the region
+    pub fn bounds(&self) -> Borders {
+        self.windows
+            .iter()
+            .filter(|(_, mode)| *mode == CombineMode::Add)
+            .map(|(window, _)| window.borders())
+            .fold(
+                (f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY, f32::INFINITY),
+                |(l, r, t, b), (wl, wr, wt, wb)| (l.min(wl), r.max(wr), t.max(wt), b.min(wb)),
+            )
+    }
+
+    // Every window clips the line with the algorithm, then the inside intervals are combined
+    pub fn clipping_check(&self, line: &Line, algorithm: ClippingAlgorithm, counters: &mut OperationCounters) -> LineClipping {
+        let length = (line.1 - line.0).norm();
+        if length <= CLIPPING_EPSILON {
+            return if self.contains(&line.0) { LineClipping::Inside } else { LineClipping::Outside };
+        }
+
+        let mut intervals = vec![];
+        for (window, mode) in &self.windows {
+            let window_intervals = to_intervals(line, &window.clipping_check(line, algorithm.clipper(), counters));
+            intervals = match mode {
+                CombineMode::Add => union(&intervals, &window_intervals),
+                CombineMode::Subtract => subtract(&intervals, &window_intervals),
+                CombineMode::Intersect => intersect(&intervals, &window_intervals),
+            };
+        }
+        // Windows sharing a border leave parts of zero length
+        intervals.retain(|(start, end)| (end - start) * length > CLIPPING_EPSILON);
+
+        let point = |t: f32| line.0 + (line.1 - line.0) * t;
+        match intervals.as_slice() {
+            [] => LineClipping::Outside,
+            [(start, end)] if *start * length <= CLIPPING_EPSILON && (1.0 - end) * length <= CLIPPING_EPSILON => LineClipping::Inside,
+            _ => LineClipping::PartlyInside(intervals.iter().map(|(start, end)| (point(*start), point(*end))).collect()),
+        }
+    }
+
+    pub fn draw(&self, window: &mut Window) {
+        for (selection, mode) in &self.windows {
+            selection.draw_colored(window, mode.color());
+        }
+    }
+}
+
+fn to_intervals(line: &Line, clipping: &LineClipping) -> Intervals {
+    let direction = line.1 - line.0;
+    let parameter = |point: &Point| ((point - line.0).dot(&direction) / direction.norm_squared()).clamp(0.0, 1.0);
+
+    match clipping {
+        LineClipping::Inside => vec![(0.0, 1.0)],
+        LineClipping::Outside => vec![],
+        LineClipping::PartlyInside(parts) => {
+            let mut intervals: Intervals = parts
+                .iter()
+                .map(|part| {
+                    let (a, b) = (parameter(&part.0), parameter(&part.1));
+                    (a.min(b), a.max(b))
+                })
+                .collect();
+            intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+            union(&intervals, &[])
+        },
+    }
+}
+
+fn union(first: &[(f32, f32)], second: &[(f32, f32)]) -> Intervals {
+    let mut all: Intervals = first.iter().chain(second.iter()).copied().collect();
+    all.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut result: Intervals = vec![];
+    for (start, end) in all {
+        match result.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => result.push((start, end)),
+        }
+    }
+    result
+}
+
+fn intersect(first: &[(f32, f32)], second: &[(f32, f32)]) -> Intervals {
+    let mut result = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < first.len() && j < second.len() {
+        let start = first[i].0.max(second[j].0);
+        let end = first[i].1.min(second[j].1);
+        if start <= end {
+            result.push((start, end));
+        }
+        if first[i].1 < second[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+// The intervals of `first` with the ones of `second` cut out
+fn subtract(first: &[(f32, f32)], second: &[(f32, f32)]) -> Intervals {
+    let mut complement = vec![];
+    let mut start = 0.0;
+    for (hole_start, hole_end) in second {
+        complement.push((start, *hole_start));
+        start = *hole_end;
+    }
+    complement.push((start, 1.0));
+    complement.retain(|(start, end)| start <= end);
+
+    intersect(first, &complement)
+}
+
+// Windows already placed, and the mode the rectangle being edited will get
+#[derive(Default)]
+pub struct CompoundSelectionBuilder {
+    windows: Vec<(RectangleSelection, CombineMode)>,
+    pub mode: CombineMode,
+}
+
+impl CompoundSelectionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, selection: RectangleSelection) {
+        self.windows.push((selection, self.mode));
+    }
+
+    pub fn clear(&mut self) {
+        self.windows.clear();
+    }
+
+    pub fn corners(&self) -> impl Iterator<Item = Point> + '_ {
+        self.windows.iter().flat_map(|(selection, _)| selection.points().iter().copied())
+    }
+
+    // The rectangle being edited goes last
+    pub fn build(&self, active: Option<RectangleSelection>) -> Option<CompoundSelection> {
+        let mut windows = self.windows.clone();
+        windows.extend(active.map(|selection| (selection, self.mode)));
+
+        (!windows.is_empty()).then_some(CompoundSelection { windows })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Samples along the line for the reference
+    const SAMPLES: usize = 4000;
+
+    fn window(l: f32, b: f32, r: f32, t: f32) -> RectangleSelection {
+        RectangleSelection::new(Point::new(l, t), Point::new(r, b))
+    }
+
+    fn compound(windows: &[(RectangleSelection, CombineMode)]) -> CompoundSelection {
+        CompoundSelection { windows: windows.to_vec() }
+    }
+
+    fn line(x0: f32, y0: f32, x1: f32, y1: f32) -> Line {
+        (Point::new(x0, y0), Point::new(x1, y1))
+    }
+
+    #[test]
+    fn union_of_intervals() {
+        assert_eq!(union(&[(0.0, 0.3)], &[(0.3, 0.5)]), vec![(0.0, 0.5)]);
+        assert_eq!(union(&[(0.1, 0.9)], &[(0.2, 0.4)]), vec![(0.1, 0.9)]);
+        assert_eq!(union(&[(0.6, 0.8)], &[(0.1, 0.2)]), vec![(0.1, 0.2), (0.6, 0.8)]);
+        assert_eq!(union(&[(0.0, 0.2), (0.5, 0.7)], &[(0.1, 0.6)]), vec![(0.0, 0.7)]);
+        assert_eq!(union(&[], &[(0.1, 0.2)]), vec![(0.1, 0.2)]);
+        assert_eq!(union(&[(0.1, 0.2)], &[]), vec![(0.1, 0.2)]);
+        assert_eq!(union(&[], &[]), vec![]);
+    }
+
+    #[test]
+    fn intersection_of_intervals() {
+        // Touching intervals leave a part of zero length, which the clipping drops
+        assert_eq!(intersect(&[(0.0, 0.3)], &[(0.3, 0.5)]), vec![(0.3, 0.3)]);
+        assert_eq!(intersect(&[(0.1, 0.9)], &[(0.2, 0.4)]), vec![(0.2, 0.4)]);
+        assert_eq!(intersect(&[(0.2, 0.4)], &[(0.1, 0.9)]), vec![(0.2, 0.4)]);
+        assert_eq!(intersect(&[(0.6, 0.8)], &[(0.1, 0.2)]), vec![]);
+        assert_eq!(intersect(&[(0.0, 0.2), (0.5, 0.7)], &[(0.1, 0.6)]), vec![(0.1, 0.2), (0.5, 0.6)]);
+        assert_eq!(intersect(&[], &[(0.1, 0.2)]), vec![]);
+        assert_eq!(intersect(&[(0.1, 0.2)], &[]), vec![]);
+    }
+
+    #[test]
+    fn subtraction_of_intervals() {
+        assert_eq!(subtract(&[(0.0, 0.3)], &[(0.3, 0.5)]), vec![(0.0, 0.3)]);
+        assert_eq!(subtract(&[(0.1, 0.9)], &[(0.2, 0.4)]), vec![(0.1, 0.2), (0.4, 0.9)]);
+        assert_eq!(subtract(&[(0.6, 0.8)], &[(0.1, 0.2)]), vec![(0.6, 0.8)]);
+        assert_eq!(subtract(&[(0.0, 0.2), (0.5, 0.7)], &[(0.1, 0.6)]), vec![(0.0, 0.1), (0.6, 0.7)]);
+        assert_eq!(subtract(&[(0.1, 0.2)], &[]), vec![(0.1, 0.2)]);
+        assert_eq!(subtract(&[], &[(0.1, 0.2)]), vec![]);
+
+        // Nothing but parts of zero length is left of a nested interval
+        let rest = subtract(&[(0.2, 0.4)], &[(0.1, 0.9)]);
+        assert!(rest.iter().all(|(start, end)| start == end), "{:?}", rest);
+        let rest = subtract(&[(0.0, 1.0)], &[(0.0, 1.0)]);
+        assert!(rest.iter().all(|(start, end)| start == end), "{:?}", rest);
+    }
+
+    // Away from the ends of the parts, the samples in the region are the ones on a clipped part
+    fn assert_matches_sampling(selection: &CompoundSelection, line: &Line) {
+        let length = (line.1 - line.0).norm();
+        let parameter = |point: &Point| (point - line.0).dot(&(line.1 - line.0)) / (length * length);
+
+        for algorithm in ClippingAlgorithm::ALL {
+            let clipping = selection.clipping_check(line, algorithm, &mut OperationCounters::default());
+            let intervals: Intervals = match &clipping {
+                LineClipping::Inside => vec![(0.0, 1.0)],
+                LineClipping::Outside => vec![],
+                LineClipping::PartlyInside(parts) => parts.iter().map(|(a, b)| (parameter(a), parameter(b))).collect(),
+            };
+            let margin = 2.0 / SAMPLES as f32 + 2.0 * CLIPPING_EPSILON / length;
+
+            for i in 0..=SAMPLES {
+                let t = i as f32 / SAMPLES as f32;
+                if intervals.iter().any(|(start, end)| (t - start).abs() <= margin || (t - end).abs() <= margin) {
+                    continue;
+                }
+
+                let point = line.0 + (line.1 - line.0) * t;
+                assert_eq!(
+                    intervals.iter().any(|(start, end)| *start <= t && t <= *end),
+                    selection.contains(&point),
+                    "{} at {} of {:?}: {:?}",
+                    algorithm.label(), point, line, clipping
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn combined_windows_match_sampling() {
+        let (a, b, c) = (window(0.0, 0.0, 100.0, 100.0), window(50.0, 50.0, 150.0, 150.0), window(25.0, 25.0, 75.0, 75.0));
+        // Sharing the right border of the first one
+        let d = window(100.0, 0.0, 200.0, 100.0);
+        let selections = [
+            compound(&[(a.clone(), CombineMode::Add), (b.clone(), CombineMode::Add)]),
+            compound(&[(a.clone(), CombineMode::Add), (b.clone(), CombineMode::Subtract)]),
+            compound(&[(a.clone(), CombineMode::Add), (b.clone(), CombineMode::Intersect)]),
+            compound(&[(a.clone(), CombineMode::Add), (c.clone(), CombineMode::Subtract)]),
+            compound(&[(a.clone(), CombineMode::Add), (d.clone(), CombineMode::Add)]),
+            compound(&[
+                (a.clone(), CombineMode::Add),
+                (d.clone(), CombineMode::Add),
+                (c.clone(), CombineMode::Subtract),
+                (b.clone(), CombineMode::Intersect),
+            ]),
+            compound(&[
+                (c.clone(), CombineMode::Add),
+                (a.clone(), CombineMode::Subtract),
+                (b.clone(), CombineMode::Add),
+            ]),
+        ];
+        let lines = [
+            line(-10.0, 60.0, 210.0, 60.0),
+            line(-10.0, -10.0, 210.0, 160.0),
+            line(60.0, -10.0, 60.0, 160.0),
+            line(40.0, 40.0, 90.0, 90.0),
+            // along borders
+            line(100.0, -10.0, 100.0, 160.0),
+            line(-10.0, 50.0, 160.0, 50.0),
+            line(160.0, 25.0, -10.0, 25.0),
+        ];
+
+        for selection in &selections {
+            for line in &lines {
+                assert_matches_sampling(selection, line);
+            }
+        }
+    }
+
+    #[test]
+    fn subtracted_hole_splits_a_line_in_two() {
+        let selection = compound(&[
+            (window(0.0, 0.0, 100.0, 100.0), CombineMode::Add),
+            (window(25.0, 25.0, 75.0, 75.0), CombineMode::Subtract),
+        ]);
+        let clipping = selection.clipping_check(&line(-10.0, 50.0, 110.0, 50.0), ClippingAlgorithm::ALL[0], &mut OperationCounters::default());
+
+        let LineClipping::PartlyInside(parts) = &clipping else { panic!("{:?}", clipping) };
+        let xs: Vec<(f32, f32)> = parts.iter().map(|(a, b)| (a.x, b.x)).collect();
+        assert_eq!(xs.len(), 2, "{:?}", xs);
+        for ((start, end), (expected_start, expected_end)) in xs.iter().zip([(0.0, 25.0), (75.0, 100.0)]) {
+            assert!((start - expected_start).abs() < 1e-2 && (end - expected_end).abs() < 1e-2, "{:?}", xs);
+        }
+    }
+
+    #[test]
+    fn windows_touching_end_to_end_keep_the_line_whole() {
+        let selection = compound(&[
+            (window(0.0, 0.0, 100.0, 100.0), CombineMode::Add),
+            (window(100.0, 0.0, 200.0, 100.0), CombineMode::Add),
+        ]);
+        let clipping = selection.clipping_check(&line(10.0, 50.0, 190.0, 50.0), ClippingAlgorithm::ALL[0], &mut OperationCounters::default());
+        assert!(matches!(clipping, LineClipping::Inside), "{:?}", clipping);
+    }
+
+    #[test]
+    fn a_region_starting_without_added_windows_is_empty() {
+        let a = window(0.0, 0.0, 100.0, 100.0);
+        let line = line(-10.0, 50.0, 110.0, 50.0);
+
+        for mode in [CombineMode::Subtract, CombineMode::Intersect] {
+            let selection = compound(&[(a.clone(), mode)]);
+            let clipping = selection.clipping_check(&line, ClippingAlgorithm::ALL[0], &mut OperationCounters::default());
+            assert!(matches!(clipping, LineClipping::Outside), "{}: {:?}", mode.label(), clipping);
+            assert!(!selection.contains(&Point::new(50.0, 50.0)));
+        }
+    }
+}
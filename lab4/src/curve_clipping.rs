@@ -0,0 +1,410 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::common::{Point, Line, LineClipping};
+use crate::clipping::{ClippingAlgorithm, OperationCounters, CLIPPING_EPSILON};
+use crate::selection::Selection;
+
+pub type Polyline = Vec<Point>;
+
+// Polyline pieces, one per run of segments staying inside the window.
+// Every segment is clipped with the chosen algorithm; parts touching end to end are joined.
+pub fn clip_polyline(polyline: &[Point], selection: &Selection, algorithm: ClippingAlgorithm, counters: &mut OperationCounters) -> Vec<Polyline> {
+    let mut pieces: Vec<Polyline> = vec![];
+
+    for segment in polyline.windows(2) {
+        let line = (segment[0], segment[1]);
+        let parts = match selection.clipping_check(&line, algorithm, counters) {
+            LineClipping::Inside => vec![line],
+            LineClipping::Outside => vec![],
+            LineClipping::PartlyInside(parts) => parts,
+        };
+
+        for (start, end) in parts {
+            match pieces.last_mut() {
+                Some(piece) if piece.last().is_some_and(|last| (last - start).norm() <= CLIPPING_EPSILON) => piece.push(end),
+                _ => pieces.push(vec![start, end]),
+            }
+        }
+    }
+
+    pieces
+}
+
+#[derive(Clone, Copy)]
+pub struct CubicBezier {
+    pub points: [Point; 4],
+}
+
+impl CubicBezier {
+    pub fn point(&self, t: f32) -> Point {
+        let [p0, p1, p2, p3] = self.points.map(|p| p.coords);
+        let s = 1.0 - t;
+
+        Point::from(p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t))
+    }
+
+    // The part between two parameters as a curve of its own (de Casteljau)
+    pub fn segment(&self, t0: f32, t1: f32) -> CubicBezier {
+        let head = self.split(t1).0;
+        if t1 <= f32::EPSILON {
+            return head;
+        }
+        head.split(t0 / t1).1
+    }
+
+    fn split(&self, t: f32) -> (CubicBezier, CubicBezier) {
+        let [p0, p1, p2, p3] = self.points;
+        let lerp = |a: Point, b: Point| a + (b - a) * t;
+        let (p01, p12, p23) = (lerp(p0, p1), lerp(p1, p2), lerp(p2, p3));
+        let (p012, p123) = (lerp(p01, p12), lerp(p12, p23));
+        let p0123 = lerp(p012, p123);
+
+        (
+            CubicBezier { points: [p0, p01, p012, p0123] },
+            CubicBezier { points: [p0123, p123, p23, p3] },
+        )
+    }
+
+    // Parameters in [0, 1] where the curve is at the given signed distance from the infinite
+    // line through the edge. That distance is a cubic polynomial of the parameter.
+    fn line_crossings(&self, (e0, e1): &Line, distance: f32) -> Vec<f32> {
+        let [p0, p1, p2, p3] = self.points.map(|p| p.coords.cast::<f64>());
+        let (e0, e1) = (e0.coords.cast::<f64>(), e1.coords.cast::<f64>());
+        let direction = e1 - e0;
+        let normal = nalgebra::Vector2::new(-direction.y, direction.x).normalize();
+
+        // Power basis coefficients of B(t)
+        let a = -p0 + p1 * 3.0 - p2 * 3.0 + p3;
+        let b = p0 * 3.0 - p1 * 6.0 + p2 * 3.0;
+        let c = (p1 - p0) * 3.0;
+        let d = p0 - e0 - normal * distance as f64;
+
+        cubic_roots(normal.dot(&a), normal.dot(&b), normal.dot(&c), normal.dot(&d))
+            .into_iter()
+            .filter(|t| (0.0..=1.0).contains(t))
+            .map(|t| t as f32)
+            .collect()
+    }
+}
+
+// Inside parameter intervals of the curve. The curve is cut where it crosses the lines of the
+// window boundary, and each arc between two crossings is entirely inside or outside the window,
+// which its middle point tells. The lines at the clipping epsilon on both sides of the borders
+// cut it too, so an arc running along a border is classified like the line clippers do.
+pub fn clip_bezier(curve: &CubicBezier, selection: &Selection) -> Vec<(f32, f32)> {
+    let mut parameters: Vec<f32> = selection
+        .boundary()
+        .iter()
+        .filter(|(e0, e1)| (e1 - e0).norm() > f32::EPSILON)
+        .flat_map(|edge| [-CLIPPING_EPSILON, 0.0, CLIPPING_EPSILON].map(|distance| curve.line_crossings(edge, distance)))
+        .flatten()
+        .chain([0.0, 1.0])
+        .collect();
+    parameters.sort_by(|a, b| a.total_cmp(b));
+    parameters.dedup_by(|a, b| (*a - *b).abs() <= f32::EPSILON);
+
+    let mut intervals: Vec<(f32, f32)> = vec![];
+    for arc in parameters.windows(2) {
+        let (t0, t1) = (arc[0], arc[1]);
+        if !selection.contains(&curve.point((t0 + t1) / 2.0)) {
+            continue;
+        }
+
+        match intervals.last_mut() {
+            Some(last) if last.1 == t0 => last.1 = t1,
+            _ => intervals.push((t0, t1)),
+        }
+    }
+
+    intervals
+}
+
+// Real roots of a t^3 + b t^2 + c t + d, by Cardano's formula or its trigonometric form
+fn cubic_roots(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    let scale = a.abs().max(b.abs()).max(c.abs()).max(d.abs());
+    if scale == 0.0 {
+        return vec![];
+    }
+    let (a, b, c, d) = (a / scale, b / scale, c / scale, d / scale);
+
+    if a.abs() <= 1e-9 {
+        return quadratic_roots(b, c, d);
+    }
+
+    // Depressed cubic x^3 + p x + q with t = x - b / 3a
+    let (b, c, d) = (b / a, c / a, d / a);
+    let shift = b / 3.0;
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+
+    let roots = if discriminant > 1e-12 {
+        let root = discriminant.sqrt();
+        vec![(-q / 2.0 + root).cbrt() + (-q / 2.0 - root).cbrt()]
+    } else if discriminant < -1e-12 {
+        let radius = 2.0 * (-p / 3.0).sqrt();
+        let angle = (3.0 * q / (p * radius)).clamp(-1.0, 1.0).acos() / 3.0;
+        (0..3).map(|k| radius * (angle - 2.0 * std::f64::consts::PI * k as f64 / 3.0).cos()).collect()
+    } else {
+        // A double root, where the curve touches the line
+        let root = (-q / 2.0).cbrt();
+        vec![2.0 * root, -root]
+    };
+
+    // One Newton step removes most of the cancellation error
+    let polynomial = |t: f64| ((t + b) * t + c) * t + d;
+    let derivative = |t: f64| (3.0 * t + 2.0 * b) * t + c;
+    roots
+        .into_iter()
+        .map(|x| x - shift)
+        .map(|t| {
+            let slope = derivative(t);
+            if slope.abs() > 1e-12 { t - polynomial(t) / slope } else { t }
+        })
+        .collect()
+}
+
+fn quadratic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() <= 1e-9 {
+        return if b.abs() <= 1e-12 { vec![] } else { vec![-c / b] };
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    // Without subtracting close numbers
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        vec![0.0]
+    } else {
+        vec![q / a, c / q]
+    }
+}
+
+// Inside pieces of the polylines and the curves, in a form other programs can read
+#[derive(Serialize, Default)]
+pub struct ClippedPieces {
+    pub polylines: Vec<Vec<[f32; 2]>>,
+    pub curves: Vec<[[f32; 2]; 4]>,
+}
+
+impl ClippedPieces {
+    pub fn add_polyline(&mut self, polyline: &[Point]) {
+        self.polylines.push(polyline.iter().map(|p| [p.x, p.y]).collect());
+    }
+
+    pub fn add_curve(&mut self, curve: &CubicBezier) {
+        self.curves.push(curve.points.map(|p| [p.x, p.y]));
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::RectangleSelection;
+
+    // Parameter samples of the dense reference
+    const SAMPLES: usize = 20_000;
+
+    fn curve(points: [(f32, f32); 4]) -> CubicBezier {
+        CubicBezier { points: points.map(|(x, y)| Point::new(x, y)) }
+    }
+
+    fn polynomial(roots: &[f64]) -> (f64, f64, f64, f64) {
+        roots.iter().fold((0.0, 0.0, 0.0, 1.0), |(_, b, c, d), root| (b, c - root * b, d - root * c, -root * d))
+    }
+
+    fn assert_roots(mut roots: Vec<f64>, expected: &[f64], tolerance: f64) {
+        roots.sort_by(|a, b| a.total_cmp(b));
+        assert_eq!(roots.len(), expected.len(), "{:?} instead of {:?}", roots, expected);
+        for (root, expected) in roots.iter().zip(expected.iter()) {
+            assert!((root - expected).abs() <= tolerance, "{:?} instead of {:?}", roots, expected);
+        }
+    }
+
+    #[test]
+    fn cubic_with_three_real_roots() {
+        for roots in [[0.1, 0.5, 0.9], [-2.0, 0.25, 3.5], [0.0, 0.001, 1.0]] {
+            let (a, b, c, d) = polynomial(&roots);
+            assert_roots(cubic_roots(a, b, c, d), &roots, 1e-9);
+            assert_roots(cubic_roots(-3.0 * a, -3.0 * b, -3.0 * c, -3.0 * d), &roots, 1e-9);
+        }
+    }
+
+    #[test]
+    fn cubic_with_repeated_roots() {
+        let (a, b, c, d) = polynomial(&[0.5, 0.5, 0.2]);
+        assert_roots(cubic_roots(a, b, c, d), &[0.2, 0.5], 1e-6);
+
+        // A triple root is only known to about the cube root of the rounding error
+        let (a, b, c, d) = polynomial(&[0.3, 0.3, 0.3]);
+        assert_roots(cubic_roots(a, b, c, d), &[0.3, 0.3], 1e-5);
+    }
+
+    #[test]
+    fn cubic_with_one_real_root() {
+        // (t - 0.4)(t^2 + 1)
+        assert_roots(cubic_roots(1.0, -0.4, 1.0, -0.4), &[0.4], 1e-9);
+    }
+
+    #[test]
+    fn cubic_with_a_close_to_zero_falls_back_to_the_quadratic() {
+        // (t - 0.25)(t - 0.75) and a vanishing cubic term
+        assert_roots(cubic_roots(1e-12, 1.0, -1.0, 0.1875), &[0.25, 0.75], 1e-9);
+        assert_roots(cubic_roots(0.0, 0.0, 2.0, -1.0), &[0.5], 1e-12);
+        assert!(cubic_roots(0.0, 0.0, 0.0, 1.0).is_empty());
+        assert!(cubic_roots(0.0, 0.0, 0.0, 0.0).is_empty());
+    }
+
+    #[test]
+    fn quadratic_roots_without_cancellation() {
+        assert_roots(quadratic_roots(1.0, -1.0, 0.1875), &[0.25, 0.75], 1e-12);
+        assert_roots(quadratic_roots(1.0, -1.0, 0.25), &[0.5, 0.5], 1e-12);
+        assert_roots(quadratic_roots(1.0, -1.0, 0.0), &[0.0, 1.0], 1e-12);
+        assert_roots(quadratic_roots(2.0, 0.0, 0.0), &[0.0], 1e-12);
+        assert!(quadratic_roots(1.0, 0.0, 1.0).is_empty());
+
+        // Roots far apart, where the usual formula loses the small one
+        assert_roots(quadratic_roots(1.0, -1e8, 1.0), &[1e-8, 1e8], 1e-15 * 1e8);
+    }
+
+    #[test]
+    fn split_pieces_join_continuously() {
+        let curve = curve([(0.0, 0.0), (30.0, 100.0), (70.0, -50.0), (100.0, 20.0)]);
+
+        for t in [0.0, 0.1, 0.5, 0.77, 1.0] {
+            let (head, tail) = curve.split(t);
+            assert_eq!(head.points[3], tail.points[0]);
+            assert!((head.points[3] - curve.point(t)).norm() < 1e-4);
+
+            // The same curve on both sides, with the tangents of the whole one
+            for s in [0.0, 0.3, 0.6, 1.0] {
+                assert!((head.point(s) - curve.point(s * t)).norm() < 1e-3, "t = {}, s = {}", t, s);
+                assert!((tail.point(s) - curve.point(t + s * (1.0 - t))).norm() < 1e-3, "t = {}, s = {}", t, s);
+            }
+            let (head_tangent, tail_tangent) = (head.points[3] - head.points[2], tail.points[1] - tail.points[0]);
+            assert!(head_tangent.perp(&tail_tangent).abs() < 1e-2, "t = {}", t);
+            assert!((head_tangent * (1.0 - t) - tail_tangent * t).norm() < 1e-3, "t = {}", t);
+        }
+
+        let segment = curve.segment(0.2, 0.6);
+        assert!((segment.point(0.0) - curve.point(0.2)).norm() < 1e-3);
+        assert!((segment.point(0.5) - curve.point(0.4)).norm() < 1e-3);
+        assert!((segment.point(1.0) - curve.point(0.6)).norm() < 1e-3);
+    }
+
+    #[test]
+    fn line_crossings_match_sign_changes() {
+        let s_curve = curve([(0.0, -20.0), (40.0, 150.0), (60.0, -150.0), (100.0, 20.0)]);
+        let cases = [
+            // three crossings, with the line going either way
+            (s_curve, (Point::new(-10.0, 0.0), Point::new(110.0, 0.0)), 3),
+            (s_curve, (Point::new(110.0, 5.0), Point::new(-10.0, 5.0)), 3),
+            (s_curve, (Point::new(-10.0, -30.0), Point::new(110.0, -30.0)), 2),
+            (s_curve, (Point::new(50.0, 100.0), Point::new(50.0, -100.0)), 1),
+            (s_curve, (Point::new(0.0, 200.0), Point::new(100.0, 200.0)), 0),
+        ];
+
+        for (curve, edge, expected) in cases {
+            let (e0, e1) = edge;
+            let normal = Point::new(-(e1 - e0).y, (e1 - e0).x).coords.normalize();
+            let distance = |t: f32| (curve.point(t) - e0).dot(&normal);
+
+            let crossings = curve.line_crossings(&edge, 0.0);
+            assert_eq!(crossings.len(), expected, "{:?}", crossings);
+            for t in &crossings {
+                assert!(distance(*t).abs() < 1e-2, "distance {} at {}", distance(*t), t);
+            }
+
+            let sign_changes = (0..SAMPLES)
+                .filter(|i| {
+                    let (t0, t1) = (*i as f32 / SAMPLES as f32, (*i + 1) as f32 / SAMPLES as f32);
+                    distance(t0).signum() != distance(t1).signum()
+                })
+                .count();
+            assert_eq!(sign_changes, expected);
+
+            // Lines shifted along the normal
+            for t in curve.line_crossings(&edge, 5.0) {
+                assert!((distance(t) - 5.0).abs() < 1e-2, "distance {} at {}", distance(t), t);
+            }
+        }
+    }
+
+    // Away from the ends of the intervals, the samples inside the window are the ones inside an interval
+    fn assert_matches_sampling(curve: &CubicBezier, selection: &Selection) {
+        let intervals = clip_bezier(curve, selection);
+        let step = 1.0 / SAMPLES as f32;
+
+        for window in intervals.windows(2) {
+            assert!(window[0].1 < window[1].0, "{:?}", intervals);
+        }
+        for (t0, t1) in &intervals {
+            assert!(0.0 <= *t0 && t0 < t1 && *t1 <= 1.0, "{:?}", intervals);
+        }
+
+        for i in 0..=SAMPLES {
+            let t = i as f32 * step;
+            let near_end = intervals.iter().any(|(t0, t1)| (t - t0).abs() <= 2.0 * step || (t - t1).abs() <= 2.0 * step);
+            if near_end {
+                continue;
+            }
+
+            let in_interval = intervals.iter().any(|(t0, t1)| *t0 <= t && t <= *t1);
+            assert_eq!(
+                in_interval,
+                selection.contains(&curve.point(t)),
+                "at t = {}, {} for {:?}, intervals {:?}",
+                t, curve.point(t), curve.points, intervals
+            );
+        }
+    }
+
+    #[test]
+    fn clipped_intervals_match_sampling() {
+        let selection = Selection::Rectangle(RectangleSelection::new(Point::new(20.0, 80.0), Point::new(80.0, 20.0)));
+        let curves = [
+            // through the window several times
+            curve([(0.0, 50.0), (40.0, 200.0), (60.0, -100.0), (100.0, 50.0)]),
+            curve([(0.0, 0.0), (100.0, 0.0), (0.0, 100.0), (100.0, 100.0)]),
+            curve([(50.0, 50.0), (150.0, 90.0), (-50.0, 90.0), (50.0, 10.0)]),
+            // inside and outside
+            curve([(30.0, 30.0), (40.0, 70.0), (60.0, 30.0), (70.0, 70.0)]),
+            curve([(0.0, 0.0), (10.0, 100.0), (0.0, 100.0), (-10.0, 0.0)]),
+            // tangent to the top border from inside and from outside
+            curve([(30.0, 50.0), (30.0, 90.0), (70.0, 90.0), (70.0, 50.0)]),
+            curve([(30.0, 130.0), (30.0, 130.0 - 200.0 / 3.0), (70.0, 130.0 - 200.0 / 3.0), (70.0, 130.0)]),
+            // along a border, and crossing the line of a border outside the window
+            curve([(0.0, 80.0), (30.0, 80.0), (60.0, 80.0), (100.0, 80.0)]),
+            curve([(20.0, 0.0), (20.0, 30.0), (20.0, 60.0), (20.0, 100.0)]),
+            curve([(90.0, 0.0), (110.0, 30.0), (70.0, 60.0), (90.0, 100.0)]),
+        ];
+
+        for curve in &curves {
+            assert_matches_sampling(curve, &selection);
+        }
+    }
+
+    #[test]
+    fn curves_on_a_border_or_inside_are_kept_whole() {
+        let selection = Selection::Rectangle(RectangleSelection::new(Point::new(20.0, 80.0), Point::new(80.0, 20.0)));
+
+        let on_border = curve([(20.0, 80.0), (40.0, 80.0), (60.0, 80.0), (80.0, 80.0)]);
+        assert_eq!(clip_bezier(&on_border, &selection), vec![(0.0, 1.0)]);
+
+        let touching = curve([(30.0, 50.0), (30.0, 90.0), (70.0, 90.0), (70.0, 50.0)]);
+        assert_eq!(clip_bezier(&touching, &selection), vec![(0.0, 1.0)]);
+
+        let outside = curve([(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)]);
+        assert!(clip_bezier(&outside, &selection).is_empty());
+    }
+}
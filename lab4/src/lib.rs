@@ -0,0 +1,11 @@
+// Clipping code shared by the application and the benchmarks. The selections draw themselves
+// and read the scene colors, so the crate depends on kiss3d, but nothing here opens a window.
+pub mod common;
+pub mod clipping;
+pub mod polygon_clipping;
+pub mod selection;
+pub mod compound_selection;
+pub mod line_set;
+pub mod line_index;
+pub mod curve_clipping;
+pub mod scene;
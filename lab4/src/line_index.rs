@@ -0,0 +1,252 @@
+use crate::common::{Point, Line, Borders};
+use crate::clipping::CLIPPING_EPSILON;
+
+// A node splits once it holds more lines than that
+const NODE_CAPACITY: usize = 16;
+
+// Quadrants deeper than that would be smaller than a pixel on any screen
+const MAX_DEPTH: usize = 12;
+
+// Axis-aligned bounding box
+#[derive(Clone, Copy)]
+struct Bounds {
+    min: Point,
+    max: Point,
+}
+
+impl Bounds {
+    fn of_line(line: &Line) -> Self {
+        Self {
+            min: Point::new(line.0.x.min(line.1.x), line.0.y.min(line.1.y)),
+            max: Point::new(line.0.x.max(line.1.x), line.0.y.max(line.1.y)),
+        }
+    }
+
+    fn contains(&self, other: &Bounds) -> bool {
+        self.min.x <= other.min.x && other.max.x <= self.max.x
+            && self.min.y <= other.min.y && other.max.y <= self.max.y
+    }
+
+    // Lines touching the borders within the clipping epsilon are kept for the clipper
+    fn is_outside(&self, (l, r, t, b): Borders) -> bool {
+        let e = CLIPPING_EPSILON;
+        self.max.x < l - e || self.min.x > r + e || self.max.y < b - e || self.min.y > t + e
+    }
+
+    fn is_inside(&self, (l, r, t, b): Borders) -> bool {
+        l <= self.min.x && self.max.x <= r && b <= self.min.y && self.max.y <= t
+    }
+
+    fn quadrants(&self) -> [Bounds; 4] {
+        let center = Point::new((self.min.x + self.max.x) / 2.0, (self.min.y + self.max.y) / 2.0);
+
+        [
+            Bounds { min: self.min, max: center },
+            Bounds { min: Point::new(center.x, self.min.y), max: Point::new(self.max.x, center.y) },
+            Bounds { min: Point::new(self.min.x, center.y), max: Point::new(center.x, self.max.y) },
+            Bounds { min: center, max: self.max },
+        ]
+    }
+}
+
+struct Node {
+    bounds: Bounds,
+    // Index of the first of the four consecutive children
+    children: Option<usize>,
+    // Lines that fit in this node but in none of its children
+    lines: Vec<usize>,
+}
+
+impl Node {
+    fn new(bounds: Bounds) -> Self {
+        Self { bounds, children: None, lines: vec![] }
+    }
+}
+
+// Lines split by their bounding boxes against a rectangle; the lines in neither list are outside
+pub struct LineQuery {
+    pub inside: Vec<usize>,
+    pub crossing: Vec<usize>,
+}
+
+// Quadtree keeping every line in the smallest quadrant holding its whole bounding box (MX-CIF),
+// so long lines stay near the root instead of being copied into every cell they cross
+pub struct LineIndex {
+    nodes: Vec<Node>,
+    line_bounds: Vec<Bounds>,
+}
+
+impl LineIndex {
+    pub fn new(lines: &[Line]) -> Self {
+        let line_bounds: Vec<Bounds> = lines.iter().map(Bounds::of_line).collect();
+        let root_bounds = line_bounds
+            .iter()
+            .copied()
+            .reduce(|a, b| Bounds {
+                min: Point::new(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+                max: Point::new(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+            })
+            .unwrap_or(Bounds { min: Point::origin(), max: Point::origin() });
+
+        let mut index = Self { nodes: vec![Node::new(root_bounds)], line_bounds };
+        for line in 0..index.line_bounds.len() {
+            index.insert(0, line, 0);
+        }
+        index
+    }
+
+    fn insert(&mut self, node: usize, line: usize, depth: usize) {
+        let bounds = self.line_bounds[line];
+
+        if let Some(first_child) = self.nodes[node].children {
+            match (first_child..first_child + 4).find(|child| self.nodes[*child].bounds.contains(&bounds)) {
+                Some(child) => self.insert(child, line, depth + 1),
+                None => self.nodes[node].lines.push(line),
+            }
+            return;
+        }
+
+        self.nodes[node].lines.push(line);
+        if self.nodes[node].lines.len() > NODE_CAPACITY && depth < MAX_DEPTH {
+            let first_child = self.nodes.len();
+            let quadrants = self.nodes[node].bounds.quadrants();
+            self.nodes.extend(quadrants.into_iter().map(Node::new));
+            self.nodes[node].children = Some(first_child);
+
+            for line in std::mem::take(&mut self.nodes[node].lines) {
+                self.insert(node, line, depth);
+            }
+        }
+    }
+
+    // Whole quadrants inside or outside the rectangle are taken without looking at their lines
+    pub fn query(&self, borders: Borders) -> LineQuery {
+        let mut query = LineQuery { inside: vec![], crossing: vec![] };
+        let mut stack = vec![0];
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.bounds.is_outside(borders) {
+                continue;
+            }
+            if node.bounds.is_inside(borders) {
+                self.collect(node, &mut query.inside);
+                continue;
+            }
+
+            for &line in &node.lines {
+                let bounds = &self.line_bounds[line];
+                if bounds.is_inside(borders) {
+                    query.inside.push(line);
+                } else if !bounds.is_outside(borders) {
+                    query.crossing.push(line);
+                }
+            }
+            if let Some(first_child) = node.children {
+                stack.extend(first_child..first_child + 4);
+            }
+        }
+
+        query
+    }
+
+    fn collect(&self, node: &Node, lines: &mut Vec<usize>) {
+        lines.extend_from_slice(&node.lines);
+        if let Some(first_child) = node.children {
+            for child in first_child..first_child + 4 {
+                self.collect(&self.nodes[child], lines);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::clipping::{ClippingAlgorithm, OperationCounters};
+    use crate::common::LineClipping;
+    use crate::line_set::{generate_lines, Distribution};
+
+    // The root spans [-400, 400] x [-300, 300], so quadrant borders fall on these
+    const NODE_XS: [f32; 5] = [-400.0, -200.0, 0.0, 200.0, 400.0];
+    const NODE_YS: [f32; 5] = [-300.0, -150.0, 0.0, 150.0, 300.0];
+
+    fn lines() -> Vec<Line> {
+        let mut lines = vec![(Point::new(-400.0, -300.0), Point::new(400.0, 300.0))];
+        for (seed, distribution) in Distribution::ALL.iter().enumerate() {
+            lines.extend(generate_lines(seed as u64, *distribution, 300, -400.0..400.0, -300.0..300.0));
+        }
+
+        // Along the quadrant borders, and short ones ending on them
+        for x in NODE_XS {
+            lines.push((Point::new(x, -300.0), Point::new(x, 300.0)));
+            lines.push((Point::new(x, 10.0), Point::new(x, 20.0)));
+            lines.push((Point::new(x - 5.0, 75.0), Point::new(x, 75.0)));
+        }
+        for y in NODE_YS {
+            lines.push((Point::new(-400.0, y), Point::new(400.0, y)));
+            lines.push((Point::new(10.0, y), Point::new(20.0, y)));
+            lines.push((Point::new(100.0, y), Point::new(100.0, y + 5.0)));
+        }
+        for (x, y) in NODE_XS.iter().zip(NODE_YS.iter()) {
+            lines.push((Point::new(*x, *y), Point::new(*x, *y)));
+        }
+
+        lines
+    }
+
+    fn windows() -> Vec<Borders> {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut windows: Vec<Borders> = (0..200)
+            .map(|_| {
+                let (x0, x1) = (rng.gen_range(-450.0..450.0), rng.gen_range(-450.0..450.0));
+                let (y0, y1) = (rng.gen_range(-350.0..350.0), rng.gen_range(-350.0..350.0));
+                (f32::min(x0, x1), f32::max(x0, x1), f32::max(y0, y1), f32::min(y0, y1))
+            })
+            .collect();
+
+        // Borders on the quadrant borders, and windows reduced to a line or a point
+        for (i, &l) in NODE_XS.iter().enumerate() {
+            for (j, &b) in NODE_YS.iter().enumerate() {
+                windows.push((l, NODE_XS[(i + 1).min(4)], NODE_YS[(j + 1).min(4)], b));
+                windows.push((l, l, NODE_YS[(j + 2).min(4)], b));
+                windows.push((l, l, b, b));
+            }
+        }
+
+        windows
+    }
+
+    #[test]
+    fn query_finds_every_line_the_clipper_keeps() {
+        let lines = lines();
+        let index = LineIndex::new(&lines);
+        assert!(index.nodes.len() > 1, "the lines should split the root");
+        let clipper = ClippingAlgorithm::ALL[0].clipper();
+
+        for borders in windows() {
+            let query = index.query(borders);
+
+            let mut found = vec![false; lines.len()];
+            for &line in query.inside.iter().chain(query.crossing.iter()) {
+                assert!(!found[line], "line {} found twice in {:?}", line, borders);
+                found[line] = true;
+            }
+
+            for (i, line) in lines.iter().enumerate() {
+                let clipping = clipper.clip(borders, line, &mut OperationCounters::default());
+                if !matches!(clipping, LineClipping::Outside) {
+                    assert!(found[i], "{:?} is missing from the query of {:?}", line, borders);
+                }
+            }
+
+            // The application takes these as inside without clipping them
+            for &i in &query.inside {
+                assert!(Bounds::of_line(&lines[i]).is_inside(borders), "{:?} is not inside {:?}", lines[i], borders);
+            }
+        }
+    }
+}
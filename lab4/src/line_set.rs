@@ -0,0 +1,372 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::common::{Point, Line};
+
+// Number of cluster centers of the clustered distribution
+const CLUSTERS_COUNT: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Distribution {
+    Uniform,
+    Clustered,
+    AxisAligned,
+    Short,
+    Long,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 5] = [
+        Distribution::Uniform,
+        Distribution::Clustered,
+        Distribution::AxisAligned,
+        Distribution::Short,
+        Distribution::Long,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Distribution::Uniform => "Uniform",
+            Distribution::Clustered => "Clustered",
+            Distribution::AxisAligned => "Axis-aligned",
+            Distribution::Short => "Short",
+            Distribution::Long => "Long",
+        }
+    }
+}
+
+// The same seed, distribution, count and area always give the same lines
+pub fn generate_lines(seed: u64, distribution: Distribution, count: usize, x_range: Range<f32>, y_range: Range<f32>) -> Vec<Line> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    if x_range.is_empty() || y_range.is_empty() {
+        return vec![];
+    }
+
+    let (width, height) = (x_range.end - x_range.start, y_range.end - y_range.start);
+    let clamp = |p: Point| Point::new(
+        p.x.clamp(x_range.start, x_range.end),
+        p.y.clamp(y_range.start, y_range.end),
+    );
+    let random_point = |rng: &mut StdRng| Point::new(
+        rng.gen_range(x_range.clone()),
+        rng.gen_range(y_range.clone()),
+    );
+    // Random direction and length between the given parts of the area size
+    let random_segment = |rng: &mut StdRng, min_part: f32, max_part: f32| {
+        let start = random_point(rng);
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let length = rng.gen_range(min_part..max_part) * width.min(height);
+        (start, clamp(Point::new(start.x + length * angle.cos(), start.y + length * angle.sin())))
+    };
+
+    let centers: Vec<Point> = (0..CLUSTERS_COUNT).map(|_| random_point(&mut rng)).collect();
+    let deviation = width.min(height) / 12.0;
+
+    (0..count)
+        .map(|_| match distribution {
+            Distribution::Uniform => (random_point(&mut rng), random_point(&mut rng)),
+            Distribution::Clustered => {
+                let center = centers[rng.gen_range(0..centers.len())];
+                let mut near_center = || {
+                    let (dx, dy) = gaussian_pair(&mut rng);
+                    clamp(Point::new(center.x + dx * deviation, center.y + dy * deviation))
+                };
+                (near_center(), near_center())
+            },
+            Distribution::AxisAligned => {
+                let start = random_point(&mut rng);
+                if rng.gen_bool(0.5) {
+                    (start, Point::new(rng.gen_range(x_range.clone()), start.y))
+                } else {
+                    (start, Point::new(start.x, rng.gen_range(y_range.clone())))
+                }
+            },
+            Distribution::Short => random_segment(&mut rng, 0.01, 0.05),
+            Distribution::Long => random_segment(&mut rng, 0.5, 1.0),
+        })
+        .collect()
+}
+
+// Two independent standard normal values (Box-Muller)
+fn gaussian_pair(rng: &mut StdRng) -> (f32, f32) {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen_range(0.0..1.0);
+    let radius = (-2.0 * u1.ln()).sqrt();
+    let angle = std::f32::consts::TAU * u2;
+
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+#[derive(Clone, Copy)]
+pub enum LineSetFormat {
+    Json,
+    Csv,
+}
+
+impl LineSetFormat {
+    pub const ALL: [LineSetFormat; 2] = [LineSetFormat::Json, LineSetFormat::Csv];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineSetFormat::Json => "JSON",
+            LineSetFormat::Csv => "CSV",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LineSetFormat::Json => "json",
+            LineSetFormat::Csv => "csv",
+        }
+    }
+}
+
+// Lines with the selection rectangle corners and how they were generated, to reproduce a case
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+pub struct LineSet {
+    pub seed: u64,
+    pub distribution: Distribution,
+    // Area the lines were generated in, start and end
+    pub x_range: [f32; 2],
+    pub y_range: [f32; 2],
+    pub lines: Vec<[f32; 4]>,
+    pub selection: Option<[f32; 4]>,
+}
+
+impl LineSet {
+    pub fn new(
+        seed: u64,
+        distribution: Distribution,
+        x_range: &Range<f32>,
+        y_range: &Range<f32>,
+        lines: &[Line],
+        selection: Option<(Point, Point)>
+    ) -> Self {
+        Self {
+            seed,
+            distribution,
+            x_range: [x_range.start, x_range.end],
+            y_range: [y_range.start, y_range.end],
+            lines: lines.iter().map(|(a, b)| [a.x, a.y, b.x, b.y]).collect(),
+            selection: selection.map(|(a, b)| [a.x, a.y, b.x, b.y]),
+        }
+    }
+
+    pub fn x_range(&self) -> Range<f32> {
+        self.x_range[0]..self.x_range[1]
+    }
+
+    pub fn y_range(&self) -> Range<f32> {
+        self.y_range[0]..self.y_range[1]
+    }
+
+    pub fn lines(&self) -> Vec<Line> {
+        self.lines.iter().map(|l| (Point::new(l[0], l[1]), Point::new(l[2], l[3]))).collect()
+    }
+
+    pub fn selection(&self) -> Option<(Point, Point)> {
+        self.selection.map(|s| (Point::new(s[0], s[1]), Point::new(s[2], s[3])))
+    }
+
+    pub fn save(&self, path: &Path, format: LineSetFormat) -> Result<(), String> {
+        let text = match format {
+            LineSetFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string())?,
+            LineSetFormat::Csv => self.to_csv(),
+        };
+
+        fs::write(path, text).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path, format: LineSetFormat) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        match format {
+            LineSetFormat::Json => serde_json::from_str(&text).map_err(|e| e.to_string()),
+            LineSetFormat::Csv => Self::from_csv(&text),
+        }
+    }
+
+    // One record per line or selection, the seed, the distribution and the area go in the header comment
+    fn to_csv(&self) -> String {
+        let mut csv = format!(
+            "# seed={} distribution={:?} x_range={}..{} y_range={}..{}\nkind,x1,y1,x2,y2\n",
+            self.seed, self.distribution, self.x_range[0], self.x_range[1], self.y_range[0], self.y_range[1]
+        );
+
+        for (kind, [x1, y1, x2, y2]) in self.selection.iter().map(|s| ("selection", s)).chain(self.lines.iter().map(|l| ("line", l))) {
+            csv += &format!("{},{},{},{},{}\n", kind, x1, y1, x2, y2);
+        }
+
+        csv
+    }
+
+    fn from_csv(text: &str) -> Result<Self, String> {
+        let mut line_set = LineSet {
+            seed: 0,
+            distribution: Distribution::Uniform,
+            x_range: [0.0; 2],
+            y_range: [0.0; 2],
+            lines: vec![],
+            selection: None,
+        };
+        let (mut has_x_range, mut has_y_range) = (false, false);
+
+        for (index, row) in text.lines().enumerate() {
+            let row = row.trim();
+            if let Some(comment) = row.strip_prefix('#') {
+                for (key, value) in comment.split_whitespace().filter_map(|pair| pair.split_once('=')) {
+                    match key {
+                        "seed" => line_set.seed = value.parse().map_err(|_| format!("Row {}: invalid seed", index + 1))?,
+                        "distribution" => {
+                            line_set.distribution = *Distribution::ALL
+                                .iter()
+                                .find(|d| format!("{:?}", d) == value)
+                                .ok_or(format!("Row {}: unknown distribution {}", index + 1, value))?
+                        },
+                        "x_range" => {
+                            line_set.x_range = parse_range(value).ok_or(format!("Row {}: invalid x_range {}", index + 1, value))?;
+                            has_x_range = true;
+                        },
+                        "y_range" => {
+                            line_set.y_range = parse_range(value).ok_or(format!("Row {}: invalid y_range {}", index + 1, value))?;
+                            has_y_range = true;
+                        },
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            if row.is_empty() || row.starts_with("kind") {
+                continue;
+            }
+
+            let fields: Vec<&str> = row.split(',').map(str::trim).collect();
+            if fields.len() != 5 {
+                return Err(format!("Row {}: expected 5 fields", index + 1));
+            }
+            let mut coordinates = [0.0f32; 4];
+            for (coordinate, field) in coordinates.iter_mut().zip(fields[1..].iter()) {
+                *coordinate = field.parse().map_err(|_| format!("Row {}: invalid number {}", index + 1, field))?;
+            }
+
+            match fields[0] {
+                "line" => line_set.lines.push(coordinates),
+                "selection" => line_set.selection = Some(coordinates),
+                kind => return Err(format!("Row {}: unknown kind {}", index + 1, kind)),
+            }
+        }
+
+        if !has_x_range || !has_y_range {
+            return Err("The header has no x_range or y_range".to_string());
+        }
+
+        Ok(line_set)
+    }
+}
+
+// Start and end written as start..end
+fn parse_range(text: &str) -> Option<[f32; 2]> {
+    let (start, end) = text.split_once("..")?;
+    Some([start.parse().ok()?, end.parse().ok()?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const X_RANGE: Range<f32> = -400.0..400.0;
+    const Y_RANGE: Range<f32> = -250.5..300.25;
+
+    fn clustered_set() -> LineSet {
+        let lines = generate_lines(7, Distribution::Clustered, 50, X_RANGE, Y_RANGE);
+        let selection = (Point::new(-10.5, 20.0), Point::new(100.0, -0.1));
+        LineSet::new(7, Distribution::Clustered, &X_RANGE, &Y_RANGE, &lines, Some(selection))
+    }
+
+    #[test]
+    fn same_arguments_give_the_same_lines() {
+        for distribution in Distribution::ALL {
+            let lines = generate_lines(42, distribution, 200, X_RANGE, Y_RANGE);
+            assert_eq!(lines.len(), 200);
+            assert_eq!(lines, generate_lines(42, distribution, 200, X_RANGE, Y_RANGE), "{}", distribution.label());
+            assert_ne!(lines, generate_lines(43, distribution, 200, X_RANGE, Y_RANGE), "{}", distribution.label());
+
+            // The first lines do not depend on how many follow
+            assert_eq!(lines[..20], generate_lines(42, distribution, 20, X_RANGE, Y_RANGE), "{}", distribution.label());
+        }
+    }
+
+    #[test]
+    fn lines_stay_in_the_area() {
+        for distribution in Distribution::ALL {
+            for (a, b) in generate_lines(1, distribution, 1000, X_RANGE, Y_RANGE) {
+                for p in [a, b] {
+                    assert!(X_RANGE.start <= p.x && p.x <= X_RANGE.end, "{}: {}", distribution.label(), p);
+                    assert!(Y_RANGE.start <= p.y && p.y <= Y_RANGE.end, "{}: {}", distribution.label(), p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_area_gives_no_lines() {
+        assert!(generate_lines(1, Distribution::Uniform, 10, 0.0..0.0, Y_RANGE).is_empty());
+        assert!(generate_lines(1, Distribution::Long, 10, X_RANGE, 5.0..5.0).is_empty());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let line_set = clustered_set();
+        let json = serde_json::to_string_pretty(&line_set).unwrap();
+        let loaded: LineSet = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded, line_set);
+        assert_eq!((loaded.x_range(), loaded.y_range()), (X_RANGE, Y_RANGE));
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let line_set = clustered_set();
+        assert_eq!(LineSet::from_csv(&line_set.to_csv()), Ok(line_set));
+
+        let without_selection = LineSet { selection: None, ..clustered_set() };
+        assert_eq!(LineSet::from_csv(&without_selection.to_csv()), Ok(without_selection));
+    }
+
+    #[test]
+    fn csv_skips_blank_rows_and_spaces() {
+        let text = "# seed=3 distribution=Short x_range=0..10 y_range=-5..5\n\nkind,x1,y1,x2,y2\n line , 1, 2 ,3,4 \n\n";
+        let line_set = LineSet::from_csv(text).unwrap();
+
+        assert_eq!((line_set.seed, line_set.distribution), (3, Distribution::Short));
+        assert_eq!((line_set.x_range(), line_set.y_range()), (0.0..10.0, -5.0..5.0));
+        assert_eq!(line_set.lines, vec![[1.0, 2.0, 3.0, 4.0]]);
+        assert_eq!(line_set.selection, None);
+    }
+
+    #[test]
+    fn malformed_csv_rows_are_reported() {
+        let header = "# seed=3 distribution=Short x_range=0..10 y_range=-5..5\nkind,x1,y1,x2,y2\n";
+        let cases = [
+            (format!("{}line,1,2,3\n", header), "Row 3: expected 5 fields"),
+            (format!("{}line,1,2,3,4,5\n", header), "Row 3: expected 5 fields"),
+            (format!("{}line,1,2,x,4\n", header), "Row 3: invalid number x"),
+            (format!("{}line,1,2,,4\n", header), "Row 3: invalid number "),
+            (format!("{}point,1,2,3,4\n", header), "Row 3: unknown kind point"),
+            ("# seed=-1 distribution=Short x_range=0..10 y_range=-5..5\n".to_string(), "Row 1: invalid seed"),
+            ("# seed=1 distribution=Spiral x_range=0..10 y_range=-5..5\n".to_string(), "Row 1: unknown distribution Spiral"),
+            ("# seed=1 distribution=Short x_range=0-10 y_range=-5..5\n".to_string(), "Row 1: invalid x_range 0-10"),
+            ("# seed=1 distribution=Short x_range=0..10 y_range=-5..y\n".to_string(), "Row 1: invalid y_range -5..y"),
+            ("# seed=1 distribution=Short y_range=-5..5\nline,1,2,3,4\n".to_string(), "The header has no x_range or y_range"),
+        ];
+
+        for (text, error) in cases {
+            assert_eq!(LineSet::from_csv(&text), Err(error.to_string()), "{}", text);
+        }
+    }
+}
@@ -1,12 +1,15 @@
-use kiss3d::event::{WindowEvent, MouseButton};
+use kiss3d::event::{WindowEvent, MouseButton, Key, Action, Modifiers};
 use kiss3d::nalgebra as na;
 use kiss3d::light::Light;
 use kiss3d::window::Window;
 use kiss3d::camera::{FixedView};
 use kiss3d::conrod::{self, Sizeable, Colorable};
-use na::{Point2, Point3};
+use na::{Point2, Point3, Vector2};
 
 use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use conrod::{
     widget_ids, 
@@ -17,38 +20,152 @@ use conrod::{
 
 use lazy_static::lazy_static;
 
-use coordinate_converter::CoordinateConverter;
-use common::*;
+use lab_common::coordinate_converter::CoordinateConverter;
+use lab_common::history::{Command, History};
+use lab_common::screenshot::Screenshots;
+use lab4::common::*;
 use rand::Rng;
-use selection::*;
+use lab4::selection::*;
+use lab4::compound_selection::*;
+use lab4::clipping::*;
+use lab4::line_set::*;
+use lab4::line_index::LineIndex;
+use lab4::curve_clipping::*;
+use lab4::scene::{self, scene, Scene};
+use lab_common::scene_file::{self, color, SAVE_SCENE_KEY};
+use lab_common::grid::{proceed_grid_ui, Grid, GridIds, SnapKey};
 
-mod coordinate_converter;
-mod common;
-mod selection;
+
+const PIECES_FILE_NAME: &str = "pieces.json";
+
+// Segments a curve is drawn with, whatever part of it is drawn
+const CURVE_DRAW_SEGMENTS: usize = 64;
+
+// Weight of the last frame in the smoothed frame time
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
+
+// Edits listed in the bottom left corner
+const HISTORY_LINES: usize = 10;
+
+// The reports clip every line once per algorithm, so they wait until the window
+// stays still that long instead of following a drag or a held arrow key
+const REPORTS_DELAY: Duration = Duration::from_millis(300);
 
 lazy_static! {
-    static ref LINE_COLOR: Point3<f32> = Point3::new(0.6, 0.6, 0.6);
-    static ref SELECTED_LINE_COLOR: Point3<f32> = Point3::new(1.0, 0.0, 0.0);
+    static ref LINE_COLOR: Point3<f32> = color(scene().colors.line);
+    static ref SELECTED_LINE_COLOR: Point3<f32> = color(scene().colors.selected_line);
+    static ref SELECTED_FILL_COLOR: Point3<f32> = color(scene().colors.selected_fill);
+    static ref AXES_COLOR: Point3<f32> = color(scene().colors.axes);
 }
 
 struct LinesManager {
     lines_count: u32,
-    lines: Vec<Line>,
+    seed: u64,
+    distribution: Distribution,
+    // Shared with the history, copied when changed
+    lines: Rc<Vec<Line>>,
+    index: LineIndex,
+    selection_check: Option<SelectionCheck>,
+    // Area the lines are generated in. It stays as the window is resized,
+    // so the same seed gives the same lines until a line set brings its own area.
     x_range: Range<f32>,
     y_range: Range<f32>
 }
 
+// Clipping of every line against a selection, kept until the lines, the selection or the algorithm change
+struct SelectionCheck {
+    selection: Selection,
+    algorithm: ClippingAlgorithm,
+    clippings: Vec<LineClipping>,
+    // Lines the index could not classify and went to the clipper
+    tested_count: usize,
+    duration: Duration,
+}
+
+// Lines and what they were generated from, as an edit left them
+#[derive(Clone)]
+struct LinesState {
+    lines_count: u32,
+    seed: u64,
+    distribution: Distribution,
+    x_range: Range<f32>,
+    y_range: Range<f32>,
+    lines: Rc<Vec<Line>>,
+}
+
+// What the lines are generated with, or lines loaded or added
+#[derive(PartialEq)]
+enum LinesChange {
+    Distribution,
+    Seed,
+    Count,
+    Lines,
+}
+
+struct LinesEdit {
+    from: LinesState,
+    to: LinesState,
+}
+
+impl LinesEdit {
+    fn change(&self) -> LinesChange {
+        if self.from.distribution != self.to.distribution {
+            LinesChange::Distribution
+        } else if self.from.seed != self.to.seed {
+            LinesChange::Seed
+        } else if self.from.lines_count != self.to.lines_count {
+            LinesChange::Count
+        } else {
+            LinesChange::Lines
+        }
+    }
+}
+
+impl Command for LinesEdit {
+    type Target = LinesManager;
+
+    fn apply(&self, lines_manager: &mut LinesManager) {
+        lines_manager.set_state(&self.to);
+    }
+
+    fn revert(&self, lines_manager: &mut LinesManager) {
+        lines_manager.set_state(&self.from);
+    }
+
+    fn label(&self) -> String {
+        match self.change() {
+            LinesChange::Distribution => format!("{} distribution", self.to.distribution.label()),
+            LinesChange::Seed => format!("Seed {}", self.to.seed),
+            LinesChange::Count => format!("{} lines", self.to.lines_count),
+            LinesChange::Lines => format!("Lines replaced, {} now", self.to.lines.len()),
+        }
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        if next.change() != self.change() {
+            return false;
+        }
+        self.to = next.to.clone();
+        true
+    }
+}
+
 impl LinesManager {
     pub fn new() -> Self {
         Self {
             lines_count: 0,
-            lines: vec![],
+            seed: scene().lines.seed,
+            distribution: scene().lines.distribution,
+            lines: Rc::new(vec![]),
+            index: LineIndex::new(&[]),
+            selection_check: None,
             x_range: 0.0..0.0,
             y_range: 0.0..0.0
         }
     }
 
-    pub fn set_draw_area_size(&mut self, width: f32, height: f32) {
+    // Around the window center
+    pub fn set_area_size(&mut self, width: f32, height: f32) {
         let x_abs = width / 2.0;
         let y_abs = height / 2.0;
         self.x_range = -x_abs..x_abs;
@@ -60,21 +177,104 @@ impl LinesManager {
         self.generate_lines();
     }
 
-    pub fn draw(&mut self, window: &mut Window) {
-        for line in &self.lines {
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.generate_lines();
+    }
+
+    pub fn set_distribution(&mut self, distribution: Distribution) {
+        self.distribution = distribution;
+        self.generate_lines();
+    }
+
+    pub fn line_set(&self, selection: Option<(Point, Point)>) -> LineSet {
+        LineSet::new(self.seed, self.distribution, &self.x_range, &self.y_range, &self.lines, selection)
+    }
+
+    fn state(&self) -> LinesState {
+        LinesState {
+            lines_count: self.lines_count,
+            seed: self.seed,
+            distribution: self.distribution,
+            x_range: self.x_range.clone(),
+            y_range: self.y_range.clone(),
+            lines: self.lines.clone(),
+        }
+    }
+
+    fn is_in_state(&self, state: &LinesState) -> bool {
+        self.lines_count == state.lines_count
+            && self.seed == state.seed
+            && self.distribution == state.distribution
+            && self.x_range == state.x_range
+            && self.y_range == state.y_range
+            && Rc::ptr_eq(&self.lines, &state.lines)
+    }
+
+    fn set_state(&mut self, state: &LinesState) {
+        self.lines_count = state.lines_count;
+        self.seed = state.seed;
+        self.distribution = state.distribution;
+        self.x_range = state.x_range.clone();
+        self.y_range = state.y_range.clone();
+        self.lines = state.lines.clone();
+        self.update_index();
+    }
+
+    // Lines are taken as saved, not generated again from the seed
+    pub fn set_line_set(&mut self, line_set: &LineSet) {
+        self.seed = line_set.seed;
+        self.distribution = line_set.distribution;
+        self.x_range = line_set.x_range();
+        self.y_range = line_set.y_range();
+        self.lines = Rc::new(line_set.lines());
+        self.lines_count = self.lines.len() as u32;
+        self.update_index();
+    }
+
+    pub fn draw(&self, window: &mut Window) {
+        for line in self.lines.iter() {
             window.draw_planar_line(&line.0, &line.1, &LINE_COLOR);
         }
     }
 
-    pub fn draw_with_selection_check(&mut self, window: &mut Window, selection: RectangleSelection) {
-        for line in &self.lines {
-            match selection.clipping_check(line) {
+    // Returns whether the lines had to be clipped again
+    pub fn update_selection_check(&mut self, selection: &Selection, algorithm: ClippingAlgorithm) -> bool {
+        let is_actual = self.selection_check
+            .as_ref()
+            .is_some_and(|check| check.selection == *selection && check.algorithm == algorithm);
+        if is_actual {
+            return false;
+        }
+
+        let start = Instant::now();
+        let (clippings, tested_count) = self.clip_lines(selection, algorithm, &mut OperationCounters::default());
+        self.selection_check = Some(SelectionCheck {
+            selection: selection.clone(),
+            algorithm,
+            clippings,
+            tested_count,
+            duration: start.elapsed(),
+        });
+        true
+    }
+
+    // All the lines go to the planar line renderer, which sends them to the GPU in a single draw call
+    pub fn draw_with_selection_check(&self, window: &mut Window) {
+        let Some(check) = &self.selection_check else {
+            return self.draw(window);
+        };
+
+        for (line, clipping) in self.lines.iter().zip(check.clippings.iter()) {
+            match clipping {
                 LineClipping::Inside => {
                     window.draw_planar_line(&line.0, &line.1, &SELECTED_LINE_COLOR);
                 },
-                LineClipping::PartlyInside(inside_line_part) => {
+                LineClipping::PartlyInside(inside_line_parts) => {
                     window.draw_planar_line(&line.0, &line.1, &LINE_COLOR);
-                    window.draw_planar_line(&inside_line_part.0, &inside_line_part.1, &SELECTED_LINE_COLOR);
+                    for part in inside_line_parts {
+                        window.draw_planar_line(&part.0, &part.1, &SELECTED_LINE_COLOR);
+                    }
                 },
                 LineClipping::Outside => {
                     window.draw_planar_line(&line.0, &line.1, &LINE_COLOR);
@@ -83,45 +283,440 @@ impl LinesManager {
         }
     }
 
+    // Lines whose bounding box is inside or outside the selection bounds skip the clipper.
+    // Returns the clippings and the number of lines that were clipped.
+    fn clip_lines(&self, selection: &Selection, algorithm: ClippingAlgorithm, counters: &mut OperationCounters) -> (Vec<LineClipping>, usize) {
+        let mut clippings: Vec<LineClipping> = self.lines.iter().map(|_| LineClipping::Outside).collect();
+        let query = self.index.query(selection.bounds());
+
+        // The bounding box of a polygon or of several windows holds more than the region itself
+        let tested: Vec<usize> = match selection {
+            Selection::Rectangle(_) => {
+                for &i in &query.inside {
+                    clippings[i] = LineClipping::Inside;
+                }
+                query.crossing
+            },
+            Selection::Polygon(_) | Selection::Compound(_) => query.inside.into_iter().chain(query.crossing).collect(),
+        };
+        for &i in &tested {
+            clippings[i] = selection.clipping_check(&self.lines[i], algorithm, counters);
+        }
+
+        (clippings, tested.len())
+    }
+
+    pub fn check_stats(&self) -> Option<(usize, Duration)> {
+        self.selection_check.as_ref().map(|check| (check.tested_count, check.duration))
+    }
+
+    pub fn add_lines(&mut self, lines: &[Line]) {
+        Rc::make_mut(&mut self.lines).extend_from_slice(lines);
+        self.update_index();
+    }
+
+    // Ends of the lines within `distance` of the point on both axes
+    pub fn endpoints_near(&self, point: Point, distance: f32) -> Vec<Point> {
+        let query = self.index.query((point.x - distance, point.x + distance, point.y + distance, point.y - distance));
+        query.inside
+            .into_iter()
+            .chain(query.crossing)
+            .flat_map(|i| [self.lines[i].0, self.lines[i].1])
+            .filter(|end| (end.x - point.x).abs() <= distance && (end.y - point.y).abs() <= distance)
+            .collect()
+    }
+
+    // Operations of the algorithm on the lines the index did not cull
+    pub fn report(&self, selection: &Selection, algorithm: ClippingAlgorithm) -> AlgorithmReport {
+        let mut counters = OperationCounters::default();
+        let (_, clipped_count) = self.clip_lines(selection, algorithm, &mut counters);
+
+        AlgorithmReport { algorithm, counters, culled_count: self.lines.len() - clipped_count }
+    }
+
     fn generate_lines(&mut self) {
+        self.lines = Rc::new(generate_lines(
+            self.seed,
+            self.distribution,
+            self.lines_count as usize,
+            self.x_range.clone(),
+            self.y_range.clone()
+        ));
+        self.update_index();
+    }
+
+    fn update_index(&mut self) {
+        self.index = LineIndex::new(&self.lines);
+        self.selection_check = None;
+    }
+}
+
+struct PolygonsManager {
+    polygons_count: u32,
+    random_polygons: Vec<Polygon>,
+    drawn_polygons: Vec<Polygon>,
+    x_range: Range<f32>,
+    y_range: Range<f32>
+}
+
+impl PolygonsManager {
+    pub fn new() -> Self {
+        Self {
+            polygons_count: 0,
+            random_polygons: vec![],
+            drawn_polygons: vec![],
+            x_range: 0.0..0.0,
+            y_range: 0.0..0.0
+        }
+    }
+
+    pub fn set_draw_area_size(&mut self, width: f32, height: f32) {
+        let x_abs = width / 2.0;
+        let y_abs = height / 2.0;
+        self.x_range = -x_abs..x_abs;
+        self.y_range = -y_abs..y_abs;
+    }
+
+    pub fn set_polygons_count(&mut self, polygons_count: u32) {
+        self.polygons_count = polygons_count;
+        self.generate_polygons();
+    }
+
+    pub fn add_polygon(&mut self, polygon: Polygon) {
+        self.drawn_polygons.push(polygon);
+    }
+
+    pub fn clear_drawn_polygons(&mut self) {
+        self.drawn_polygons.clear();
+    }
+
+    fn polygons(&self) -> impl Iterator<Item = &Polygon> {
+        self.random_polygons.iter().chain(self.drawn_polygons.iter())
+    }
+
+    pub fn draw(&self, window: &mut Window) {
+        for polygon in self.polygons() {
+            draw_polyline(window, polygon, true, &LINE_COLOR);
+        }
+    }
+
+    pub fn draw_with_selection_check(&self, window: &mut Window, selection: &Selection) {
+        for polygon in self.polygons() {
+            fill_polygons(window, &selection.clip_polygon(polygon), &SELECTED_FILL_COLOR);
+            draw_polyline(window, polygon, true, &LINE_COLOR);
+        }
+    }
+
+    // Star-shaped around a random center, so they never intersect themselves
+    fn generate_polygons(&mut self) {
         let mut rng = rand::thread_rng();
+        let max_radius = (self.x_range.end - self.x_range.start).min(self.y_range.end - self.y_range.start) / 6.0;
 
-        let mut random_point = || {
-            Point::new(
-                rng.gen_range(self.x_range.clone()),
-                rng.gen_range(self.y_range.clone()),
-            )
-        };
+        self.random_polygons = (0..self.polygons_count)
+            .map(|_| {
+                let center = Point::new(rng.gen_range(self.x_range.clone()), rng.gen_range(self.y_range.clone()));
+                let vertices_count = rng.gen_range(3..=8);
+                let angle_step = std::f32::consts::TAU / vertices_count as f32;
+
+                (0..vertices_count)
+                    .map(|i| {
+                        // Small jitter keeps every angle gap below pi
+                        let angle = angle_step * (i as f32 + rng.gen_range(-0.2..0.2));
+                        let radius = rng.gen_range(max_radius / 3.0..max_radius);
+                        Point::new(center.x + radius * angle.cos(), center.y + radius * angle.sin())
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+}
+
+struct CurvesManager {
+    curves_count: u32,
+    polylines: Vec<Polyline>,
+    curves: Vec<CubicBezier>,
+    x_range: Range<f32>,
+    y_range: Range<f32>
+}
+
+impl CurvesManager {
+    pub fn new() -> Self {
+        Self {
+            curves_count: 0,
+            polylines: vec![],
+            curves: vec![],
+            x_range: 0.0..0.0,
+            y_range: 0.0..0.0
+        }
+    }
+
+    pub fn set_draw_area_size(&mut self, width: f32, height: f32) {
+        let x_abs = width / 2.0;
+        let y_abs = height / 2.0;
+        self.x_range = -x_abs..x_abs;
+        self.y_range = -y_abs..y_abs;
+    }
+
+    // That many polylines and as many curves
+    pub fn set_curves_count(&mut self, curves_count: u32) {
+        self.curves_count = curves_count;
+        self.generate_curves();
+    }
+
+    pub fn draw(&self, window: &mut Window) {
+        for polyline in &self.polylines {
+            draw_polyline(window, polyline, false, &LINE_COLOR);
+        }
+        for curve in &self.curves {
+            draw_bezier(window, curve, 0.0, 1.0, &LINE_COLOR);
+        }
+    }
+
+    // Returns the inside pieces, polylines clipped by the algorithm and curves analytically
+    pub fn draw_with_selection_check(&self, window: &mut Window, selection: &Selection, algorithm: ClippingAlgorithm) -> ClippedPieces {
+        let mut pieces = ClippedPieces::default();
+        self.draw(window);
+
+        for polyline in &self.polylines {
+            for piece in clip_polyline(polyline, selection, algorithm, &mut OperationCounters::default()) {
+                draw_polyline(window, &piece, false, &SELECTED_LINE_COLOR);
+                pieces.add_polyline(&piece);
+            }
+        }
+        for curve in &self.curves {
+            for (t0, t1) in clip_bezier(curve, selection) {
+                draw_bezier(window, curve, t0, t1, &SELECTED_LINE_COLOR);
+                pieces.add_curve(&curve.segment(t0, t1));
+            }
+        }
+
+        pieces
+    }
+
+    fn generate_curves(&mut self) {
+        let mut rng = rand::thread_rng();
+        let mut random_point = || Point::new(rng.gen_range(self.x_range.clone()), rng.gen_range(self.y_range.clone()));
 
-        let mut lines = <Vec<Line>>::with_capacity(self.lines_count as usize);
-        for _ in 0..self.lines_count {
-            let line = (random_point(), random_point());
-            lines.push(line);
+        self.curves = (0..self.curves_count)
+            .map(|_| CubicBezier { points: [random_point(), random_point(), random_point(), random_point()] })
+            .collect();
+
+        let mut rng = rand::thread_rng();
+        let max_step = (self.x_range.end - self.x_range.start).min(self.y_range.end - self.y_range.start) / 4.0;
+        self.polylines = (0..self.curves_count)
+            .map(|_| {
+                let mut point = Point::new(rng.gen_range(self.x_range.clone()), rng.gen_range(self.y_range.clone()));
+                (0..rng.gen_range(3..=8))
+                    .map(|_| {
+                        let current = point;
+                        point = Point::new(
+                            (point.x + rng.gen_range(-max_step..max_step)).clamp(self.x_range.start, self.x_range.end),
+                            (point.y + rng.gen_range(-max_step..max_step)).clamp(self.y_range.start, self.y_range.end),
+                        );
+                        current
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+}
+
+// The part of the curve between two parameters, flattened for display only
+fn draw_bezier(window: &mut Window, curve: &CubicBezier, t0: f32, t1: f32, color: &Point3<f32>) {
+    let segments = ((CURVE_DRAW_SEGMENTS as f32 * (t1 - t0)).ceil() as usize).max(1);
+    let points: Vec<Point> = (0..=segments)
+        .map(|i| curve.point(t0 + (t1 - t0) * i as f32 / segments as f32))
+        .collect();
+
+    draw_polyline(window, &points, false, color);
+}
+
+// Scan-line fill with the even-odd rule, one horizontal line per pixel row
+fn fill_polygons(window: &mut Window, polygons: &[Polygon], color: &Point3<f32>) {
+    let points = || polygons.iter().flatten();
+    let y_min = points().map(|p| p.y).fold(f32::INFINITY, f32::min);
+    let y_max = points().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+
+    let mut y = y_min.floor() + 0.5;
+    while y < y_max {
+        let mut crossings: Vec<f32> = polygons
+            .iter()
+            .flat_map(|polygon| polygon.iter().zip(polygon.iter().cycle().skip(1)))
+            .filter(|(a, b)| (a.y > y) != (b.y > y))
+            .map(|(a, b)| a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y))
+            .collect();
+        crossings.sort_by(|a, b| a.total_cmp(b));
+
+        for span in crossings.chunks_exact(2) {
+            window.draw_planar_line(&Point::new(span[0], y), &Point::new(span[1], y), color);
+        }
+        y += 1.0;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SubjectKind {
+    Lines,
+    Polygons,
+    // Polylines and cubic Bézier curves
+    Curves,
+}
+
+impl SubjectKind {
+    const ALL: [SubjectKind; 3] = [SubjectKind::Lines, SubjectKind::Polygons, SubjectKind::Curves];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SubjectKind::Lines => "Clip lines",
+            SubjectKind::Polygons => "Clip polygons",
+            SubjectKind::Curves => "Clip curves",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum WindowKind {
+    Rectangle,
+    Polygon,
+    Compound,
+}
+
+impl WindowKind {
+    const ALL: [WindowKind; 3] = [WindowKind::Rectangle, WindowKind::Polygon, WindowKind::Compound];
+
+    fn label(&self) -> &'static str {
+        match self {
+            WindowKind::Rectangle => "Rectangle",
+            WindowKind::Polygon => "Polygon",
+            WindowKind::Compound => "Several rectangles",
+        }
+    }
+}
+
+// Chosen clipping window and algorithm, and the operations of every algorithm on the current window
+struct ClippingSettings {
+    subject_kind: SubjectKind,
+    window_kind: WindowKind,
+    algorithm: ClippingAlgorithm,
+    // Why the drawn window was rejected
+    error: Option<String>,
+    add_degenerate_lines: bool,
+    // Mode of the rectangle being edited among several
+    combine_mode: CombineMode,
+    clear_windows: bool,
+    reports: Vec<AlgorithmReport>,
+    // Last change of the window or the lines the reports do not include yet
+    reports_outdated_at: Option<Instant>,
+}
+
+// Operations of an algorithm on the current frame, not counting the lines culled by the index
+struct AlgorithmReport {
+    algorithm: ClippingAlgorithm,
+    counters: OperationCounters,
+    culled_count: usize,
+}
+
+// Smoothed frame time, and how long the last clipping of all the lines and the reports took
+struct FrameStats {
+    last_frame: Instant,
+    frame_time: f32,
+    reports_duration: Duration,
+}
+
+impl FrameStats {
+    fn new() -> Self {
+        Self { last_frame: Instant::now(), frame_time: 0.0, reports_duration: Duration::ZERO }
+    }
+
+    fn update(&mut self) {
+        let now = Instant::now();
+        let elapsed = (now - self.last_frame).as_secs_f32();
+        self.frame_time += (elapsed - self.frame_time) * FRAME_TIME_SMOOTHING;
+        self.last_frame = now;
+    }
+
+    fn text(&self, lines_manager: &LinesManager) -> String {
+        let mut text = format!(
+            "{:.0} FPS, frame {:.1} ms",
+            1.0 / self.frame_time.max(f32::EPSILON), self.frame_time * 1000.0
+        );
+        if let Some((tested_count, duration)) = lines_manager.check_stats() {
+            text += &format!(
+                "\nClipping {:.2} ms, {} of {} lines clipped, reports {:.1} ms",
+                duration.as_secs_f32() * 1000.0, tested_count, lines_manager.lines.len(),
+                self.reports_duration.as_secs_f32() * 1000.0
+            );
         }
-        self.lines = lines;
+        text
     }
 }
 
+// Seed being typed and the result of the last save or load
+struct LineSetSettings {
+    seed_text: String,
+    status: String,
+}
+
+#[derive(Clone, Copy)]
+enum LineSetAction {
+    Save(LineSetFormat),
+    Load(LineSetFormat),
+    ExportPieces,
+}
+
 widget_ids! {
     pub struct Ids {
         canvas,
-        line_counter
+        line_set_canvas,
+        seed_text_box,
+        distribution_list,
+        line_set_area,
+        save_json_button,
+        save_csv_button,
+        load_json_button,
+        load_csv_button,
+        export_button,
+        line_set_status,
+        line_counter,
+        algorithm_list,
+        window_list,
+        polygon_counter,
+        subject_list,
+        clear_button,
+        degenerate_button,
+        mode_list,
+        counters_text,
+        timing_text,
+        error_text,
+        history_text,
     }
 }
 
-fn proceed_ui(ui_cell: &mut UiCell, ids: &Ids, lines_manager: &mut LinesManager) {
+fn proceed_ui(
+    ui_cell: &mut UiCell,
+    ids: &Ids,
+    lines_manager: &mut LinesManager,
+    polygons_manager: &mut PolygonsManager,
+    curves_manager: &mut CurvesManager,
+    clipping_settings: &mut ClippingSettings,
+    frame_stats: &FrameStats
+) {
     let dialer_margin = 20.0;
     let dialer_w = 120.0;
     let dialer_h = 30.0;
+    let list_w = 160.0;
+    let text_w = 300.0;
+    let text_h = 80.0;
+    let timing_h = 30.0;
 
     widget::Canvas::new()
-        .w_h(dialer_w + dialer_margin, dialer_h + dialer_margin)
+        .w_h(dialer_w + 2.0 * list_w + 4.0 * dialer_margin, 3.0 * dialer_h + text_h + timing_h + 6.0 * dialer_margin)
         .rgb(1.0, 1.0, 1.0)
         .border_rgb(1.0, 1.0, 1.0)
         .top_left()
         .set(ids.canvas, ui_cell);
 
-    for value in widget::NumberDialer::new(lines_manager.lines_count as f32, 0.0, 100.0, 0)
+    if let Some(value) = widget::NumberDialer::new(lines_manager.lines_count as f32, 0.0, scene().ui.max_lines_count as f32, 0)
         .w_h(dialer_w, dialer_h)
         .top_left_with_margin_on(ids.canvas, dialer_margin)
         .label("Lines")
@@ -129,9 +724,270 @@ fn proceed_ui(ui_cell: &mut UiCell, ids: &Ids, lines_manager: &mut LinesManager)
     {
         lines_manager.set_lines_count(value as u32);
     }
+
+    let labels: Vec<&str> = ClippingAlgorithm::ALL.iter().map(|a| a.label()).collect();
+    let selected = ClippingAlgorithm::ALL.iter().position(|a| *a == clipping_settings.algorithm);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(list_w, dialer_h)
+        .right_from(ids.line_counter, dialer_margin)
+        .set(ids.algorithm_list, ui_cell)
+    {
+        clipping_settings.algorithm = ClippingAlgorithm::ALL[index];
+    }
+
+    let labels: Vec<&str> = WindowKind::ALL.iter().map(|k| k.label()).collect();
+    let selected = WindowKind::ALL.iter().position(|k| *k == clipping_settings.window_kind);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(list_w, dialer_h)
+        .right_from(ids.algorithm_list, dialer_margin)
+        .set(ids.window_list, ui_cell)
+    {
+        clipping_settings.window_kind = WindowKind::ALL[index];
+    }
+
+    // Polygons, or polylines and curves when those are clipped
+    let (count, label) = match clipping_settings.subject_kind {
+        SubjectKind::Curves => (curves_manager.curves_count, "Curves"),
+        _ => (polygons_manager.polygons_count, "Polygons"),
+    };
+    if let Some(value) = widget::NumberDialer::new(count as f32, 0.0, 20.0, 0)
+        .w_h(dialer_w, dialer_h)
+        .down_from(ids.line_counter, dialer_margin)
+        .label(label)
+        .set(ids.polygon_counter, ui_cell)
+    {
+        match clipping_settings.subject_kind {
+            SubjectKind::Curves => curves_manager.set_curves_count(value as u32),
+            _ => polygons_manager.set_polygons_count(value as u32),
+        }
+    }
+
+    let labels: Vec<&str> = SubjectKind::ALL.iter().map(|k| k.label()).collect();
+    let selected = SubjectKind::ALL.iter().position(|k| *k == clipping_settings.subject_kind);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(list_w, dialer_h)
+        .right_from(ids.polygon_counter, dialer_margin)
+        .set(ids.subject_list, ui_cell)
+    {
+        clipping_settings.subject_kind = SubjectKind::ALL[index];
+    }
+
+    if widget::Button::new()
+        .w_h(list_w, dialer_h)
+        .right_from(ids.subject_list, dialer_margin)
+        .label("Clear drawn")
+        .set(ids.clear_button, ui_cell)
+        .was_clicked()
+    {
+        polygons_manager.clear_drawn_polygons();
+        clipping_settings.clear_windows = true;
+    }
+
+    if widget::Button::new()
+        .w_h(list_w, dialer_h)
+        .down_from(ids.polygon_counter, dialer_margin)
+        .label("Degenerate lines")
+        .set(ids.degenerate_button, ui_cell)
+        .was_clicked()
+    {
+        clipping_settings.add_degenerate_lines = true;
+    }
+
+    if clipping_settings.window_kind == WindowKind::Compound {
+        let labels: Vec<&str> = CombineMode::ALL.iter().map(|m| m.label()).collect();
+        let selected = CombineMode::ALL.iter().position(|m| *m == clipping_settings.combine_mode);
+
+        if let Some(index) = widget::DropDownList::new(&labels, selected)
+            .w_h(list_w, dialer_h)
+            .right_from(ids.degenerate_button, dialer_margin)
+            .set(ids.mode_list, ui_cell)
+        {
+            clipping_settings.combine_mode = CombineMode::ALL[index];
+        }
+    }
+
+    let mut info = match clipping_settings.subject_kind {
+        // The culled lines depend on the window only, so they are the same for every algorithm
+        SubjectKind::Lines => clipping_settings.reports
+            .first()
+            .map(|report| {
+                let outdated = if clipping_settings.reports_outdated_at.is_some() { " (updated when the window stops)" } else { "" };
+                format!("After index culling of {} lines{}:", report.culled_count, outdated)
+            })
+            .into_iter()
+            .chain(clipping_settings.reports.iter().map(|report| {
+                let c = report.counters;
+                format!(
+                    "{}: {} arithmetic, {} comparisons, {} intersections",
+                    report.algorithm.label(), c.arithmetic, c.comparisons, c.intersection_tests
+                )
+            }))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        SubjectKind::Polygons => {
+            let algorithm = match clipping_settings.window_kind {
+                WindowKind::Rectangle => "Sutherland-Hodgman",
+                WindowKind::Polygon => "Weiler-Atherton",
+                WindowKind::Compound => "None",
+            };
+            format!("{}\nRight click to draw a polygon, double click to close it", algorithm)
+        },
+        SubjectKind::Curves => format!(
+            "Polylines: {} on every segment\nCurves: crossings with the borders solved from the cubic",
+            clipping_settings.algorithm.label()
+        ),
+    };
+    if clipping_settings.window_kind == WindowKind::Compound {
+        info += "\nThe mode applies to the rectangle being edited, drawing another one keeps it with that mode";
+    }
+
+    widget::Text::new(&info)
+        .font_size(11)
+        .w(text_w)
+        .down_from(ids.degenerate_button, dialer_margin)
+        .set(ids.counters_text, ui_cell);
+
+    widget::Text::new(&frame_stats.text(lines_manager))
+        .font_size(11)
+        .w(text_w)
+        .down_from(ids.counters_text, dialer_margin / 2.0)
+        .set(ids.timing_text, ui_cell);
+
+    if let Some(error) = &clipping_settings.error {
+        widget::Text::new(error)
+            .font_size(14)
+            .rgb(0.8, 0.0, 0.0)
+            .w(text_w)
+            .down_from(ids.timing_text, dialer_margin / 2.0)
+            .set(ids.error_text, ui_cell);
+    }
+}
+
+fn proceed_line_set_ui(ui_cell: &mut UiCell, ids: &Ids, lines_manager: &mut LinesManager, settings: &mut LineSetSettings) -> Option<LineSetAction> {
+    let margin = 20.0;
+    let widget_w = 160.0;
+    let widget_h = 30.0;
+    let button_w = 70.0;
+
+    widget::Canvas::new()
+        .w_h(widget_w + 2.0 * margin, 5.0 * widget_h + 8.0 * margin)
+        .rgb(1.0, 1.0, 1.0)
+        .border_rgb(1.0, 1.0, 1.0)
+        .top_right()
+        .set(ids.line_set_canvas, ui_cell);
+
+    for event in widget::TextBox::new(&settings.seed_text)
+        .w_h(widget_w, widget_h)
+        .font_size(14)
+        .top_left_with_margin_on(ids.line_set_canvas, margin)
+        .set(ids.seed_text_box, ui_cell)
+    {
+        if let widget::text_box::Event::Update(text) = event {
+            // Digits only, the lines follow the seed as it is typed
+            if text.chars().all(|c| c.is_ascii_digit()) {
+                if let Ok(seed) = text.parse() {
+                    lines_manager.set_seed(seed);
+                }
+                settings.seed_text = text;
+            }
+        }
+    }
+
+    let labels: Vec<&str> = Distribution::ALL.iter().map(|d| d.label()).collect();
+    let selected = Distribution::ALL.iter().position(|d| *d == lines_manager.distribution);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(widget_w, widget_h)
+        .down_from(ids.seed_text_box, margin / 2.0)
+        .set(ids.distribution_list, ui_cell)
+    {
+        lines_manager.set_distribution(Distribution::ALL[index]);
+    }
+
+    let (x_range, y_range) = (&lines_manager.x_range, &lines_manager.y_range);
+    widget::Text::new(&format!("Area {} x {}", x_range.end - x_range.start, y_range.end - y_range.start))
+        .font_size(11)
+        .w(widget_w)
+        .down_from(ids.distribution_list, margin / 2.0)
+        .set(ids.line_set_area, ui_cell);
+
+    let save_ids = [ids.save_json_button, ids.save_csv_button];
+    let load_ids = [ids.load_json_button, ids.load_csv_button];
+    let mut action = None;
+
+    for (index, format) in LineSetFormat::ALL.iter().enumerate() {
+        let save_label = format!("Save {}", format.label());
+        let save_button = widget::Button::new()
+            .w_h(button_w, widget_h)
+            .label(&save_label);
+        let save_button = match index {
+            0 => save_button.down_from(ids.line_set_area, margin / 2.0),
+            _ => save_button.down_from(save_ids[index - 1], margin / 4.0),
+        };
+        if save_button.set(save_ids[index], ui_cell).was_clicked() {
+            action = Some(LineSetAction::Save(*format));
+        }
+
+        if widget::Button::new()
+            .w_h(button_w, widget_h)
+            .label(&format!("Load {}", format.label()))
+            .right_from(save_ids[index], widget_w - 2.0 * button_w)
+            .set(load_ids[index], ui_cell)
+            .was_clicked()
+        {
+            action = Some(LineSetAction::Load(*format));
+        }
+    }
+
+    // Inside pieces of the clipped polylines and curves
+    if widget::Button::new()
+        .w_h(widget_w, widget_h)
+        .label("Export pieces")
+        .down_from(ids.save_csv_button, margin / 2.0)
+        .set(ids.export_button, ui_cell)
+        .was_clicked()
+    {
+        action = Some(LineSetAction::ExportPieces);
+    }
+
+    widget::Text::new(&settings.status)
+        .font_size(11)
+        .w(widget_w)
+        .down_from(ids.export_button, margin / 2.0)
+        .set(ids.line_set_status, ui_cell);
+
+    action
+}
+
+// Selection corners snap to the grid, the axes, the ends of the lines and the corners of the other rectangles,
+// unless the snap key is held
+fn snap_corner(point: Point, grid: &Grid, snap_key: &SnapKey, lines_manager: &LinesManager, compound_builder: &CompoundSelectionBuilder) -> Point {
+    if !snap_key.is_snapping() {
+        return point;
+    }
+
+    let mut others = lines_manager.endpoints_near(point, grid.settings().snapping.distance);
+    others.extend(compound_builder.corners());
+    grid.snap(point, &others)
 }
 
 fn main() {
+    // Screenshots and frame recording
+    let mut screenshots = Screenshots::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    // Scene
+    let (loaded_scene, scene_path) = scene_file::load_from_args::<Scene>().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    scene::install(loaded_scene);
+
     // Window
     let mut window = Window::new("Kiss3d: obj");
     window.set_line_width(2.0);
@@ -144,51 +1000,274 @@ fn main() {
     // LinesManager
     let mut cursor = Point2::new(0.0, 0.0);
     let mut lines_manager = LinesManager::new();
+    let mut polygons_manager = PolygonsManager::new();
+    let mut curves_manager = CurvesManager::new();
+    let mut clipped_pieces = ClippedPieces::default();
+    let mut line_set_settings = LineSetSettings { seed_text: lines_manager.seed.to_string(), status: String::new() };
     let mut selection_builder = RectangleSelectionBuilder::new();
+    let mut compound_builder = CompoundSelectionBuilder::new();
+    let mut polygon_selection_builder = PolygonSelectionBuilder::new(PolygonShape::Convex);
+    let mut subject_builder = PolygonSelectionBuilder::new(PolygonShape::Simple);
+    let mut frame_stats = FrameStats::new();
+    let mut clipping_settings = ClippingSettings {
+        subject_kind: SubjectKind::Lines,
+        window_kind: WindowKind::Rectangle,
+        algorithm: ClippingAlgorithm::CohenSutherland,
+        error: None,
+        add_degenerate_lines: false,
+        combine_mode: CombineMode::Add,
+        clear_windows: false,
+        reports: vec![],
+        reports_outdated_at: None,
+    };
+
+    // Lines of the scene, spread over the draw area of the window as it opens
+    let draw_area_part = 0.9;
+    lines_manager.set_area_size(window.width() as f32 * draw_area_part, window.height() as f32 * draw_area_part);
+    lines_manager.set_lines_count(scene().lines.count);
+    selection_builder.set_corners(scene().selection.map(|[start, end]| (Point::from(start), Point::from(end))));
+    let mut lines_state = lines_manager.state();
+    let mut history = History::new();
+    let mut grid_settings = scene().grid.clone();
+    let mut snap_key = SnapKey::default();
 
     // UI
     let ids = Ids::new(window.conrod_ui_mut().widget_id_generator());
+    let grid_ids = GridIds::new(window.conrod_ui_mut().widget_id_generator());
 
     while window.render_with_camera(&mut camera) {
+        screenshots.capture(&window);
+
+        frame_stats.update();
         let window_width = window.width();
         let window_height = window.height();
 
-        let draw_area_part = 0.9;
-        lines_manager.set_draw_area_size(
+        polygons_manager.set_draw_area_size(
             window_width as f32 * draw_area_part, 
             window_height as f32 * draw_area_part
         );
+        curves_manager.set_draw_area_size(
+            window_width as f32 * draw_area_part,
+            window_height as f32 * draw_area_part
+        );
 
         // Coordinate system helper
         let cc = CoordinateConverter::new(window_width, window_height);
 
         let mut ui_cell = window.conrod_ui_mut().set_widgets();
-        proceed_ui(&mut ui_cell, &ids, &mut lines_manager);
+        proceed_ui(&mut ui_cell, &ids, &mut lines_manager, &mut polygons_manager, &mut curves_manager, &mut clipping_settings, &frame_stats);
+        let line_set_action = proceed_line_set_ui(&mut ui_cell, &ids, &mut lines_manager, &mut line_set_settings);
+        widget::Text::new(&history.text(HISTORY_LINES))
+            .font_size(11)
+            .bottom_left_with_margin(20.0)
+            .set(ids.history_text, &mut ui_cell);
+        proceed_grid_ui(&mut ui_cell, &grid_ids, &mut grid_settings);
         drop(ui_cell);
 
+        // Under everything else, the axes through the window center only with the grid
+        let grid = Grid::new(&grid_settings, Point::origin(), window_width, window_height, grid_settings.is_shown);
+        grid.draw(&mut window);
+        grid.draw_axes(&mut window, &AXES_COLOR);
+
+        // The selection rectangle is saved and loaded with the lines
+        if let Some(action) = line_set_action {
+            line_set_settings.status = match action {
+                LineSetAction::Save(format) => {
+                    let file_name = format!("lines.{}", format.extension());
+                    match lines_manager.line_set(selection_builder.corners()).save(Path::new(&file_name), format) {
+                        Ok(()) => format!("Saved {}", file_name),
+                        Err(error) => format!("Failed to save {}: {}", file_name, error),
+                    }
+                },
+                LineSetAction::Load(format) => {
+                    let file_name = format!("lines.{}", format.extension());
+                    match LineSet::load(Path::new(&file_name), format) {
+                        Ok(line_set) => {
+                            lines_manager.set_line_set(&line_set);
+                            selection_builder.set_corners(line_set.selection());
+                            line_set_settings.seed_text = line_set.seed.to_string();
+                            format!("Loaded {}", file_name)
+                        },
+                        Err(error) => format!("Failed to load {}: {}", file_name, error),
+                    }
+                },
+                LineSetAction::ExportPieces => match clipped_pieces.save(Path::new(PIECES_FILE_NAME)) {
+                    Ok(()) => format!("Exported {}", PIECES_FILE_NAME),
+                    Err(error) => format!("Failed to export {}: {}", PIECES_FILE_NAME, error),
+                },
+            };
+        }
+
+        // Cyrus-Beck needs a convex window, Weiler-Atherton only a simple one
+        polygon_selection_builder.set_shape(match clipping_settings.subject_kind {
+            SubjectKind::Lines | SubjectKind::Curves => PolygonShape::Convex,
+            SubjectKind::Polygons => PolygonShape::Simple,
+        });
+
+        // Any change of the lines since the last frame, degenerate lines included, is one edit
+        if !lines_manager.is_in_state(&lines_state) {
+            let state = lines_manager.state();
+            history.record(LinesEdit { from: lines_state, to: state.clone() });
+            lines_state = state;
+        }
+
+        // Clicks on the UI don't edit the window
+        let is_ui_capturing_mouse = window.is_conrod_ui_capturing_mouse();
+        let is_ui_capturing_keyboard = window.is_conrod_ui_capturing_keyboard();
+
         for event in window.events().iter() {
+            screenshots.handle_event(&event.value);
+            snap_key.handle_event(&event.value);
+            // The seed text box takes Ctrl+Z for itself
+            if let Some(action) = history.handle_event(&event.value).filter(|_| !is_ui_capturing_keyboard) {
+                history.perform(action, &mut lines_manager);
+                lines_state = lines_manager.state();
+                line_set_settings.seed_text = lines_manager.seed.to_string();
+            }
             match event.value {
                 WindowEvent::CursorPos(x, y, _modif) => {
                     cursor = Point::new(
                         cc.x_top_left_to_centered_p(x as f32), 
                         cc.y_top_left_to_centered_p(y as f32)
                     );
-                    selection_builder.update_cursor(cursor, None);
+                    let snap = |point| snap_corner(point, &grid, &snap_key, &lines_manager, &compound_builder);
+                    selection_builder.update_cursor(cursor, None, &snap);
+                    polygon_selection_builder.update_cursor(snap(cursor), None);
+                    subject_builder.update_cursor(cursor, None);
                 },
-                WindowEvent::MouseButton(btn, action ,_) => {
-                    if let MouseButton::Button1 = btn {
-                        selection_builder.update_cursor(cursor, Some(action));
+                WindowEvent::MouseButton(MouseButton::Button1, action ,_) => {
+                    let snap = |point| snap_corner(point, &grid, &snap_key, &lines_manager, &compound_builder);
+                    // A drag started on the canvas still ends when released over the UI
+                    let is_ui_press = is_ui_capturing_mouse && action == Action::Press;
+                    match clipping_settings.window_kind {
+                        WindowKind::Rectangle | WindowKind::Compound if !is_ui_press => selection_builder.update_cursor(cursor, Some(action), &snap),
+                        WindowKind::Polygon if !is_ui_capturing_mouse => polygon_selection_builder.update_cursor(snap(cursor), Some(action)),
+                        _ => {}
+                    }
+                }
+                WindowEvent::Key(key, Action::Press, _) if key == SAVE_SCENE_KEY => {
+                    let current_scene = Scene {
+                        lines: scene::Lines {
+                            count: lines_manager.lines_count,
+                            seed: lines_manager.seed,
+                            distribution: lines_manager.distribution,
+                        },
+                        selection: selection_builder.corners().map(|(start, end)| [start.into(), end.into()]),
+                        grid: grid_settings.clone(),
+                        ..scene().clone()
+                    };
+                    match scene_file::save(&scene_path, &current_scene) {
+                        Ok(()) => println!("Saved {}", scene_path.display()),
+                        Err(error) => eprintln!("{}", error),
                     }
                 }
+                WindowEvent::Key(key, Action::Press, modifiers)
+                    if clipping_settings.window_kind != WindowKind::Polygon && !is_ui_capturing_keyboard =>
+                {
+                    let nudge_step = scene().ui.nudge_step;
+                    let step = if modifiers.contains(Modifiers::Shift) { nudge_step * 10.0 } else { nudge_step };
+                    let offset = match key {
+                        Key::Left => Vector2::new(-step, 0.0),
+                        Key::Right => Vector2::new(step, 0.0),
+                        Key::Up => Vector2::new(0.0, step),
+                        Key::Down => Vector2::new(0.0, -step),
+                        _ => Vector2::zeros(),
+                    };
+                    selection_builder.nudge(offset);
+                }
+                WindowEvent::MouseButton(MouseButton::Button2, action ,_)
+                    if clipping_settings.subject_kind == SubjectKind::Polygons && !is_ui_capturing_mouse =>
+                {
+                    subject_builder.update_cursor(cursor, Some(action));
+                }
                 _ => {}
             }
         }
 
-        if let Some(selection) = selection_builder.build() {
-            selection.draw(&mut window);
-            lines_manager.draw_with_selection_check(&mut window, selection);
-        } else {
-            lines_manager.draw(&mut window);
+        // Among several rectangles, starting a new one keeps the previous
+        if clipping_settings.clear_windows {
+            compound_builder.clear();
+            clipping_settings.clear_windows = false;
+        }
+        compound_builder.mode = clipping_settings.combine_mode;
+        if let Some(replaced) = selection_builder.take_replaced() {
+            if clipping_settings.window_kind == WindowKind::Compound {
+                compound_builder.add(replaced);
+            }
+        }
+
+        let selection = match clipping_settings.window_kind {
+            WindowKind::Rectangle => {
+                selection_builder.draw_handles(&mut window);
+                selection_builder.build().map(Selection::Rectangle)
+            },
+            WindowKind::Compound => {
+                selection_builder.draw_handles(&mut window);
+                compound_builder.build(selection_builder.build()).map(Selection::Compound)
+            },
+            WindowKind::Polygon => {
+                polygon_selection_builder.draw(&mut window);
+                polygon_selection_builder.build().map(Selection::Polygon)
+            },
+        };
+
+        // Closed subject polygons join the drawn ones
+        if let Some(subject) = subject_builder.build() {
+            polygons_manager.add_polygon(subject.points().to_vec());
+            subject_builder.clear();
+        }
+        subject_builder.draw(&mut window);
+
+        let window_error = match (clipping_settings.window_kind, clipping_settings.subject_kind) {
+            (WindowKind::Polygon, _) => polygon_selection_builder.error(),
+            (WindowKind::Compound, SubjectKind::Polygons) => Some("Several rectangles only clip lines"),
+            _ => None,
+        };
+        clipping_settings.error = window_error.or(subject_builder.error()).map(str::to_string);
+
+        match (clipping_settings.subject_kind, selection) {
+            (SubjectKind::Lines, Some(selection)) => {
+                if clipping_settings.add_degenerate_lines {
+                    lines_manager.add_lines(&degenerate_lines(selection.bounds()));
+                }
+
+                selection.draw(&mut window);
+                let is_changed = lines_manager.update_selection_check(&selection, clipping_settings.algorithm);
+                lines_manager.draw_with_selection_check(&mut window);
+
+                if is_changed {
+                    clipping_settings.reports_outdated_at = Some(Instant::now());
+                }
+                let is_still = clipping_settings.reports_outdated_at.is_some_and(|changed_at| changed_at.elapsed() >= REPORTS_DELAY);
+                if is_still || clipping_settings.reports.is_empty() {
+                    let start = Instant::now();
+                    clipping_settings.reports = selection.algorithms()
+                        .iter()
+                        .map(|algorithm| lines_manager.report(&selection, *algorithm))
+                        .collect();
+                    clipping_settings.reports_outdated_at = None;
+                    frame_stats.reports_duration = start.elapsed();
+                }
+            },
+            (SubjectKind::Lines, None) => {
+                lines_manager.draw(&mut window);
+                clipping_settings.reports.clear();
+                clipping_settings.reports_outdated_at = None;
+            },
+            (SubjectKind::Polygons, Some(selection)) => {
+                selection.draw(&mut window);
+                polygons_manager.draw_with_selection_check(&mut window, &selection);
+            },
+            (SubjectKind::Polygons, None) => polygons_manager.draw(&mut window),
+            (SubjectKind::Curves, Some(selection)) => {
+                selection.draw(&mut window);
+                clipped_pieces = curves_manager.draw_with_selection_check(&mut window, &selection, clipping_settings.algorithm);
+            },
+            (SubjectKind::Curves, None) => {
+                curves_manager.draw(&mut window);
+                clipped_pieces = ClippedPieces::default();
+            },
         }
+        clipping_settings.add_degenerate_lines = false;
     }
 }
\ No newline at end of file
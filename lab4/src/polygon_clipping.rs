@@ -0,0 +1,574 @@
+use nalgebra::Vector2;
+
+use crate::common::{Point, Polygon, Borders};
+use crate::clipping::signed_double_area;
+
+// Distance under which a vertex is considered lying on the other polygon boundary
+const DEGENERACY_EPSILON: f32 = 1e-3;
+
+// Weiler-Atherton retries with a perturbed subject at most that many times
+const MAX_PERTURBATIONS: usize = 8;
+
+// Subject clipped by the four half-planes of the rectangle in turn.
+// A concave subject crossing the window several times comes back as a single polygon
+// whose pieces are joined by edges running along the borders; these enclose no area.
+pub fn sutherland_hodgman(subject: &[Point], borders: Borders) -> Polygon {
+    let (l, r, t, b) = borders;
+
+    // Signed distance inside each border
+    let inside_distances: [&dyn Fn(&Point) -> f32; 4] = [
+        &|p| p.x - l,
+        &|p| r - p.x,
+        &|p| p.y - b,
+        &|p| t - p.y,
+    ];
+
+    let mut output = subject.to_vec();
+    for inside_distance in inside_distances {
+        if output.is_empty() {
+            break;
+        }
+
+        let input = std::mem::take(&mut output);
+        for (i, current) in input.iter().enumerate() {
+            let previous = &input[(i + input.len() - 1) % input.len()];
+            let (d_current, d_previous) = (inside_distance(current), inside_distance(previous));
+
+            if d_current >= 0.0 {
+                if d_previous < 0.0 {
+                    output.push(previous + (current - previous) * (d_previous / (d_previous - d_current)));
+                }
+                output.push(*current);
+            } else if d_previous >= 0.0 {
+                output.push(previous + (current - previous) * (d_previous / (d_previous - d_current)));
+            }
+        }
+    }
+
+    without_degenerate_vertices(output)
+}
+
+// Consecutive duplicates and polygons without area are dropped
+fn without_degenerate_vertices(polygon: Polygon) -> Polygon {
+    let mut result: Polygon = Vec::with_capacity(polygon.len());
+    for point in polygon {
+        if result.last().is_none_or(|last| (last - point).norm() > DEGENERACY_EPSILON) {
+            result.push(point);
+        }
+    }
+    while result.len() > 1 && (result[0] - result[result.len() - 1]).norm() <= DEGENERACY_EPSILON {
+        result.pop();
+    }
+
+    if result.len() < 3 || signed_double_area(&result).abs() <= DEGENERACY_EPSILON {
+        vec![]
+    } else {
+        result
+    }
+}
+
+// Crossing of a subject edge with a clip edge
+struct Intersection {
+    point: Point,
+    subject_edge: usize,
+    subject_t: f32,
+    clip_edge: usize,
+    clip_t: f32,
+    // The subject goes inside the clip polygon here
+    entering: bool,
+}
+
+// Vertex of one of the two lists walked by Weiler-Atherton
+enum Node {
+    Vertex(Point),
+    Intersection(usize),
+}
+
+// Intersection of two simple polygons, which may be concave and may give several pieces.
+// Vertices lying on the other boundary make the entering / leaving classification ambiguous,
+// so the subject is moved by a fraction of a pixel until no such vertex remains.
+pub fn weiler_atherton(subject: &[Point], clip: &[Point]) -> Vec<Polygon> {
+    let subject = counter_clockwise(subject);
+    let clip = counter_clockwise(clip);
+    if subject.len() < 3 || clip.len() < 3 {
+        return vec![];
+    }
+
+    let mut perturbed = subject.clone();
+    for attempt in 0..MAX_PERTURBATIONS {
+        if let Some(intersections) = intersections(&perturbed, &clip) {
+            return weiler_atherton_traversal(&perturbed, &clip, &intersections);
+        }
+
+        let shift = DEGENERACY_EPSILON * 4.0 * (attempt + 1) as f32;
+        perturbed = subject.iter().map(|p| p + Vector2::new(shift, shift * 0.5)).collect();
+    }
+
+    vec![]
+}
+
+fn counter_clockwise(polygon: &[Point]) -> Polygon {
+    let mut polygon = without_degenerate_vertices(polygon.to_vec());
+    if signed_double_area(&polygon) < 0.0 {
+        polygon.reverse();
+    }
+    polygon
+}
+
+// All the edge crossings, or None when the configuration is degenerate
+fn intersections(subject: &[Point], clip: &[Point]) -> Option<Vec<Intersection>> {
+    let mut result = vec![];
+
+    for (subject_edge, (s0, s1)) in edges(subject).enumerate() {
+        for (clip_edge, (c0, c1)) in edges(clip).enumerate() {
+            let (ds, dc) = (s1 - s0, c1 - c0);
+            let denominator = ds.perp(&dc);
+            let offset = c0 - s0;
+
+            if denominator.abs() <= f32::EPSILON * ds.norm() * dc.norm() {
+                // Parallel edges only matter when they overlap
+                if offset.perp(&ds).abs() <= DEGENERACY_EPSILON * ds.norm() && segments_overlap(s0, s1, c0, c1) {
+                    return None;
+                }
+                continue;
+            }
+
+            let subject_t = offset.perp(&dc) / denominator;
+            let clip_t = offset.perp(&ds) / denominator;
+            let (subject_margin, clip_margin) = (DEGENERACY_EPSILON / ds.norm(), DEGENERACY_EPSILON / dc.norm());
+
+            let outside = subject_t < -subject_margin || subject_t > 1.0 + subject_margin
+                || clip_t < -clip_margin || clip_t > 1.0 + clip_margin;
+            if outside {
+                continue;
+            }
+            // Crossing at a vertex of either polygon
+            let at_vertex = subject_t <= subject_margin || subject_t >= 1.0 - subject_margin
+                || clip_t <= clip_margin || clip_t >= 1.0 - clip_margin;
+            if at_vertex {
+                return None;
+            }
+
+            result.push(Intersection {
+                point: s0 + ds * subject_t,
+                subject_edge,
+                subject_t,
+                clip_edge,
+                clip_t,
+                // Against the inner normal of the counter-clockwise clip edge
+                entering: dc.perp(&ds) > 0.0,
+            });
+        }
+    }
+
+    Some(result)
+}
+
+fn edges(polygon: &[Point]) -> impl Iterator<Item = (&Point, &Point)> {
+    polygon.iter().zip(polygon.iter().cycle().skip(1))
+}
+
+// Collinear segments sharing more than a point
+fn segments_overlap(s0: &Point, s1: &Point, c0: &Point, c1: &Point) -> bool {
+    let direction = s1 - s0;
+    let length = direction.norm_squared();
+    if length <= f32::EPSILON {
+        return false;
+    }
+
+    let (a, b) = ((c0 - s0).dot(&direction) / length, (c1 - s0).dot(&direction) / length);
+    a.max(b) > 0.0 && a.min(b) < 1.0
+}
+
+fn weiler_atherton_traversal(subject: &[Point], clip: &[Point], intersections: &[Intersection]) -> Vec<Polygon> {
+    if intersections.is_empty() {
+        // One polygon contains the other or they are disjoint
+        return if contains_point(clip, &subject[0]) {
+            vec![subject.to_vec()]
+        } else if contains_point(subject, &clip[0]) {
+            vec![clip.to_vec()]
+        } else {
+            vec![]
+        };
+    }
+
+    let subject_nodes = nodes(subject, intersections, |i| (i.subject_edge, i.subject_t));
+    let clip_nodes = nodes(clip, intersections, |i| (i.clip_edge, i.clip_t));
+    let position = |list: &[Node], index: usize| {
+        list.iter()
+            .position(|node| matches!(node, Node::Intersection(i) if *i == index))
+            .unwrap()
+    };
+
+    let mut visited = vec![false; intersections.len()];
+    let mut polygons = vec![];
+
+    for start in 0..intersections.len() {
+        if visited[start] || !intersections[start].entering {
+            continue;
+        }
+
+        let mut polygon = vec![];
+        let mut current = start;
+        let mut on_subject = true;
+
+        // Inside the clip polygon follow the subject, outside it follow the clip boundary
+        loop {
+            visited[current] = true;
+            polygon.push(intersections[current].point);
+
+            let list = if on_subject { &subject_nodes } else { &clip_nodes };
+            let mut index = position(list, current);
+            current = loop {
+                index = (index + 1) % list.len();
+                match list[index] {
+                    Node::Vertex(point) => polygon.push(point),
+                    Node::Intersection(next) => break next,
+                }
+            };
+            on_subject = !on_subject;
+
+            if current == start || visited[current] {
+                break;
+            }
+        }
+
+        let polygon = without_degenerate_vertices(polygon);
+        if !polygon.is_empty() {
+            polygons.push(polygon);
+        }
+    }
+
+    polygons
+}
+
+// Polygon vertices with the intersections inserted in order along each edge
+fn nodes(polygon: &[Point], intersections: &[Intersection], place: impl Fn(&Intersection) -> (usize, f32)) -> Vec<Node> {
+    let mut nodes = vec![];
+
+    for (edge, vertex) in polygon.iter().enumerate() {
+        nodes.push(Node::Vertex(*vertex));
+
+        let mut on_edge: Vec<(f32, usize)> = intersections
+            .iter()
+            .enumerate()
+            .filter(|(_, intersection)| place(intersection).0 == edge)
+            .map(|(index, intersection)| (place(intersection).1, index))
+            .collect();
+        on_edge.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        nodes.extend(on_edge.into_iter().map(|(_, index)| Node::Intersection(index)));
+    }
+
+    nodes
+}
+
+// Even-odd ray casting
+pub fn contains_point(polygon: &[Point], point: &Point) -> bool {
+    let mut inside = false;
+
+    for (a, b) in edges(polygon) {
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+// No two edges cross, except neighbours sharing their vertex
+pub fn is_simple(polygon: &[Point]) -> bool {
+    let count = polygon.len();
+    if count < 3 || signed_double_area(polygon).abs() <= f32::EPSILON {
+        return false;
+    }
+
+    let edges: Vec<(&Point, &Point)> = edges(polygon).collect();
+    for i in 0..count {
+        for j in (i + 1)..count {
+            let are_neighbours = j == i + 1 || (i == 0 && j == count - 1);
+            if !are_neighbours && segments_intersect(edges[i], edges[j]) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn segments_intersect((a0, a1): (&Point, &Point), (b0, b1): (&Point, &Point)) -> bool {
+    let orientation = |p: &Point, q: &Point, r: &Point| (q - p).perp(&(r - p));
+    let (d1, d2) = (orientation(b0, b1, a0), orientation(b0, b1, a1));
+    let (d3, d4) = (orientation(a0, a1, b0), orientation(a0, a1, b1));
+
+    if d1 == 0.0 && d2 == 0.0 {
+        // Collinear
+        return segments_overlap(a0, a1, b0, b1);
+    }
+
+    (d1 * d2 <= 0.0) && (d3 * d4 <= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Spacing of the samples counted by the reference area
+    const REFERENCE_STEP: f32 = 0.125;
+
+    const BORDERS: Borders = (20.0, 80.0, 70.0, 30.0);
+
+    fn polygon(points: &[(f32, f32)]) -> Polygon {
+        points.iter().map(|(x, y)| Point::new(*x, *y)).collect()
+    }
+
+    fn rectangle((l, r, t, b): Borders) -> Polygon {
+        polygon(&[(l, b), (r, b), (r, t), (l, t)])
+    }
+
+    fn area(polygon: &[Point]) -> f32 {
+        signed_double_area(polygon).abs() / 2.0
+    }
+
+    fn perimeter(polygon: &[Point]) -> f32 {
+        edges(polygon).map(|(a, b)| (b - a).norm()).sum()
+    }
+
+    // Area of the intersection by counting sample points inside both polygons.
+    // Samples sit between the integer coordinates used by the test polygons.
+    fn reference_area(subject: &[Point], clip: &[Point]) -> f32 {
+        let (min_x, max_x) = clip.iter().fold((f32::MAX, f32::MIN), |(min, max), p| (min.min(p.x), max.max(p.x)));
+        let (min_y, max_y) = clip.iter().fold((f32::MAX, f32::MIN), |(min, max), p| (min.min(p.y), max.max(p.y)));
+        let columns = ((max_x - min_x) / REFERENCE_STEP).ceil() as usize;
+        let rows = ((max_y - min_y) / REFERENCE_STEP).ceil() as usize;
+
+        let mut count = 0;
+        for i in 0..columns {
+            for j in 0..rows {
+                let point = Point::new(
+                    min_x + (i as f32 + 0.5) * REFERENCE_STEP,
+                    min_y + (j as f32 + 0.5) * REFERENCE_STEP,
+                );
+                if contains_point(subject, &point) && contains_point(clip, &point) {
+                    count += 1;
+                }
+            }
+        }
+
+        count as f32 * REFERENCE_STEP * REFERENCE_STEP
+    }
+
+    // Sampling misses at most a strip of one step along both boundaries
+    fn assert_reference_area(pieces: &[Polygon], subject: &[Point], clip: &[Point], extra_tolerance: f32) {
+        let clipped: f32 = pieces.iter().map(|piece| area(piece)).sum();
+        let reference = reference_area(subject, clip);
+        let tolerance = (perimeter(subject) + perimeter(clip)) * REFERENCE_STEP / 2.0 + extra_tolerance;
+
+        assert!(
+            (clipped - reference).abs() <= tolerance,
+            "{:?} clipped by {:?} has area {}, the reference {}",
+            subject, clip, clipped, reference
+        );
+    }
+
+    fn inside_window(point: &Point) -> bool {
+        let (l, r, t, b) = BORDERS;
+        let e = DEGENERACY_EPSILON;
+
+        l - e <= point.x && point.x <= r + e && b - e <= point.y && point.y <= t + e
+    }
+
+    // Three teeth hanging into the window from a bar above its top border
+    fn comb() -> Polygon {
+        polygon(&[
+            (10.0, 40.0), (25.0, 40.0), (25.0, 80.0), (40.0, 80.0), (40.0, 40.0), (60.0, 40.0),
+            (60.0, 80.0), (75.0, 80.0), (75.0, 40.0), (90.0, 40.0), (90.0, 90.0), (10.0, 90.0),
+        ])
+    }
+
+    // Clip window open at the top, with two prongs
+    fn u_shape() -> Polygon {
+        polygon(&[(20.0, 20.0), (80.0, 20.0), (80.0, 80.0), (60.0, 80.0), (60.0, 40.0), (40.0, 40.0), (40.0, 80.0), (20.0, 80.0)])
+    }
+
+    #[test]
+    fn sutherland_hodgman_matches_the_reference_area() {
+        let window = rectangle(BORDERS);
+        let subjects = [
+            // across a corner
+            polygon(&[(0.0, 50.0), (50.0, 100.0), (0.0, 100.0)]),
+            polygon(&[(10.0, 10.0), (60.0, 20.0), (50.0, 60.0)]),
+            // sharing a border edge and lying on two borders
+            polygon(&[(20.0, 30.0), (50.0, 30.0), (50.0, 70.0), (20.0, 70.0)]),
+            // with vertices on the borders
+            polygon(&[(20.0, 50.0), (50.0, 30.0), (80.0, 50.0), (50.0, 70.0)]),
+            polygon(&[(50.0, 70.0), (90.0, 100.0), (10.0, 100.0)]),
+            // fully inside
+            polygon(&[(30.0, 40.0), (70.0, 40.0), (50.0, 60.0)]),
+        ];
+
+        for subject in subjects {
+            let clipped = sutherland_hodgman(&subject, BORDERS);
+            assert!(clipped.iter().all(inside_window), "{:?} gives {:?}", subject, clipped);
+            assert_reference_area(&[clipped], &subject, &window, 0.0);
+        }
+    }
+
+    #[test]
+    fn sutherland_hodgman_keeps_a_subject_inside_and_drops_one_outside() {
+        let inside = polygon(&[(30.0, 40.0), (70.0, 40.0), (50.0, 60.0)]);
+        assert_eq!(sutherland_hodgman(&inside, BORDERS), inside);
+
+        let outside = polygon(&[(100.0, 40.0), (120.0, 40.0), (110.0, 60.0)]);
+        assert!(sutherland_hodgman(&outside, BORDERS).is_empty());
+
+        // Touching the window along a border only
+        let touching = polygon(&[(80.0, 40.0), (120.0, 40.0), (80.0, 60.0)]);
+        assert!(sutherland_hodgman(&touching, BORDERS).is_empty());
+    }
+
+    #[test]
+    fn sutherland_hodgman_gives_the_window_for_a_subject_containing_it() {
+        let subject = polygon(&[(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)]);
+        let clipped = sutherland_hodgman(&subject, BORDERS);
+
+        assert_eq!(clipped.len(), 4, "{:?}", clipped);
+        for corner in rectangle(BORDERS) {
+            assert!(clipped.iter().any(|p| (p - corner).norm() < 1e-3), "{:?}", clipped);
+        }
+    }
+
+    #[test]
+    fn sutherland_hodgman_joins_the_pieces_of_a_concave_subject_along_the_border() {
+        let (_, _, t, _) = BORDERS;
+        let subject = comb();
+        let clipped = sutherland_hodgman(&subject, BORDERS);
+
+        assert!(clipped.iter().all(inside_window), "{:?}", clipped);
+        // The bridges between the teeth enclose no area
+        assert_reference_area(std::slice::from_ref(&clipped), &subject, &rectangle(BORDERS), 0.0);
+        assert!((area(&clipped) - 900.0).abs() < 1e-2, "{:?}", clipped);
+
+        // Over the gaps between the teeth, going the other way than the top border itself
+        let on_top = |x: f32, point: &Point| (point.x - x).abs() < 1e-3 && (point.y - t).abs() < 1e-3;
+        for (start, end) in [(25.0, 40.0), (60.0, 75.0)] {
+            assert!(edges(&clipped).any(|(a, b)| on_top(start, a) && on_top(end, b)), "{:?}", clipped);
+        }
+    }
+
+    #[test]
+    fn weiler_atherton_matches_the_reference_area() {
+        let window = rectangle(BORDERS);
+        let diamond = polygon(&[(50.0, 12.0), (88.0, 50.0), (50.0, 88.0), (12.0, 50.0)]);
+        let cases = [
+            (polygon(&[(0.0, 50.0), (50.0, 100.0), (0.0, 100.0)]), window.clone()),
+            (polygon(&[(10.0, 10.0), (60.0, 20.0), (50.0, 60.0)]), window.clone()),
+            (diamond.clone(), window.clone()),
+            (comb(), window.clone()),
+            (polygon(&[(0.0, 50.0), (50.0, 100.0), (0.0, 100.0)]), u_shape()),
+            (diamond.clone(), u_shape()),
+            // concave subject and clip
+            (u_shape(), diamond),
+        ];
+
+        for (subject, clip) in cases {
+            assert_reference_area(&weiler_atherton(&subject, &clip), &subject, &clip, 0.0);
+        }
+    }
+
+    #[test]
+    fn weiler_atherton_gives_several_pieces_for_a_concave_clip() {
+        let clip = u_shape();
+        let subject = polygon(&[(10.0, 60.0), (90.0, 60.0), (90.0, 70.0), (10.0, 70.0)]);
+        let pieces = weiler_atherton(&subject, &clip);
+
+        assert_eq!(pieces.len(), 2, "{:?}", pieces);
+        for piece in &pieces {
+            assert!((area(piece) - 200.0).abs() < 1e-2, "{:?}", piece);
+        }
+
+        let diamond = polygon(&[(50.0, 12.0), (88.0, 50.0), (50.0, 88.0), (12.0, 50.0)]);
+        let pieces = weiler_atherton(&diamond, &clip);
+        assert_eq!(pieces.len(), 1, "{:?}", pieces);
+
+        // The teeth of the comb in the window are separate pieces
+        assert_eq!(weiler_atherton(&comb(), &rectangle(BORDERS)).len(), 3);
+    }
+
+    #[test]
+    fn weiler_atherton_falls_back_without_crossings() {
+        let window = rectangle(BORDERS);
+
+        let inside = polygon(&[(30.0, 40.0), (70.0, 40.0), (50.0, 60.0)]);
+        assert_eq!(weiler_atherton(&inside, &window), vec![inside.clone()]);
+
+        let containing = polygon(&[(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)]);
+        assert_eq!(weiler_atherton(&containing, &window), vec![window.clone()]);
+
+        let outside = polygon(&[(100.0, 40.0), (120.0, 40.0), (110.0, 60.0)]);
+        assert!(weiler_atherton(&outside, &window).is_empty());
+
+        // A clip winding clockwise gives the same results
+        let reversed: Polygon = window.iter().rev().cloned().collect();
+        assert_eq!(weiler_atherton(&inside, &reversed), vec![inside]);
+        assert!(weiler_atherton(&outside, &reversed).is_empty());
+    }
+
+    #[test]
+    fn weiler_atherton_perturbs_shared_edges_and_vertices_on_the_boundary() {
+        let window = rectangle(BORDERS);
+        let subjects = [
+            // sharing an edge
+            polygon(&[(20.0, 30.0), (50.0, 30.0), (50.0, 70.0), (20.0, 70.0)]),
+            // overlapping a border and crossing it
+            polygon(&[(0.0, 30.0), (50.0, 30.0), (50.0, 50.0), (0.0, 50.0)]),
+            // vertices on the borders
+            polygon(&[(20.0, 50.0), (50.0, 30.0), (80.0, 50.0), (50.0, 70.0)]),
+            polygon(&[(50.0, 70.0), (90.0, 100.0), (10.0, 100.0), (10.0, 60.0)]),
+            // the same polygon
+            window.clone(),
+        ];
+
+        for subject in subjects {
+            assert!(intersections(&counter_clockwise(&subject), &window).is_none(), "{:?}", subject);
+
+            // The shifts add up to a fraction of a pixel along the subject boundary
+            let shift = DEGENERACY_EPSILON * 4.0 * MAX_PERTURBATIONS as f32 * 1.5;
+            let pieces = weiler_atherton(&subject, &window);
+            assert_reference_area(&pieces, &subject, &window, shift * perimeter(&subject));
+        }
+    }
+
+    #[test]
+    fn simple_polygons() {
+        let simple = [
+            rectangle(BORDERS),
+            comb(),
+            u_shape(),
+            polygon(&[(0.0, 0.0), (10.0, 0.0), (5.0, 1.0)]),
+        ];
+        for polygon in simple {
+            assert!(is_simple(&polygon), "{:?}", polygon);
+            let reversed: Polygon = polygon.iter().rev().cloned().collect();
+            assert!(is_simple(&reversed), "{:?}", reversed);
+        }
+
+        let not_simple = [
+            // too few vertices or no area
+            polygon(&[(0.0, 0.0), (10.0, 0.0)]),
+            polygon(&[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)]),
+            // bow tie
+            polygon(&[(0.0, 0.0), (10.0, 10.0), (10.0, 0.0), (0.0, 10.0)]),
+            // two loops touching in a vertex
+            polygon(&[(0.0, 0.0), (10.0, 0.0), (5.0, 5.0), (10.0, 10.0), (0.0, 10.0), (5.0, 5.0)]),
+            // an edge folding back over its neighbours
+            polygon(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (10.0, 5.0), (0.0, 10.0)]),
+        ];
+        for polygon in not_simple {
+            assert!(!is_simple(&polygon), "{:?}", polygon);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use lab_common::grid::GridSettings;
+use crate::line_set::Distribution;
+use lab_common::scene_file::{Color, Errors, Validate};
+
+static SCENE: OnceLock<Scene> = OnceLock::new();
+
+// The random lines and the window at startup, the colors they are drawn with and the UI limits
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    pub lines: Lines,
+    // Opposite corners of the selection rectangle, in pixels from the window center
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selection: Option<[[f32; 2]; 2]>,
+    pub colors: Colors,
+    pub ui: Ui,
+    pub grid: GridSettings,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Lines {
+    pub count: u32,
+    pub seed: u64,
+    pub distribution: Distribution,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub line: Color,
+    pub selected_line: Color,
+    pub selected_fill: Color,
+    pub selection_line: Color,
+    pub unfinished_selection_line: Color,
+    // Windows of the compound selection, by how they combine
+    pub add: Color,
+    pub subtract: Color,
+    pub intersect: Color,
+    // Drawn with the grid
+    pub axes: Color,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ui {
+    pub max_lines_count: u32,
+    // Pixels the arrow keys move the selection rectangle by, ten times more with Shift
+    pub nudge_step: f32,
+}
+
+impl Default for Lines {
+    fn default() -> Self {
+        Lines { count: 0, seed: 0, distribution: Distribution::Uniform }
+    }
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            line: [0.6, 0.6, 0.6],
+            selected_line: [1.0, 0.0, 0.0],
+            selected_fill: [1.0, 0.7, 0.7],
+            selection_line: [1.0, 0.0, 0.0],
+            unfinished_selection_line: [1.0, 0.6, 0.6],
+            add: [1.0, 0.0, 0.0],
+            subtract: [0.0, 0.0, 1.0],
+            intersect: [0.0, 0.6, 0.0],
+            axes: [0.5, 0.5, 0.5],
+        }
+    }
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Ui { max_lines_count: 500_000, nudge_step: 1.0 }
+    }
+}
+
+impl Validate for Scene {
+    fn validate(&self, errors: &mut Errors) {
+        errors.check("lines.count", self.lines.count <= self.ui.max_lines_count, "must not exceed ui.max_lines_count");
+        errors.check("ui.nudge_step", self.ui.nudge_step > 0.0, "must be positive");
+
+        let colors = &self.colors;
+        for (key, color) in [
+            ("colors.line", &colors.line),
+            ("colors.selected_line", &colors.selected_line),
+            ("colors.selected_fill", &colors.selected_fill),
+            ("colors.selection_line", &colors.selection_line),
+            ("colors.unfinished_selection_line", &colors.unfinished_selection_line),
+            ("colors.add", &colors.add),
+            ("colors.subtract", &colors.subtract),
+            ("colors.intersect", &colors.intersect),
+            ("colors.axes", &colors.axes),
+        ] {
+            errors.color(key, color);
+        }
+        self.grid.validate(errors);
+    }
+}
+
+// Set once at startup, before anything reads the scene
+pub fn install(scene: Scene) {
+    let _ = SCENE.set(scene);
+}
+
+pub fn scene() -> &'static Scene {
+    SCENE.get_or_init(Scene::default)
+}
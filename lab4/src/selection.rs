@@ -1,23 +1,116 @@
 use std::cmp::{Ordering};
+use std::time::{Duration, Instant};
 
 use kiss3d::event::Action;
 use kiss3d::window::Window;
 
-use nalgebra::{Point3};
+use nalgebra::{Point3, Vector2};
 
-use crate::common::{Point, LineClipping, Line};
+use crate::common::{Point, LineClipping, Line, Borders, Polygon};
+use crate::clipping::{LineClipper, OperationCounters, ClippingAlgorithm, CyrusBeck, CLIPPING_EPSILON, is_convex, signed_double_area};
+use crate::polygon_clipping::{is_simple, contains_point, sutherland_hodgman, weiler_atherton};
+use crate::compound_selection::CompoundSelection;
+use crate::scene::scene;
+use lab_common::scene_file::color;
 
 use lazy_static::lazy_static;
 
 lazy_static! {
-    static ref SELECTION_LINE_COLOR: Point3<f32> = Point3::new(1.0, 0.0, 0.0);
+    static ref SELECTION_LINE_COLOR: Point3<f32> = color(scene().colors.selection_line);
+    static ref UNFINISHED_SELECTION_LINE_COLOR: Point3<f32> = color(scene().colors.unfinished_selection_line);
 }
 
+// Side of the square handles, and how far from their center they can be grabbed
+const HANDLE_SIZE: f32 = 8.0;
+
+// Two clicks closer in time close the polygon
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+// Window the lines are clipped against
+#[derive(Clone, PartialEq)]
+pub enum Selection {
+    Rectangle(RectangleSelection),
+    Polygon(PolygonSelection),
+    Compound(CompoundSelection),
+}
+
+impl Selection {
+    // Algorithms able to clip against this window
+    pub fn algorithms(&self) -> &'static [ClippingAlgorithm] {
+        match self {
+            Selection::Rectangle(_) | Selection::Compound(_) => &ClippingAlgorithm::ALL,
+            Selection::Polygon(_) => &[ClippingAlgorithm::CyrusBeck],
+        }
+    }
+
+    // The polygon is always clipped with Cyrus-Beck
+    pub fn clipping_check(&self, line: &Line, algorithm: ClippingAlgorithm, counters: &mut OperationCounters) -> LineClipping {
+        match self {
+            Selection::Rectangle(selection) => selection.clipping_check(line, algorithm.clipper(), counters),
+            Selection::Polygon(selection) => selection.clipping_check(line, counters),
+            Selection::Compound(selection) => selection.clipping_check(line, algorithm, counters),
+        }
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        match self {
+            Selection::Rectangle(selection) => selection.contains(point),
+            Selection::Polygon(selection) => contains_point(selection.points(), point),
+            Selection::Compound(selection) => selection.contains(point),
+        }
+    }
+
+    // left, right, top, bottom of the bounding box
+    pub fn bounds(&self) -> Borders {
+        match self {
+            Selection::Rectangle(selection) => selection.borders(),
+            Selection::Polygon(selection) => {
+                let points = selection.points();
+                let x = |f: fn(f32, f32) -> f32, init| points.iter().map(|p| p.x).fold(init, f);
+                let y = |f: fn(f32, f32) -> f32, init| points.iter().map(|p| p.y).fold(init, f);
+                (x(f32::min, f32::INFINITY), x(f32::max, f32::NEG_INFINITY), y(f32::max, f32::NEG_INFINITY), y(f32::min, f32::INFINITY))
+            },
+            Selection::Compound(selection) => selection.bounds(),
+        }
+    }
+
+    // Edges of every window the region is made of
+    pub fn boundary(&self) -> Vec<Line> {
+        let closed = |points: &[Point]| -> Vec<Line> {
+            points.iter().zip(points.iter().cycle().skip(1)).map(|(a, b)| (*a, *b)).collect()
+        };
+
+        match self {
+            Selection::Rectangle(selection) => closed(&selection.points),
+            Selection::Polygon(selection) => closed(selection.points()),
+            Selection::Compound(selection) => selection.windows().flat_map(|window| closed(&window.points)).collect(),
+        }
+    }
+
+    // Sutherland-Hodgman against the rectangle, Weiler-Atherton against the polygon.
+    // Compound windows only clip lines.
+    pub fn clip_polygon(&self, subject: &[Point]) -> Vec<Polygon> {
+        match self {
+            Selection::Rectangle(selection) => vec![sutherland_hodgman(subject, selection.borders())],
+            Selection::Polygon(selection) => weiler_atherton(subject, selection.points()),
+            Selection::Compound(_) => vec![],
+        }
+    }
+
+    pub fn draw(&self, window: &mut Window) {
+        match self {
+            Selection::Rectangle(selection) => selection.draw(window),
+            Selection::Polygon(selection) => selection.draw(window),
+            Selection::Compound(selection) => selection.draw(window),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct RectangleSelection {
     // clockwise from top-left
     points: [Point; 4],
-    // left, right, top, bottom
-    borders: (f32, f32, f32, f32)
+    borders: Borders
 }
 
 impl RectangleSelection {
@@ -25,7 +118,7 @@ impl RectangleSelection {
         let third = Point::new(first.x, second.y);
         let fourth = Point::new(second.x, first.y);
 
-        let mut points = vec![first, second, third, fourth];
+        let mut points = [first, second, third, fourth];
         points.sort_by(|b, a| {
             if a.y > b.y {
                 Ordering::Greater
@@ -42,177 +135,369 @@ impl RectangleSelection {
         points.swap(2, 3);
 
 
-        let points: [Point; 4] = points.try_into().unwrap();
         Self {
-            points: points,
+            points,
             borders: (points[0].x, points[1].x, points[0].y, points[2].y)
         }
     }
 
-    pub fn clipping_check(&self, line: &Line) -> LineClipping {
-        if let Some(clipping) = self.trivial_clipping_check(line) {
-            clipping
-        } else { 
-            self.complex_clipping_check(line)
-        }
+    pub fn points(&self) -> &[Point; 4] {
+        &self.points
+    }
+
+    pub fn borders(&self) -> Borders {
+        self.borders
     }
 
-    fn complex_clipping_check(&self, line: &Line) -> LineClipping {
+    pub fn contains(&self, point: &Point) -> bool {
         let (l, r, t, b) = self.borders;
-        let (start, end) = line;
-        let (xs, xe, ys, ye) = (start.x, end.x, start.y, end.y);
-        let (dx, dy) = (xe - xs, ye - ys);
+        let e = CLIPPING_EPSILON;
 
-        let xt = |t: f32| {
-            xs + dx * t
-        };
+        l - e <= point.x && point.x <= r + e && b - e <= point.y && point.y <= t + e
+    }
+
+    pub fn clipping_check(&self, line: &Line, clipper: &dyn LineClipper, counters: &mut OperationCounters) -> LineClipping {
+        clipper.clip(self.borders, line, counters)
+    }
+
+    pub fn draw(&self, window: &mut Window) {
+        self.draw_colored(window, &SELECTION_LINE_COLOR);
+    }
+
+    pub fn draw_colored(&self, window: &mut Window, color: &Point3<f32>) {
+        for (i, point) in self.points.iter().enumerate() {
+            let start = point;
+            let end = if i != self.points.len() - 1 {
+                self.points[i + 1]
+            } else {
+                self.points[0]
+            };
 
-        let yt = |t: f32| {
-            ys + dy * t
+            window.draw_planar_line(start, &end, color);
+        }
+    }
+}
+
+// Which borders of the rectangle follow the cursor while a handle is dragged
+#[derive(Clone, Copy)]
+struct Handle {
+    left: bool,
+    right: bool,
+    top: bool,
+    bottom: bool,
+}
+
+impl Handle {
+    // Corners first, so they win over the edges next to them
+    const ALL: [Handle; 8] = [
+        Handle { left: true, right: false, top: true, bottom: false },
+        Handle { left: false, right: true, top: true, bottom: false },
+        Handle { left: false, right: true, top: false, bottom: true },
+        Handle { left: true, right: false, top: false, bottom: true },
+        Handle { left: true, right: false, top: false, bottom: false },
+        Handle { left: false, right: true, top: false, bottom: false },
+        Handle { left: false, right: false, top: true, bottom: false },
+        Handle { left: false, right: false, top: false, bottom: true },
+    ];
+
+    fn position(&self, (l, r, t, b): Borders) -> Point {
+        let pick = |first: bool, second: bool, a: f32, c: f32| match (first, second) {
+            (true, _) => a,
+            (_, true) => c,
+            _ => (a + c) / 2.0,
         };
+        Point::new(pick(self.left, self.right, l, r), pick(self.top, self.bottom, t, b))
+    }
+}
 
-        let t_i = vec![
-            (l - xs) / dx, // left
-            (r - xs) / dx, // right
-            (t - ys) / dy, // top
-            (b - ys) / dy, // bottom
-        ];
-
-        let point_i = vec![
-            Point::new(xt(t_i[0]), yt(t_i[0])),
-            Point::new(xt(t_i[1]), yt(t_i[1])),
-            Point::new(xt(t_i[2]), yt(t_i[2])),
-            Point::new(xt(t_i[3]), yt(t_i[3])),
-        ];
-
-        let mut first_point: Option<Point> = None;
-        for (i, point) in point_i.iter().enumerate() {
-            if 0.0 <= t_i[i] && t_i[i] <= 1.0 {
-                if self.is_point_on_border(&point) {
-                    if first_point.is_none() {
-                        first_point = Some(*point);
-                    } else {
-                        return LineClipping::PartlyInside((first_point.unwrap(), *point));
-                    }
-                }
+#[derive(Clone, Copy)]
+enum Drag {
+    // Second corner of a new rectangle
+    New,
+    // Cursor and rectangle as the drag started, and the corner nearest to the cursor, the one that snaps
+    Move { grab: Point, start: Point, end: Point, corner: Point },
+    Resize(Handle),
+}
+
+#[derive(Default)]
+pub struct RectangleSelectionBuilder {
+    start_point: Option<Point>,
+    end_point: Option<Point>,
+    drag: Option<Drag>,
+    // Rectangle dropped by the last new one, until taken
+    replaced: Option<RectangleSelection>,
+}
+
+impl RectangleSelectionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Corners go where `snap` moves them, the handles are still grabbed at the cursor
+    pub fn update_cursor(&mut self, cursor: Point, action: Option<Action>, snap: &dyn Fn(Point) -> Point) {
+        if let Some(action) = action {
+            match action {
+                Action::Press => self.mouse_pressed(cursor, snap),
+                Action::Release => self.mouse_released()
             }
         }
 
-        if first_point.is_none() {
-            LineClipping::Outside
-        } else {
-            let first_point = first_point.unwrap();
-            let first_candidate = Point::new(xt(0.0), yt(0.0));
-            let second_candidate = Point::new(xt(1.0), yt(1.0));
-            if self.clipping_byte_code(&first_candidate) == 0u8 {
-                LineClipping::PartlyInside((first_candidate, first_point))
-            } else {
-                LineClipping::PartlyInside((first_point, second_candidate))
+        match self.drag {
+            Some(Drag::New) => self.end_point = Some(snap(cursor)),
+            Some(Drag::Move { grab, start, end, corner }) => {
+                let offset = snap(corner + (cursor - grab)) - corner;
+                self.start_point = Some(start + offset);
+                self.end_point = Some(end + offset);
+            },
+            Some(Drag::Resize(handle)) => self.resize(handle, snap(cursor)),
+            None => {}
+        }
+    }
+
+    // Handles and the inside edit the current rectangle, anywhere else starts a new one
+    fn mouse_pressed(&mut self, cursor: Point, snap: &dyn Fn(Point) -> Point) {
+        let selection = self.build();
+        let handle = selection.as_ref().and_then(|selection| {
+            Handle::ALL
+                .into_iter()
+                .find(|handle| (handle.position(selection.borders) - cursor).norm() <= HANDLE_SIZE)
+        });
+
+        self.drag = match (handle, selection) {
+            (Some(handle), Some(selection)) => {
+                let (l, r, t, b) = selection.borders;
+                self.start_point = Some(Point::new(l, t));
+                self.end_point = Some(Point::new(r, b));
+                Some(Drag::Resize(handle))
+            },
+            (None, Some(selection)) if selection.contains(&cursor) => {
+                let corner = selection.points
+                    .into_iter()
+                    .min_by(|a, b| (a - cursor).norm().total_cmp(&(b - cursor).norm()))
+                    .unwrap_or(cursor);
+                let (l, r, t, b) = selection.borders;
+                Some(Drag::Move { grab: cursor, start: Point::new(l, t), end: Point::new(r, b), corner })
+            },
+            (_, selection) => {
+                self.replaced = selection;
+                self.start_point = Some(snap(cursor));
+                self.end_point = None;
+                Some(Drag::New)
             }
+        };
+    }
+
+    fn mouse_released(&mut self) {
+        self.drag = None;
+    }
+
+    // The corners were made top-left and bottom-right on press, so a handle keeps moving
+    // the same coordinates even when dragged past the opposite border
+    fn resize(&mut self, handle: Handle, cursor: Point) {
+        if let (Some(start_point), Some(end_point)) = (self.start_point.as_mut(), self.end_point.as_mut()) {
+            if handle.left { start_point.x = cursor.x; }
+            if handle.right { end_point.x = cursor.x; }
+            if handle.top { start_point.y = cursor.y; }
+            if handle.bottom { end_point.y = cursor.y; }
         }
     }
 
-    fn trivial_clipping_check(&self, line: &Line) -> Option<LineClipping> {
-        let (start, end) = line;
+    pub fn nudge(&mut self, offset: Vector2<f32>) {
+        if let (Some(start_point), Some(end_point)) = (self.start_point, self.end_point) {
+            self.start_point = Some(start_point + offset);
+            self.end_point = Some(end_point + offset);
+        }
+    }
 
-        let start_byte_code = self.clipping_byte_code(start);
-        let end_byte_code = self.clipping_byte_code(end);
+    pub fn take_replaced(&mut self) -> Option<RectangleSelection> {
+        self.replaced.take()
+    }
+
+    pub fn corners(&self) -> Option<(Point, Point)> {
+        self.start_point.zip(self.end_point)
+    }
+
+    pub fn set_corners(&mut self, corners: Option<(Point, Point)>) {
+        self.start_point = corners.map(|(start, _)| start);
+        self.end_point = corners.map(|(_, end)| end);
+        self.drag = None;
+    }
+
+    pub fn draw_handles(&self, window: &mut Window) {
+        let Some(selection) = self.build() else {
+            return;
+        };
+
+        for handle in Handle::ALL {
+            let center = handle.position(selection.borders);
+            let corners = [(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)]
+                .map(|(x, y)| Point::new(center.x + x * HANDLE_SIZE / 2.0, center.y + y * HANDLE_SIZE / 2.0));
+            draw_polyline(window, &corners, true, &SELECTION_LINE_COLOR);
+        }
+    }
 
-        if start_byte_code | end_byte_code == 0u8 {
-            Some(LineClipping::Inside)
-        } else if start_byte_code & end_byte_code != 0u8 {
-            Some(LineClipping::Outside)
+    pub fn build(&self) -> Option<RectangleSelection> {
+        if let (Some(start_point), Some(end_point)) = (self.start_point, self.end_point) {
+            Some(RectangleSelection::new(start_point, end_point))
         } else {
             None
         }
     }
+}
 
-    fn clipping_byte_code(&self, point: &Point) -> u8 {
-        let mut code = 0u8;
-        let (l, r, t, b) = self.borders;
-        let x = point.x;
-        let y = point.y;
+#[derive(Clone, Copy, PartialEq)]
+pub enum PolygonShape {
+    Convex,
+    // without self-intersections
+    Simple,
+}
 
-        if x < l { code |= 1u8 << 3; }
-        if x > r { code |= 1u8 << 2; }
-        if y < b { code |= 1u8 << 1; }
-        if y > t { code |= 1u8; }
+impl PolygonShape {
+    fn check(&self, points: &[Point]) -> Result<(), String> {
+        if points.len() < 3 {
+            return Err("The polygon needs at least 3 vertices".to_string());
+        }
 
-        code
+        match self {
+            PolygonShape::Convex if !is_convex(points) => Err("The polygon must be convex".to_string()),
+            PolygonShape::Simple if !is_simple(points) => Err("The polygon must not intersect itself".to_string()),
+            _ => Ok(()),
+        }
     }
+}
 
-    fn is_point_on_border(&self, point: &Point) -> bool {
-        // acceptable error
-        let e = 2.0;
-        let (l, r, t, b) = self.borders;
-        let (x, y) = (point.x, point.y);
+#[derive(Clone, PartialEq)]
+pub struct PolygonSelection {
+    // counter-clockwise
+    points: Vec<Point>,
+}
 
-        (l - e < x && x < l + e && b < y && y < t ) || // left border
-        (r - e < x && x < r + e && b < y && y < t ) || // right border
-        (t - e < y && y < t + e && l < x && x < r ) || // top border
-        (b - e < y && y < b + e && l < x && x < r ) // bottom border
+impl PolygonSelection {
+    pub fn points(&self) -> &[Point] {
+        &self.points
     }
 
-    pub fn draw(&self, window: &mut Window) {
-        for (i, point) in self.points.iter().enumerate() {
-            let start = point;
-            let end = if i != self.points.len() - 1 {
-                self.points[i + 1]
-            } else {
-                self.points[0]
-            };
+    // Only valid for convex polygons
+    pub fn clipping_check(&self, line: &Line, counters: &mut OperationCounters) -> LineClipping {
+        CyrusBeck.clip_polygon(&self.points, line, counters)
+    }
 
-            window.draw_planar_line(start, &end, &SELECTION_LINE_COLOR);
-        }
+    pub fn draw(&self, window: &mut Window) {
+        draw_polyline(window, &self.points, true, &SELECTION_LINE_COLOR);
     }
 }
 
-pub struct RectangleSelectionBuilder {
-    start_point: Option<Point>,
-    end_point: Option<Point>,
-    is_pressed: bool,
+pub struct PolygonSelectionBuilder {
+    shape: PolygonShape,
+    points: Vec<Point>,
+    cursor: Point,
+    is_closed: bool,
+    last_press: Option<Instant>,
+    error: Option<String>,
 }
 
-impl RectangleSelectionBuilder {
-    pub fn new() -> Self {
+impl PolygonSelectionBuilder {
+    pub fn new(shape: PolygonShape) -> Self {
         Self {
-            start_point: None,
-            end_point: None,
-            is_pressed: false
+            shape,
+            points: vec![],
+            cursor: Point::origin(),
+            is_closed: false,
+            last_press: None,
+            error: None,
         }
     }
 
-    pub fn update_cursor(&mut self, cursor: Point, action: Option<Action>) {
-        if let Some(action) = action {
-            match action {
-                Action::Press => self.mouse_pressed(cursor),
-                Action::Release => self.mouse_released()
+    // A closed polygon not matching the new shape is rejected
+    pub fn set_shape(&mut self, shape: PolygonShape) {
+        if self.shape == shape {
+            return;
+        }
+
+        self.shape = shape;
+        if self.is_closed {
+            if let Err(error) = shape.check(&self.points) {
+                self.error = Some(error);
+                self.clear();
             }
         }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.is_closed = false;
+    }
+
+    // Why the last polygon was rejected
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn update_cursor(&mut self, cursor: Point, action: Option<Action>) {
+        self.cursor = cursor;
 
-        if self.is_pressed {
-            self.end_point = Some(cursor);
+        if let Some(Action::Press) = action {
+            let now = Instant::now();
+            let is_double_click = self.last_press.is_some_and(|last| now - last < DOUBLE_CLICK_INTERVAL);
+            self.last_press = Some(now);
+
+            if is_double_click && !self.is_closed {
+                self.close();
+                // A third click shouldn't count as another double click
+                self.last_press = None;
+            } else {
+                self.mouse_pressed(cursor);
+            }
         }
     }
 
     fn mouse_pressed(&mut self, cursor: Point) {
-        self.start_point = Some(cursor);
-        self.end_point = None;
-        self.is_pressed = true;
+        if self.is_closed {
+            self.points.clear();
+            self.is_closed = false;
+        }
+        self.error = None;
+        self.points.push(cursor);
     }
 
-    fn mouse_released(&mut self) {
-        self.is_pressed = false;
+    fn close(&mut self) {
+        match self.shape.check(&self.points) {
+            Ok(()) => {
+                if signed_double_area(&self.points) < 0.0 {
+                    self.points.reverse();
+                }
+                self.is_closed = true;
+            },
+            Err(error) => {
+                self.error = Some(error);
+                self.points.clear();
+            }
+        }
     }
 
-    pub fn build(&self) -> Option<RectangleSelection> {
-        if self.start_point.is_some() && self.end_point.is_some() {
-            Some(RectangleSelection::new (
-                self.start_point.unwrap(),
-                self.end_point.unwrap(),
-            ))
+    // Unfinished polygon, with its last edge following the cursor
+    pub fn draw(&self, window: &mut Window) {
+        if !self.is_closed && !self.points.is_empty() {
+            let mut points = self.points.clone();
+            points.push(self.cursor);
+            draw_polyline(window, &points, false, &UNFINISHED_SELECTION_LINE_COLOR);
+        }
+    }
+
+    pub fn build(&self) -> Option<PolygonSelection> {
+        if self.is_closed {
+            Some(PolygonSelection { points: self.points.clone() })
         } else {
             None
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn draw_polyline(window: &mut Window, points: &[Point], is_closed: bool, color: &Point3<f32>) {
+    for segment in points.windows(2) {
+        window.draw_planar_line(&segment[0], &segment[1], color);
+    }
+    if is_closed && points.len() > 2 {
+        window.draw_planar_line(&points[points.len() - 1], &points[0], color);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::framebuffer::Point;
+use crate::rasterization::RasterStep;
+
+// The eight pixels symmetric to (x, y) around the center
+fn octants((cx, cy): (i32, i32), x: i32, y: i32, intensity: f32) -> Vec<(i32, i32, f32)> {
+    vec![
+        (cx + x, cy + y, intensity), (cx - x, cy + y, intensity), (cx + x, cy - y, intensity), (cx - x, cy - y, intensity),
+        (cx + y, cy + x, intensity), (cx - y, cy + x, intensity), (cx + y, cy - x, intensity), (cx - y, cy - x, intensity),
+    ]
+}
+
+// The four pixels symmetric to (x, y) around the center
+fn quadrants((cx, cy): (i32, i32), x: i32, y: i32) -> Vec<(i32, i32, f32)> {
+    vec![(cx + x, cy + y, 1.0), (cx - x, cy + y, 1.0), (cx + x, cy - y, 1.0), (cx - x, cy - y, 1.0)]
+}
+
+// One octant from the top, x always grows, y drops when the midpoint between
+// the two candidates is outside the circle (d >= 0)
+pub fn midpoint_circle(center: (i32, i32), radius: i32) -> Vec<RasterStep> {
+    let (mut x, mut y) = (0, radius);
+    let mut d = 1 - radius;
+
+    let mut steps = vec![];
+    while x <= y {
+        steps.push(RasterStep {
+            pixels: octants(center, x, y, 1.0),
+            decision: format!("x = {}, y = {}, d = {}", x, y, d),
+        });
+
+        if d < 0 {
+            d += 2 * x + 3;
+        } else {
+            d += 2 * (x - y) + 5;
+            y -= 1;
+        }
+        x += 1;
+    }
+
+    steps
+}
+
+// Wu's circle: the exact y of every column of an octant, split between the two pixels around it.
+// The octant goes one column past the diagonal, otherwise the pixel next to it is never lit.
+pub fn wu_circle(center: (i32, i32), radius: f32) -> Vec<RasterStep> {
+    let mut steps = vec![];
+    let mut x = 0;
+
+    while x as f32 <= (radius / std::f32::consts::SQRT_2).ceil() {
+        let y = (radius * radius - (x * x) as f32).sqrt();
+        let (floor, fraction) = (y.floor() as i32, y - y.floor());
+
+        let mut pixels = octants(center, x, floor, 1.0 - fraction);
+        pixels.extend(octants(center, x, floor + 1, fraction));
+        steps.push(RasterStep {
+            pixels,
+            decision: format!("y = {:.2}, intensities {:.2} / {:.2}", y, 1.0 - fraction, fraction),
+        });
+        x += 1;
+    }
+
+    steps
+}
+
+// Midpoint ellipse of integer radii: x steps while the slope is flatter than -1 (region 1),
+// then y steps (region 2); the switch happens when 2b²x reaches 2a²y
+pub fn midpoint_ellipse(center: (i32, i32), a: i32, b: i32) -> Vec<RasterStep> {
+    let (a2, b2) = ((a * a) as f64, (b * b) as f64);
+    let (mut x, mut y) = (0, b);
+    let mut steps = vec![];
+
+    if b == 0 {
+        return (-a..=a)
+            .map(|x| RasterStep { pixels: vec![(center.0 + x, center.1, 1.0)], decision: "flat".to_string() })
+            .collect();
+    }
+
+    let (mut dx, mut dy) = (0.0, 2.0 * a2 * y as f64);
+    let mut d = b2 - a2 * b as f64 + 0.25 * a2;
+    while dx < dy {
+        steps.push(RasterStep {
+            pixels: quadrants(center, x, y),
+            decision: format!("region 1, d = {:.2}", d),
+        });
+
+        x += 1;
+        dx += 2.0 * b2;
+        if d < 0.0 {
+            d += dx + b2;
+        } else {
+            y -= 1;
+            dy -= 2.0 * a2;
+            d += dx - dy + b2;
+        }
+    }
+
+    let mut d = b2 * (x as f64 + 0.5).powi(2) + a2 * ((y - 1) as f64).powi(2) - a2 * b2;
+    while y >= 0 {
+        steps.push(RasterStep {
+            pixels: quadrants(center, x, y),
+            decision: format!("region 2, d = {:.2}", d),
+        });
+
+        y -= 1;
+        dy -= 2.0 * a2;
+        if d > 0.0 {
+            d += a2 - dy;
+        } else {
+            x += 1;
+            dx += 2.0 * b2;
+            d += dx - dy + a2;
+        }
+    }
+
+    steps
+}
+
+#[derive(Clone, Copy)]
+pub struct Ellipse {
+    pub center: Point,
+    pub radii: (f32, f32),
+    // Angle of the first axis, counter-clockwise from x
+    pub rotation: f32,
+}
+
+impl Ellipse {
+    // Coordinates along the ellipse axes
+    fn local(&self, point: &Point) -> (f32, f32) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (x, y) = (point.x - self.center.x, point.y - self.center.y);
+        (x * cos + y * sin, -x * sin + y * cos)
+    }
+
+    // Zero on the ellipse, negative inside
+    fn implicit(&self, point: &Point) -> f32 {
+        let (u, v) = self.local(point);
+        (u / self.radii.0).powi(2) + (v / self.radii.1).powi(2) - 1.0
+    }
+
+    // First order distance to the ellipse, exact on it and good a pixel or two away
+    pub fn distance(&self, point: &Point) -> f32 {
+        let (u, v) = self.local(point);
+        let gradient = (2.0 * u / self.radii.0.powi(2)).hypot(2.0 * v / self.radii.1.powi(2));
+        self.implicit(point).abs() / gradient.max(f32::EPSILON)
+    }
+
+    pub fn point(&self, angle: f32) -> Point {
+        let (u, v) = (self.radii.0 * angle.cos(), self.radii.1 * angle.sin());
+        let (sin, cos) = self.rotation.sin_cos();
+        Point::new(self.center.x + u * cos - v * sin, self.center.y + u * sin + v * cos)
+    }
+
+    // Pixel rows the ellipse spans, with one pixel more on every side
+    fn bounding_box(&self) -> ((i32, i32), (i32, i32)) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (a, b) = self.radii;
+        let half_width = (a * a * cos * cos + b * b * sin * sin).sqrt();
+        let half_height = (a * a * sin * sin + b * b * cos * cos).sqrt();
+
+        (
+            ((self.center.x - half_width).floor() as i32 - 1, (self.center.x + half_width).ceil() as i32 + 1),
+            ((self.center.y - half_height).floor() as i32 - 1, (self.center.y + half_height).ceil() as i32 + 1),
+        )
+    }
+
+    fn rows(&self, pixel: impl Fn(&Point) -> Option<f32>) -> Vec<RasterStep> {
+        let ((x_min, x_max), (y_min, y_max)) = self.bounding_box();
+
+        (y_min..=y_max)
+            .map(|y| {
+                let pixels: Vec<(i32, i32, f32)> = (x_min..=x_max)
+                    .filter_map(|x| pixel(&Point::new(x as f32, y as f32)).map(|intensity| (x, y, intensity)))
+                    .collect();
+                RasterStep { decision: format!("row {}: {} pixels", y, pixels.len()), pixels }
+            })
+            .filter(|step| !step.pixels.is_empty())
+            .collect()
+    }
+}
+
+// Any ellipse, rotated too: wherever the ellipse passes between two 4-neighbours,
+// which the sign of the implicit function tells, the one nearer to it is set
+pub fn implicit_ellipse(ellipse: &Ellipse) -> Vec<RasterStep> {
+    let inside = |point: &Point| ellipse.implicit(point) <= 0.0;
+    let neighbours = [(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)];
+
+    ellipse.rows(|point| {
+        let is_boundary = neighbours.iter().any(|(dx, dy)| {
+            let neighbour = Point::new(point.x + dx, point.y + dy);
+            inside(point) != inside(&neighbour) && ellipse.distance(point) <= ellipse.distance(&neighbour)
+        });
+        is_boundary.then_some(1.0)
+    })
+}
+
+// Any ellipse, rotated too: intensity falls linearly with the distance of the pixel center to the ellipse
+pub fn antialiased_ellipse(ellipse: &Ellipse) -> Vec<RasterStep> {
+    ellipse.rows(|point| {
+        let intensity = 1.0 - ellipse.distance(point);
+        (intensity > 0.0).then_some(intensity)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const RADII: std::ops::RangeInclusive<i32> = 0..=15;
+    const ROTATION_STEP_DEGREES: usize = 7;
+
+    fn pixels(steps: &[RasterStep], min_intensity: f32) -> HashSet<(i32, i32)> {
+        steps
+            .iter()
+            .flat_map(|step| step.pixels.iter())
+            .filter(|(_, _, intensity)| *intensity >= min_intensity)
+            .map(|(x, y, _)| (*x, *y))
+            .collect()
+    }
+
+    fn neighbours((x, y): (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+        (-1..=1).flat_map(move |dx| (-1..=1).map(move |dy| (x + dx, y + dy))).filter(move |p| *p != (x, y))
+    }
+
+    // Every pixel reachable from any other through 8-neighbours
+    fn assert_no_gaps(pixels: &HashSet<(i32, i32)>, context: &str) {
+        let start = *pixels.iter().next().unwrap_or_else(|| panic!("{}: no pixels", context));
+        let mut reached = HashSet::from([start]);
+        let mut queue = vec![start];
+        while let Some(pixel) = queue.pop() {
+            for neighbour in neighbours(pixel).filter(|p| pixels.contains(p)) {
+                if reached.insert(neighbour) {
+                    queue.push(neighbour);
+                }
+            }
+        }
+        assert_eq!(reached.len(), pixels.len(), "{}: gap in the outline", context);
+    }
+
+    fn assert_near(ellipse: &Ellipse, pixels: &HashSet<(i32, i32)>, context: &str) {
+        for (x, y) in pixels {
+            let distance = ellipse.distance(&Point::new(*x as f32, *y as f32));
+            assert!(distance <= 0.5, "{}: ({}, {}) is {} px away", context, x, y, distance);
+        }
+    }
+
+    // Distance to the closest point of the ellipse, found on a fine grid of angles
+    // and refined by a ternary search around it; unlike Ellipse::distance it holds at the thin tips
+    fn exact_distance(ellipse: &Ellipse, point: &Point) -> f32 {
+        let samples = 1440;
+        let step = std::f32::consts::TAU / samples as f32;
+        let distance = |angle: f32| (ellipse.point(angle) - point).norm();
+
+        let closest = (0..samples)
+            .map(|i| i as f32 * step)
+            .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+            .unwrap();
+        let (mut low, mut high) = (closest - step, closest + step);
+        for _ in 0..40 {
+            let (first, second) = (low + (high - low) / 3.0, high - (high - low) / 3.0);
+            if distance(first) < distance(second) {
+                high = second;
+            } else {
+                low = first;
+            }
+        }
+
+        distance((low + high) / 2.0).min(distance(closest))
+    }
+
+    fn ellipses() -> impl Iterator<Item = Ellipse> {
+        RADII.filter(|a| *a > 0).flat_map(|a| {
+            RADII.filter(|b| *b > 0).flat_map(move |b| {
+                (0..180).step_by(ROTATION_STEP_DEGREES).map(move |degrees| Ellipse {
+                    center: Point::new(0.0, 0.0),
+                    radii: (a as f32, b as f32),
+                    rotation: (degrees as f32).to_radians(),
+                })
+            })
+        })
+    }
+
+    fn describe(ellipse: &Ellipse) -> String {
+        format!("radii {:?}, rotation {:.0}°", ellipse.radii, ellipse.rotation.to_degrees())
+    }
+
+    #[test]
+    fn midpoint_circle_is_near_the_circle_without_gaps() {
+        for radius in RADII {
+            let context = format!("radius {}", radius);
+            let pixels = pixels(&midpoint_circle((3, -2), radius), 1.0);
+            let circle = Ellipse { center: Point::new(3.0, -2.0), radii: (radius as f32, radius as f32), rotation: 0.0 };
+
+            if radius > 0 {
+                assert_near(&circle, &pixels, &context);
+            }
+            assert_no_gaps(&pixels, &context);
+        }
+    }
+
+    // With a = 1 region 1 still takes its first step in x, to a pixel beside the tip that the
+    // ellipse only reaches much lower; from b = 8 on it is more than half a pixel away
+    fn midpoint_ellipse_bound(a: i32, b: i32) -> f32 {
+        if a == 1 && b >= 8 { 0.65 } else { 0.5 }
+    }
+
+    #[test]
+    fn midpoint_ellipse_is_near_the_ellipse_without_gaps() {
+        for a in RADII {
+            for b in RADII {
+                let context = format!("radii ({}, {})", a, b);
+                let ellipse = Ellipse { center: Point::new(3.0, -2.0), radii: (a as f32, b as f32), rotation: 0.0 };
+                let pixels = pixels(&midpoint_ellipse((3, -2), a, b), 1.0);
+
+                for (x, y) in &pixels {
+                    let distance = exact_distance(&ellipse, &Point::new(*x as f32, *y as f32));
+                    assert!(distance <= midpoint_ellipse_bound(a, b), "{}: ({}, {}) is {} px away", context, x, y, distance);
+                }
+                assert_no_gaps(&pixels, &context);
+            }
+        }
+    }
+
+    #[test]
+    fn implicit_ellipse_is_near_the_ellipse_without_gaps() {
+        for ellipse in ellipses() {
+            let pixels = pixels(&implicit_ellipse(&ellipse), 1.0);
+
+            assert_near(&ellipse, &pixels, &describe(&ellipse));
+            assert_no_gaps(&pixels, &describe(&ellipse));
+        }
+    }
+
+    // The pixels at least half lit make an outline of their own
+    #[test]
+    fn antialiased_ellipse_is_near_the_ellipse_without_gaps() {
+        for ellipse in ellipses() {
+            let pixels = pixels(&antialiased_ellipse(&ellipse), 0.5);
+
+            assert_near(&ellipse, &pixels, &describe(&ellipse));
+            assert_no_gaps(&pixels, &describe(&ellipse));
+        }
+    }
+
+    #[test]
+    fn wu_circle_is_near_the_circle_without_gaps() {
+        for radius in RADII.filter(|radius| *radius > 0) {
+            let context = format!("radius {}", radius);
+            let circle = Ellipse { center: Point::new(3.0, -2.0), radii: (radius as f32, radius as f32), rotation: 0.0 };
+            let pixels = pixels(&wu_circle((3, -2), radius as f32), 0.5);
+
+            assert_near(&circle, &pixels, &context);
+            assert_no_gaps(&pixels, &context);
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use crate::framebuffer::Point;
+use crate::rasterization::{LineAlgorithm, RasterStep};
+
+// Filled pixels are drawn at full intensity over the lighter polygon boundary
+pub const FILL_INTENSITY: f32 = 1.0;
+pub const BOUNDARY_INTENSITY: f32 = 0.4;
+
+// Pixel centers closer than this to an edge count as on it, whatever the rounding
+const EDGE_EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FillRule {
+    EvenOdd,
+    NonZero,
+}
+
+impl FillRule {
+    fn is_inside(&self, winding: i32, crossings: usize) -> bool {
+        match self {
+            FillRule::EvenOdd => crossings % 2 == 1,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+// Non-horizontal polygon edge over the pixel rows it crosses
+struct Edge {
+    y_start: i32,
+    y_end: i32,
+    x: f64,
+    inverse_slope: f64,
+    // 1 going up, -1 going down, for the non-zero rule
+    direction: i32,
+}
+
+fn edges(polygon: &[Point]) -> Vec<Edge> {
+    (0..polygon.len())
+        .filter_map(|i| {
+            let (a, b) = (polygon[i].cast::<f64>(), polygon[(i + 1) % polygon.len()].cast::<f64>());
+            let (low, high, direction) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+            let (y_start, y_end) = (low.y.ceil() as i32, high.y.ceil() as i32);
+            if y_start >= y_end {
+                return None;
+            }
+
+            let inverse_slope = (high.x - low.x) / (high.y - low.y);
+            Some(Edge {
+                y_start,
+                y_end,
+                x: low.x + (y_start as f64 - low.y) * inverse_slope,
+                inverse_slope,
+                direction,
+            })
+        })
+        .collect()
+}
+
+// Scan-line fill with an edge table bucketed by the first row of every edge and an active edge
+// table moved from row to row by the inverse slopes. Spans start at the edges where the rule turns
+// inside and end before the ones where it turns outside.
+pub fn scan_line(polygon: &[Point], rule: FillRule) -> Vec<RasterStep> {
+    let mut edge_table = edges(polygon);
+    edge_table.sort_by_key(|edge| edge.y_start);
+    let (Some(y_min), Some(y_max)) = (edge_table.first().map(|e| e.y_start), edge_table.iter().map(|e| e.y_end).max()) else {
+        return vec![];
+    };
+
+    let mut edge_table = edge_table.into_iter().peekable();
+    let mut active: Vec<Edge> = vec![];
+    let mut steps = vec![];
+
+    for y in y_min..y_max {
+        while let Some(edge) = edge_table.next_if(|edge| edge.y_start == y) {
+            active.push(edge);
+        }
+        active.retain(|edge| edge.y_end > y);
+        active.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+        let mut pixels = vec![];
+        let mut spans = vec![];
+        let (mut winding, mut crossings) = (0, 0);
+        for pair in active.windows(2) {
+            winding += pair[0].direction;
+            crossings += 1;
+            if !rule.is_inside(winding, crossings) {
+                continue;
+            }
+
+            let (first, last) = ((pair[0].x - EDGE_EPSILON).ceil() as i32, (pair[1].x - EDGE_EPSILON).ceil() as i32 - 1);
+            if first <= last {
+                pixels.extend((first..=last).map(|x| (x, y, FILL_INTENSITY)));
+                spans.push(format!("{}..={}", first, last));
+            }
+        }
+
+        let aet: Vec<String> = active
+            .iter()
+            .map(|edge| format!("{:.2}{}", edge.x, if edge.direction > 0 { "+" } else { "-" }))
+            .collect();
+        steps.push(RasterStep {
+            pixels,
+            decision: format!("y = {}, AET {}, spans {}", y, aet.join(" "), spans.join(" ")),
+        });
+
+        for edge in active.iter_mut() {
+            edge.x += edge.inverse_slope;
+        }
+    }
+
+    steps
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+impl Connectivity {
+    fn neighbours(&self) -> &'static [(i32, i32)] {
+        match self {
+            Connectivity::Four => &[(1, 0), (0, 1), (-1, 0), (0, -1)],
+            Connectivity::Eight => &[(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)],
+        }
+    }
+}
+
+// The polygon outline drawn with Bresenham between its rounded vertices, closed
+pub fn boundary(polygon: &[Point]) -> Vec<(i32, i32)> {
+    (0..polygon.len())
+        .flat_map(|i| LineAlgorithm::Bresenham.rasterize(polygon[i], polygon[(i + 1) % polygon.len()]))
+        .flat_map(|step| step.pixels)
+        .map(|(x, y, _)| (x, y))
+        .collect()
+}
+
+// Seed fill over the framebuffer with an explicit stack instead of recursion.
+// The first step draws the boundary; after it, one step per filled pixel.
+fn seed_fill(
+    boundary: &[(i32, i32)],
+    seed: (i32, i32),
+    (width, height): (usize, usize),
+    connectivity: Connectivity,
+    can_fill: impl Fn(f32) -> bool,
+) -> Vec<RasterStep> {
+    let mut intensities = vec![0.0; width * height];
+    let index = |(x, y): (i32, i32)| {
+        (x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height).then(|| y as usize * width + x as usize)
+    };
+
+    for pixel in boundary {
+        if let Some(i) = index(*pixel) {
+            intensities[i] = BOUNDARY_INTENSITY;
+        }
+    }
+    let mut steps = vec![RasterStep {
+        pixels: boundary.iter().map(|(x, y)| (*x, *y, BOUNDARY_INTENSITY)).collect(),
+        decision: format!("boundary, {} pixels", boundary.len()),
+    }];
+
+    let mut stack = vec![seed];
+    while let Some(pixel) = stack.pop() {
+        let Some(i) = index(pixel) else { continue };
+        if !can_fill(intensities[i]) {
+            continue;
+        }
+
+        intensities[i] = FILL_INTENSITY;
+        stack.extend(connectivity.neighbours().iter().map(|(dx, dy)| (pixel.0 + dx, pixel.1 + dy)));
+        steps.push(RasterStep {
+            pixels: vec![(pixel.0, pixel.1, FILL_INTENSITY)],
+            decision: format!("stack {}", stack.len()),
+        });
+    }
+
+    steps
+}
+
+// Replaces the connected region of the seed's own intensity, which may be the boundary itself
+pub fn flood_fill(boundary: &[(i32, i32)], seed: (i32, i32), size: (usize, usize), connectivity: Connectivity) -> Vec<RasterStep> {
+    let target = if boundary.contains(&seed) { BOUNDARY_INTENSITY } else { 0.0 };
+    seed_fill(boundary, seed, size, connectivity, |intensity| intensity == target)
+}
+
+// Fills everything connected to the seed up to the boundary intensity
+pub fn boundary_fill(boundary: &[(i32, i32)], seed: (i32, i32), size: (usize, usize)) -> Vec<RasterStep> {
+    seed_fill(boundary, seed, size, Connectivity::Four, |intensity| intensity != BOUNDARY_INTENSITY && intensity != FILL_INTENSITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const SIZE: (usize, usize) = (24, 24);
+
+    fn polygon(points: &[(f32, f32)]) -> Vec<Point> {
+        points.iter().map(|(x, y)| Point::new(*x, *y)).collect()
+    }
+
+    // The default polygon of the lab, its inner pentagon winds twice
+    fn star() -> Vec<Point> {
+        polygon(&[(2.0, 2.0), (12.0, 12.0), (20.0, 3.0), (1.0, 9.0), (21.0, 10.0)])
+    }
+
+    fn diamond() -> Vec<Point> {
+        polygon(&[(11.0, 2.0), (20.0, 11.0), (11.0, 20.0), (2.0, 11.0)])
+    }
+
+    // Pixel centers with an edge crossing their row at or left of them, straight from the vertices;
+    // an edge covers the rows from its lower end up to, but not including, its upper end
+    fn analytic_contains(polygon: &[Point], rule: FillRule, (x, y): (i32, i32)) -> bool {
+        let (x, y) = (x as f64, y as f64);
+        let (mut winding, mut crossings) = (0, 0);
+
+        for i in 0..polygon.len() {
+            let (a, b) = (polygon[i].cast::<f64>(), polygon[(i + 1) % polygon.len()].cast::<f64>());
+            let (low, high, direction) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+            if low.y <= y && y < high.y && low.x + (y - low.y) * (high.x - low.x) / (high.y - low.y) <= x + EDGE_EPSILON {
+                winding += direction;
+                crossings += 1;
+            }
+        }
+
+        rule.is_inside(winding, crossings)
+    }
+
+    fn filled(steps: &[RasterStep]) -> HashSet<(i32, i32)> {
+        steps.iter().flat_map(|step| step.pixels.iter()).map(|(x, y, _)| (*x, *y)).collect()
+    }
+
+    // The first step of a seed fill draws the boundary
+    fn seed_filled(steps: &[RasterStep]) -> HashSet<(i32, i32)> {
+        filled(&steps[1..])
+    }
+
+    fn assert_scan_line_matches_analytic(polygon: &[Point]) {
+        let (x_min, x_max) = polygon.iter().fold((i32::MAX, i32::MIN), |(min, max), p| (min.min(p.x.floor() as i32), max.max(p.x.ceil() as i32)));
+        let (y_min, y_max) = polygon.iter().fold((i32::MAX, i32::MIN), |(min, max), p| (min.min(p.y.floor() as i32), max.max(p.y.ceil() as i32)));
+
+        for rule in [FillRule::EvenOdd, FillRule::NonZero] {
+            let steps = scan_line(polygon, rule);
+            let filled = filled(&steps);
+            assert_eq!(filled.len(), steps.iter().map(|step| step.pixels.len()).sum::<usize>(), "{:?} fills a pixel twice", rule);
+
+            for y in y_min - 1..=y_max + 1 {
+                for x in x_min - 1..=x_max + 1 {
+                    assert_eq!(
+                        filled.contains(&(x, y)),
+                        analytic_contains(polygon, rule, (x, y)),
+                        "{:?} at ({}, {}) of {:?}",
+                        rule, x, y, polygon
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scan_line_matches_the_analytic_test() {
+        let polygons = [
+            star(),
+            diamond(),
+            // horizontal edges, vertices on pixel rows and columns
+            polygon(&[(2.0, 2.0), (8.0, 2.0), (8.0, 6.0), (2.0, 6.0)]),
+            polygon(&[(1.0, 1.0), (15.0, 1.0), (15.0, 9.0), (10.0, 9.0), (10.0, 4.0), (6.0, 4.0), (6.0, 9.0), (1.0, 9.0)]),
+            polygon(&[(3.0, 3.0), (12.0, 3.0), (7.0, 3.0), (7.0, 10.0)]),
+            // vertices between pixel centers
+            polygon(&[(1.5, 1.5), (14.25, 2.75), (9.5, 12.5), (4.75, 8.0)]),
+            // spikes touching a row in a vertex, and going both ways
+            polygon(&[(1.0, 5.0), (5.0, 1.0), (9.0, 5.0), (13.0, 1.0), (17.0, 5.0), (9.0, 9.0)]),
+            polygon(&[(17.0, 5.0), (13.0, 1.0), (9.0, 5.0), (5.0, 1.0), (1.0, 5.0), (9.0, 9.0)]),
+        ];
+
+        for polygon in &polygons {
+            assert_scan_line_matches_analytic(polygon);
+        }
+    }
+
+    #[test]
+    fn even_odd_and_non_zero_differ_on_the_star() {
+        let even_odd = filled(&scan_line(&star(), FillRule::EvenOdd));
+        let non_zero = filled(&scan_line(&star(), FillRule::NonZero));
+
+        assert!(even_odd.is_subset(&non_zero));
+        let center: Vec<&(i32, i32)> = non_zero.difference(&even_odd).collect();
+        assert!(!center.is_empty());
+        assert!(center.iter().all(|pixel| analytic_contains(&star(), FillRule::NonZero, **pixel)));
+        assert!(non_zero.contains(&(11, 8)) && !even_odd.contains(&(11, 8)));
+    }
+
+    #[test]
+    fn rectangle_fill_leaves_out_the_right_and_top_edges() {
+        let filled = filled(&scan_line(&polygon(&[(2.0, 2.0), (8.0, 2.0), (8.0, 6.0), (2.0, 6.0)]), FillRule::EvenOdd));
+        let expected: HashSet<(i32, i32)> = (2..6).flat_map(|y| (2..8).map(move |x| (x, y))).collect();
+
+        assert_eq!(filled, expected);
+    }
+
+    #[test]
+    fn four_connected_flood_fill_stays_inside_diagonal_edges() {
+        let boundary = boundary(&diamond());
+        let boundary_set: HashSet<(i32, i32)> = boundary.iter().copied().collect();
+        let seed = (11, 11);
+
+        let four = seed_filled(&flood_fill(&boundary, seed, SIZE, Connectivity::Four));
+        assert!(four.contains(&seed));
+        assert!(four.is_disjoint(&boundary_set));
+        for pixel in &four {
+            assert!(analytic_contains(&diamond(), FillRule::EvenOdd, *pixel), "{:?} leaked", pixel);
+        }
+
+        // The diagonal Bresenham edges let the 8-connected fill slip out to the corners
+        let eight = seed_filled(&flood_fill(&boundary, seed, SIZE, Connectivity::Eight));
+        assert!(four.is_subset(&eight));
+        assert!(eight.contains(&(0, 0)) && eight.contains(&(23, 23)));
+        assert!(eight.is_disjoint(&boundary_set));
+    }
+
+    #[test]
+    fn boundary_fill_stops_at_the_boundary() {
+        let boundary = boundary(&diamond());
+        let four = seed_filled(&flood_fill(&boundary, (11, 11), SIZE, Connectivity::Four));
+
+        assert_eq!(seed_filled(&boundary_fill(&boundary, (11, 11), SIZE)), four);
+        // From any other inside seed as well
+        assert_eq!(seed_filled(&boundary_fill(&boundary, (8, 13), SIZE)), four);
+    }
+
+    #[test]
+    fn seed_on_the_boundary() {
+        let boundary = boundary(&diamond());
+        let boundary_set: HashSet<(i32, i32)> = boundary.iter().copied().collect();
+        let seed = (11, 2);
+        assert!(boundary_set.contains(&seed));
+
+        // Flood fill recolors the boundary pixels connected to the seed
+        assert_eq!(seed_filled(&flood_fill(&boundary, seed, SIZE, Connectivity::Eight)), boundary_set);
+        let four = seed_filled(&flood_fill(&boundary, seed, SIZE, Connectivity::Four));
+        assert!(four.contains(&seed) && four.is_subset(&boundary_set) && four.len() < boundary_set.len());
+
+        // Boundary fill has nothing to fill from there
+        assert_eq!(boundary_fill(&boundary, seed, SIZE).len(), 1);
+    }
+
+    #[test]
+    fn seed_outside_the_polygon() {
+        let boundary = boundary(&diamond());
+        let inside = seed_filled(&flood_fill(&boundary, (11, 11), SIZE, Connectivity::Four));
+
+        for steps in [flood_fill(&boundary, (0, 0), SIZE, Connectivity::Four), boundary_fill(&boundary, (0, 0), SIZE)] {
+            let outside = seed_filled(&steps);
+            assert!(outside.contains(&(0, 0)) && outside.contains(&(23, 23)) && outside.contains(&(0, 23)));
+            assert!(outside.is_disjoint(&inside));
+            assert!(outside.iter().all(|pixel| !boundary.contains(pixel)));
+            // Everything but the boundary and the inside
+            assert_eq!(outside.len() + inside.len() + boundary.iter().collect::<HashSet<_>>().len(), SIZE.0 * SIZE.1);
+        }
+
+        // Outside the framebuffer nothing is filled
+        assert_eq!(flood_fill(&boundary, (-1, 5), SIZE, Connectivity::Four).len(), 1);
+        assert_eq!(boundary_fill(&boundary, (5, 30), SIZE).len(), 1);
+    }
+}
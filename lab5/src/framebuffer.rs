@@ -0,0 +1,109 @@
+use kiss3d::window::Window;
+use nalgebra::{Point2, Point3};
+
+use lazy_static::lazy_static;
+
+use crate::scene::scene;
+use lab_common::scene_file::color;
+
+lazy_static! {
+    static ref GRID_COLOR: Point3<f32> = color(scene().colors.grid);
+}
+
+// Pixel centers of the framebuffer are at integer coordinates
+pub type Point = Point2<f32>;
+
+// Low-resolution image the algorithms draw into, one intensity per pixel
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    intensities: Vec<f32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, intensities: vec![0.0; width * height] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Pixels outside are dropped; a pixel set twice keeps the brighter intensity
+    pub fn plot(&mut self, x: i32, y: i32, intensity: f32) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+
+        let pixel = &mut self.intensities[y as usize * self.width + x as usize];
+        *pixel = pixel.max(intensity.clamp(0.0, 1.0));
+    }
+
+    pub fn intensity(&self, x: usize, y: usize) -> f32 {
+        self.intensities[y * self.width + x]
+    }
+}
+
+// Where a framebuffer is shown: the screen position of its bottom-left corner and the size of its pixels
+pub struct PixelGrid {
+    origin: Point,
+    cell_size: f32,
+}
+
+impl PixelGrid {
+    pub fn new(origin: Point, cell_size: f32) -> Self {
+        Self { origin, cell_size }
+    }
+
+    pub fn to_screen(&self, point: &Point) -> Point {
+        Point::new(
+            self.origin.x + (point.x + 0.5) * self.cell_size,
+            self.origin.y + (point.y + 0.5) * self.cell_size,
+        )
+    }
+
+    pub fn to_framebuffer(&self, point: &Point) -> Point {
+        Point::new(
+            (point.x - self.origin.x) / self.cell_size - 0.5,
+            (point.y - self.origin.y) / self.cell_size - 0.5,
+        )
+    }
+
+    // Lit pixels are blended from white to the color by their intensity,
+    // each filled with one horizontal line per screen pixel row
+    pub fn draw(&self, window: &mut Window, framebuffer: &Framebuffer, color: &Point3<f32>) {
+        let white = Point3::new(1.0, 1.0, 1.0);
+
+        for y in 0..framebuffer.height() {
+            for x in 0..framebuffer.width() {
+                let intensity = framebuffer.intensity(x, y);
+                if intensity <= 0.0 {
+                    continue;
+                }
+
+                let pixel_color = Point3::from(white.coords.lerp(&color.coords, intensity));
+                let left = self.origin.x + x as f32 * self.cell_size;
+                let bottom = self.origin.y + y as f32 * self.cell_size;
+                let mut row = bottom + 0.5;
+                while row < bottom + self.cell_size {
+                    window.draw_planar_line(&Point::new(left, row), &Point::new(left + self.cell_size, row), &pixel_color);
+                    row += 1.0;
+                }
+            }
+        }
+
+        let (width, height) = (framebuffer.width() as f32 * self.cell_size, framebuffer.height() as f32 * self.cell_size);
+        for x in 0..=framebuffer.width() {
+            let x = self.origin.x + x as f32 * self.cell_size;
+            window.draw_planar_line(&Point::new(x, self.origin.y), &Point::new(x, self.origin.y + height), &GRID_COLOR);
+        }
+        for y in 0..=framebuffer.height() {
+            let y = self.origin.y + y as f32 * self.cell_size;
+            window.draw_planar_line(&Point::new(self.origin.x, y), &Point::new(self.origin.x + width, y), &GRID_COLOR);
+        }
+    }
+}
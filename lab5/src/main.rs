@@ -0,0 +1,557 @@
+use kiss3d::event::{WindowEvent, MouseButton, Action};
+use kiss3d::nalgebra as na;
+use kiss3d::light::Light;
+use kiss3d::window::Window;
+use kiss3d::camera::{FixedView};
+use kiss3d::conrod::{self, Sizeable, Colorable};
+use na::{Point3};
+
+use conrod::{
+    widget_ids,
+    UiCell,
+    widget, Widget,
+    Positionable, Borderable, Labelable
+};
+
+use lazy_static::lazy_static;
+
+use lab_common::coordinate_converter::CoordinateConverter;
+use lab_common::history::{Command, History};
+use scene::{scene, Handles, Scene};
+use lab_common::scene_file::{self, color, SAVE_SCENE_KEY};
+use lab_common::screenshot::Screenshots;
+use framebuffer::*;
+use rasterization::*;
+use shapes::*;
+
+mod conics;
+mod fill;
+mod framebuffer;
+mod rasterization;
+mod scene;
+mod shapes;
+
+// Space for the settings above the grids and for the steps below them
+const UI_HEIGHT: f32 = 140.0;
+const STEPS_HEIGHT: f32 = 220.0;
+const PANEL_MARGIN: f32 = 20.0;
+
+const ENDPOINT_RADIUS: f32 = 8.0;
+
+// Steps listed under the grids, all of them are printed on demand
+const MAX_SHOWN_STEPS: usize = 12;
+
+// Edits listed right of the steps
+const HISTORY_LINES: usize = 12;
+
+lazy_static! {
+    static ref PIXEL_COLOR: Point3<f32> = color(scene().colors.pixel);
+    static ref IDEAL_SHAPE_COLOR: Point3<f32> = color(scene().colors.ideal_shape);
+    static ref ENDPOINT_COLOR: Point3<f32> = color(scene().colors.endpoint);
+    static ref CURRENT_STEP_COLOR: Point3<f32> = color(scene().colors.current_step);
+}
+
+struct DragAndDrop {
+    is_hovering: bool,
+    mouse_pressed: bool,
+    control_point_index: Option<u32>,
+}
+
+impl DragAndDrop {
+    pub fn new() -> Self {
+        DragAndDrop {
+            is_hovering: false,
+            mouse_pressed: false,
+            control_point_index: None
+        }
+    }
+
+    pub fn set_hovering(&mut self, point_index: Option<u32>) {
+        if self.is_hovering && self.mouse_pressed {return}
+
+        if let Some(point_index) = point_index {
+            self.is_hovering = true;
+            self.control_point_index = Some(point_index);
+        } else {
+            self.is_hovering = false;
+            self.control_point_index = None;
+        }
+    }
+
+    pub fn set_mouse_pressed(&mut self, mouse_pressed: bool) {
+        self.mouse_pressed = mouse_pressed;
+    }
+
+    pub fn is_dragging(&self) -> Option<u32> {
+        if self.mouse_pressed {
+            self.control_point_index
+        } else {
+            None
+        }
+    }
+}
+
+struct RasterSettings {
+    cell_size: u32,
+    shape: ShapeKind,
+    // Handles of every shape, kept while another one is shown
+    handles: Vec<Vec<Point>>,
+    // Algorithm whose steps are listed, one of the shape's
+    steps_algorithm: Algorithm,
+    print_steps: bool,
+    // Steps drawn in every grid, all of them once it reaches the longest run
+    shown_steps: usize,
+    // One more step is shown every frame
+    animate: bool,
+    clear_polygon: bool,
+}
+
+// Handles moved, a polygon vertex added or the polygon cleared, or another shape or pixel size
+enum Edit {
+    Handles { shape: ShapeKind, label: &'static str, from: Vec<Point>, to: Vec<Point> },
+    Settings { from: (ShapeKind, u32), to: (ShapeKind, u32) },
+}
+
+impl Edit {
+    fn set_settings(settings: &mut RasterSettings, (shape, cell_size): (ShapeKind, u32)) {
+        if settings.shape != shape {
+            settings.shape = shape;
+            settings.steps_algorithm = shape.algorithms()[0];
+        }
+        settings.cell_size = cell_size;
+    }
+}
+
+impl Command for Edit {
+    type Target = RasterSettings;
+
+    fn apply(&self, settings: &mut RasterSettings) {
+        match self {
+            Edit::Handles { shape, to, .. } => settings.handles[*shape as usize] = to.clone(),
+            Edit::Settings { to, .. } => Edit::set_settings(settings, *to),
+        }
+    }
+
+    fn revert(&self, settings: &mut RasterSettings) {
+        match self {
+            Edit::Handles { shape, from, .. } => settings.handles[*shape as usize] = from.clone(),
+            Edit::Settings { from, .. } => Edit::set_settings(settings, *from),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Edit::Handles { shape, label, .. } => format!("{}: {}", shape.label(), label),
+            Edit::Settings { from, to } if from.0 != to.0 => format!("Shape {}", to.0.label()),
+            Edit::Settings { to, .. } => format!("Pixel size {}", to.1),
+        }
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        match (self, next) {
+            (Edit::Handles { shape, label, to, .. }, Edit::Handles { shape: next_shape, label: next_label, to: next_to, .. })
+                if shape == next_shape && label == next_label =>
+            {
+                *to = next_to.clone();
+                true
+            },
+            (Edit::Settings { from, to }, Edit::Settings { to: next_to, .. }) if from.0 == to.0 && to.0 == next_to.0 => {
+                *to = *next_to;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+widget_ids! {
+    pub struct Ids {
+        canvas,
+        cell_size_dialer,
+        shape_list,
+        steps_list,
+        print_button,
+        progress_slider,
+        animate_toggle,
+        clear_button,
+        titles[],
+        steps_text,
+        history_text,
+    }
+}
+
+fn proceed_ui(ui_cell: &mut UiCell, ids: &Ids, settings: &mut RasterSettings, max_steps: usize) {
+    let margin = 20.0;
+    let widget_w = 160.0;
+    let widget_h = 30.0;
+    let [min_cell_size, max_cell_size] = scene().ui.cell_size_range;
+
+    widget::Canvas::new()
+        .w_h(4.0 * widget_w + 5.0 * margin, 2.0 * widget_h + 3.0 * margin)
+        .rgb(1.0, 1.0, 1.0)
+        .border_rgb(1.0, 1.0, 1.0)
+        .top_left()
+        .set(ids.canvas, ui_cell);
+
+    if let Some(value) = widget::NumberDialer::new(settings.cell_size as f32, min_cell_size, max_cell_size, 0)
+        .w_h(widget_w, widget_h)
+        .top_left_with_margin_on(ids.canvas, margin)
+        .label("Pixel size")
+        .set(ids.cell_size_dialer, ui_cell)
+    {
+        settings.cell_size = value as u32;
+    }
+
+    let labels: Vec<&str> = ShapeKind::ALL.iter().map(|s| s.label()).collect();
+    let selected = ShapeKind::ALL.iter().position(|s| *s == settings.shape);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(widget_w, widget_h)
+        .right_from(ids.cell_size_dialer, margin)
+        .set(ids.shape_list, ui_cell)
+    {
+        settings.shape = ShapeKind::ALL[index];
+        settings.steps_algorithm = settings.shape.algorithms()[0];
+    }
+
+    let algorithms = settings.shape.algorithms();
+    let geometry = settings.shape.geometry(&settings.handles[settings.shape as usize]);
+    let labels: Vec<&str> = algorithms.iter().map(|a| a.title(&geometry)).collect();
+    let selected = algorithms.iter().position(|a| *a == settings.steps_algorithm);
+
+    if let Some(index) = widget::DropDownList::new(&labels, selected)
+        .w_h(widget_w, widget_h)
+        .right_from(ids.shape_list, margin)
+        .set(ids.steps_list, ui_cell)
+    {
+        settings.steps_algorithm = algorithms[index];
+    }
+
+    if widget::Button::new()
+        .w_h(widget_w, widget_h)
+        .right_from(ids.steps_list, margin)
+        .label("Print steps")
+        .set(ids.print_button, ui_cell)
+        .was_clicked()
+    {
+        settings.print_steps = true;
+    }
+
+    let shown_steps = settings.shown_steps.min(max_steps);
+    if let Some(value) = widget::Slider::new(shown_steps as f32, 0.0, max_steps as f32)
+        .w_h(2.0 * widget_w + margin, widget_h)
+        .down_from(ids.cell_size_dialer, margin)
+        .label(&format!("Steps shown: {} / {}", shown_steps, max_steps))
+        .set(ids.progress_slider, ui_cell)
+    {
+        settings.shown_steps = value.round() as usize;
+        settings.animate = false;
+    }
+
+    if let Some(value) = widget::Toggle::new(settings.animate)
+        .w_h(widget_w, widget_h)
+        .right_from(ids.progress_slider, margin)
+        .label("Animate")
+        .set(ids.animate_toggle, ui_cell)
+        .last()
+    {
+        settings.animate = value;
+        if value {
+            settings.shown_steps = 0;
+        }
+    }
+
+    if settings.shape == ShapeKind::Polygon && widget::Button::new()
+        .w_h(widget_w, widget_h)
+        .right_from(ids.animate_toggle, margin)
+        .label("Clear polygon")
+        .set(ids.clear_button, ui_cell)
+        .was_clicked()
+    {
+        settings.clear_polygon = true;
+    }
+}
+
+// Steps from the given one on, numbered from the first
+fn steps_table(title: &str, steps: &[RasterStep], skip: usize, limit: usize) -> String {
+    let mut lines: Vec<String> = steps
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .take(limit)
+        .map(|(i, step)| {
+            let pixels: Vec<String> = step.pixels
+                .iter()
+                .map(|(x, y, intensity)| format!("({}, {}) {:.2}", x, y, intensity))
+                .collect();
+            format!("{:>3}: {:<28} {}", i, pixels.join(" "), step.decision)
+        })
+        .collect();
+    if steps.len() > skip + limit {
+        lines.push(format!("... {} more steps", steps.len() - skip - limit));
+    }
+
+    format!("{}, {} steps\n{}", title, steps.len(), lines.join("\n"))
+}
+
+fn draw_circle(window: &mut Window, center: &Point, radius: f32, color: &Point3<f32>) {
+    let segments = 16;
+    for i in 0..segments {
+        let angle = |i: usize| std::f32::consts::TAU * i as f32 / segments as f32;
+        let point = |i: usize| Point::new(center.x + radius * angle(i).cos(), center.y + radius * angle(i).sin());
+        window.draw_planar_line(&point(i), &point(i + 1), color);
+    }
+}
+
+fn main() {
+    // Screenshots and frame recording
+    let mut screenshots = Screenshots::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    // Scene
+    let (loaded_scene, scene_path) = scene_file::load_from_args::<Scene>().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    scene::install(loaded_scene);
+
+    // Window
+    let mut window = Window::new("Kiss3d: rasterization");
+    window.set_line_width(2.0);
+    window.set_light(Light::StickToCamera);
+    window.set_background_color(1.0, 1.0, 1.0);
+
+    // Camera
+    let mut camera = FixedView::new();
+
+    // State
+    let mut cursor = Point::new(0.0, 0.0);
+    let mut settings = RasterSettings {
+        cell_size: scene().cell_size,
+        shape: scene().shape,
+        handles: ShapeKind::ALL.iter().map(|s| scene().handles.of(*s)).collect(),
+        steps_algorithm: scene().shape.algorithms()[0],
+        print_steps: false,
+        shown_steps: usize::MAX,
+        animate: false,
+        clear_polygon: false,
+    };
+
+    // Drag and drop helper
+    let mut dd = DragAndDrop::new();
+    let mut history = History::new();
+
+    // UI
+    let mut ids = Ids::new(window.conrod_ui_mut().widget_id_generator());
+    let max_panels_count = ShapeKind::ALL.iter().map(|s| s.algorithms().len()).max().unwrap_or(0);
+    ids.titles.resize(max_panels_count, &mut window.conrod_ui_mut().widget_id_generator());
+
+    while window.render_with_camera(&mut camera) {
+        screenshots.capture(&window);
+
+        let window_width = window.width() as f32;
+        let window_height = window.height() as f32;
+
+        // Coordinate system helper
+        let cc = CoordinateConverter::new(window.width(), window.height());
+
+        // One grid per algorithm of the shape, side by side; the UI may change the shape for the next frame
+        let shape = settings.shape;
+        let algorithms = shape.algorithms();
+        let panels_count = algorithms.len() as f32;
+        let panel_w = (window_width - (panels_count + 1.0) * PANEL_MARGIN) / panels_count;
+        let panel_h = window_height - UI_HEIGHT - STEPS_HEIGHT - 2.0 * PANEL_MARGIN;
+        let cell_size = settings.cell_size as f32;
+        let framebuffer_width = (panel_w / cell_size).floor().max(1.0) as usize;
+        let framebuffer_height = (panel_h / cell_size).floor().max(1.0) as usize;
+
+        let grids: Vec<PixelGrid> = (0..algorithms.len())
+            .map(|i| PixelGrid::new(
+                Point::new(
+                    -window_width / 2.0 + PANEL_MARGIN + i as f32 * (panel_w + PANEL_MARGIN),
+                    -window_height / 2.0 + STEPS_HEIGHT + PANEL_MARGIN,
+                ),
+                cell_size,
+            ))
+            .collect();
+        let panel_at = |point: &Point| ((point.x + window_width / 2.0 - PANEL_MARGIN) / (panel_w + PANEL_MARGIN))
+            .floor()
+            .clamp(0.0, panels_count - 1.0) as usize;
+
+        if settings.clear_polygon {
+            let polygon = &mut settings.handles[ShapeKind::Polygon as usize];
+            let from = polygon.clone();
+            polygon.truncate(1);
+            history.record(Edit::Handles { shape: ShapeKind::Polygon, label: "clear", from, to: polygon.clone() });
+            settings.clear_polygon = false;
+        }
+
+        // Line ends and polygon vertices stay on the grid when it shrinks,
+        // circles and ellipses keep their center on it
+        let handles = &mut settings.handles[shape as usize];
+        let clamp = |p: &Point| Point::new(
+            p.x.clamp(0.0, framebuffer_width as f32 - 1.0),
+            p.y.clamp(0.0, framebuffer_height as f32 - 1.0),
+        );
+        if matches!(shape, ShapeKind::Line | ShapeKind::Polygon) {
+            handles.iter_mut().for_each(|handle| *handle = clamp(handle));
+        } else {
+            let shift = clamp(&handles[0]) - handles[0];
+            handles.iter_mut().for_each(|handle| *handle += shift);
+        }
+
+        let geometry = shape.geometry(handles);
+        let all_steps: Vec<Vec<RasterStep>> = algorithms
+            .iter()
+            .map(|algorithm| algorithm.rasterize(&geometry, (framebuffer_width, framebuffer_height)))
+            .collect();
+        let summaries: Vec<String> = algorithms
+            .iter()
+            .zip(all_steps.iter())
+            .map(|(algorithm, steps)| algorithm.summary(&geometry, steps))
+            .collect();
+
+        let max_steps = all_steps.iter().map(|steps| steps.len()).max().unwrap_or(0);
+        if settings.animate {
+            settings.shown_steps += 1;
+            settings.animate = settings.shown_steps < max_steps;
+        }
+        let is_in_progress = settings.shown_steps < max_steps;
+
+        let outline = geometry.points(1.0);
+        for ((grid, steps), algorithm) in grids.iter().zip(all_steps.iter()).zip(algorithms.iter()) {
+            let shown = settings.shown_steps.min(steps.len());
+            let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+            for (x, y, intensity) in steps[..shown].iter().flat_map(|step| step.pixels.iter()) {
+                framebuffer.plot(*x, *y, *intensity);
+            }
+            grid.draw(&mut window, &framebuffer, &PIXEL_COLOR);
+
+            // The pixels of the last shown step stand out while the steps are walked through
+            if is_in_progress && shown > 0 {
+                let mut current = Framebuffer::new(framebuffer_width, framebuffer_height);
+                for (x, y, intensity) in &steps[shown - 1].pixels {
+                    current.plot(*x, *y, *intensity);
+                }
+                grid.draw(&mut window, &current, &CURRENT_STEP_COLOR);
+            }
+
+            for segment in outline.windows(2) {
+                window.draw_planar_line(&grid.to_screen(&segment[0]), &grid.to_screen(&segment[1]), &IDEAL_SHAPE_COLOR);
+            }
+            // Radii of circles and ellipses are shown from the center to their handles
+            for handle in handles.iter() {
+                if matches!(shape, ShapeKind::Circle | ShapeKind::Ellipse) {
+                    window.draw_planar_line(&grid.to_screen(&handles[0]), &grid.to_screen(handle), &ENDPOINT_COLOR);
+                }
+                draw_circle(&mut window, &grid.to_screen(handle), ENDPOINT_RADIUS, &ENDPOINT_COLOR);
+            }
+
+            if settings.print_steps && *algorithm == settings.steps_algorithm {
+                println!("{}", steps_table(algorithm.title(&geometry), steps, 0, steps.len()));
+            }
+        }
+        settings.print_steps = false;
+
+        let settings_before_ui = (settings.shape, settings.cell_size);
+        let mut ui_cell = window.conrod_ui_mut().set_widgets();
+        proceed_ui(&mut ui_cell, &ids, &mut settings, max_steps);
+        if (settings.shape, settings.cell_size) != settings_before_ui {
+            history.record(Edit::Settings { from: settings_before_ui, to: (settings.shape, settings.cell_size) });
+        }
+
+        for (((grid, algorithm), summary), id) in grids.iter().zip(algorithms.iter()).zip(summaries.iter()).zip(ids.titles.iter()) {
+            let top_center = grid.to_screen(&Point::new(framebuffer_width as f32 / 2.0 - 0.5, framebuffer_height as f32));
+            widget::Text::new(&format!("{}\n{}", algorithm.title(&geometry), summary))
+                .font_size(12)
+                .center_justify()
+                .x_y(top_center.x as f64, top_center.y as f64 + 8.0)
+                .set(*id, &mut ui_cell);
+        }
+
+        // While in progress the table follows the last shown step
+        let shown_index = algorithms.iter().position(|a| *a == settings.steps_algorithm).unwrap_or(0);
+        let skip = if is_in_progress { settings.shown_steps.saturating_sub(MAX_SHOWN_STEPS) } else { 0 };
+        widget::Text::new(&steps_table(algorithms[shown_index].title(&geometry), &all_steps[shown_index], skip, MAX_SHOWN_STEPS))
+            .font_size(11)
+            .bottom_left_with_margin(PANEL_MARGIN as f64)
+            .set(ids.steps_text, &mut ui_cell);
+        widget::Text::new(&history.text(HISTORY_LINES))
+            .font_size(11)
+            .bottom_right_with_margin(PANEL_MARGIN as f64)
+            .set(ids.history_text, &mut ui_cell);
+        drop(ui_cell);
+
+        // Any grid moves the handles of all of them
+        let handles = &mut settings.handles[shape as usize];
+        dd.set_hovering(None);
+        for grid in &grids {
+            for (index, handle) in handles.iter().enumerate() {
+                if (grid.to_screen(handle) - cursor).norm() <= ENDPOINT_RADIUS {
+                    dd.set_hovering(Some(index as u32));
+                }
+            }
+        }
+
+        if let Some(index) = dd.is_dragging() {
+            let from = handles.clone();
+            shape.move_handle(handles, index as usize, grids[panel_at(&cursor)].to_framebuffer(&cursor));
+            if *handles != from {
+                history.record(Edit::Handles { shape, label: "move", from, to: handles.clone() });
+            }
+        }
+
+        let is_ui_capturing_mouse = window.is_conrod_ui_capturing_mouse();
+        let mut save_scene = false;
+        let mut history_action = None;
+
+        for event in window.events().iter() {
+            screenshots.handle_event(&event.value);
+            history_action = history.handle_event(&event.value).or(history_action);
+            match event.value {
+                WindowEvent::CursorPos(x, y, _modif) => {
+                    cursor = Point::new(
+                        cc.x_top_left_to_centered_p(x as f32),
+                        cc.y_top_left_to_centered_p(y as f32)
+                    );
+                },
+                WindowEvent::MouseButton(MouseButton::Button1, action, _) => {
+                    let is_pressed = action == Action::Press && !is_ui_capturing_mouse;
+                    dd.set_mouse_pressed(is_pressed);
+
+                    // A click on a grid, away from the handles, adds a polygon vertex there
+                    if is_pressed && shape == ShapeKind::Polygon && !dd.is_hovering {
+                        let vertex = grids[panel_at(&cursor)].to_framebuffer(&cursor);
+                        let (x, y) = (vertex.x.round(), vertex.y.round());
+                        if (0.0..framebuffer_width as f32).contains(&x) && (0.0..framebuffer_height as f32).contains(&y) {
+                            let from = handles.clone();
+                            handles.push(Point::new(x, y));
+                            history.record(Edit::Handles { shape, label: "add vertex", from, to: handles.clone() });
+                        }
+                    }
+                },
+                WindowEvent::Key(key, Action::Press, _) => save_scene |= key == SAVE_SCENE_KEY,
+                _ => {}
+            }
+        }
+
+        // Once the events are through, the handles no longer borrowed
+        if let Some(action) = history_action {
+            history.perform(action, &mut settings);
+        }
+        if save_scene {
+            let current_scene = Scene {
+                shape,
+                cell_size: settings.cell_size,
+                handles: Handles::from_shapes(&settings.handles),
+                ..scene().clone()
+            };
+            match scene_file::save(&scene_path, &current_scene) {
+                Ok(()) => println!("Saved {}", scene_path.display()),
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+    }
+}
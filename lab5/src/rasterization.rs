@@ -0,0 +1,283 @@
+use crate::framebuffer::Point;
+
+// One iteration of an algorithm: the pixels it set, with their intensities,
+// and the state it decided them with
+pub struct RasterStep {
+    pub pixels: Vec<(i32, i32, f32)>,
+    pub decision: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineAlgorithm {
+    Dda,
+    Bresenham,
+    Wu,
+}
+
+impl LineAlgorithm {
+    pub const ALL: [LineAlgorithm; 3] = [LineAlgorithm::Dda, LineAlgorithm::Bresenham, LineAlgorithm::Wu];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineAlgorithm::Dda => "DDA",
+            LineAlgorithm::Bresenham => "Bresenham",
+            LineAlgorithm::Wu => "Xiaolin Wu",
+        }
+    }
+
+    // DDA and Bresenham start and end at the nearest pixel centers, Wu keeps the exact ends
+    pub fn rasterize(&self, start: Point, end: Point) -> Vec<RasterStep> {
+        let round = |p: Point| (p.x.round() as i32, p.y.round() as i32);
+
+        match self {
+            LineAlgorithm::Dda => dda(round(start), round(end)),
+            LineAlgorithm::Bresenham => bresenham(round(start), round(end)),
+            LineAlgorithm::Wu => wu(start, end),
+        }
+    }
+}
+
+// Equal steps along the longer axis, the other coordinate is accumulated in floating point and rounded
+fn dda((x0, y0): (i32, i32), (x1, y1): (i32, i32)) -> Vec<RasterStep> {
+    let (dx, dy) = ((x1 - x0) as f32, (y1 - y0) as f32);
+    let steps = dx.abs().max(dy.abs()) as i32;
+    let (x_increment, y_increment) = if steps == 0 { (0.0, 0.0) } else { (dx / steps as f32, dy / steps as f32) };
+
+    let (mut x, mut y) = (x0 as f32, y0 as f32);
+    (0..=steps)
+        .map(|_| {
+            let step = RasterStep {
+                pixels: vec![(x.round() as i32, y.round() as i32, 1.0)],
+                decision: format!("x = {:.2}, y = {:.2}", x, y),
+            };
+            x += x_increment;
+            y += y_increment;
+            step
+        })
+        .collect()
+}
+
+// Integer error of the next pixel center from the ideal line, for all the octants:
+// a step along x is taken when 2e >= dy, along y when 2e <= dx, both for a diagonal step
+fn bresenham((mut x, mut y): (i32, i32), (x1, y1): (i32, i32)) -> Vec<RasterStep> {
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut error = dx + dy;
+
+    let mut steps = vec![];
+    loop {
+        let doubled = 2 * error;
+        let (step_x, step_y) = (doubled >= dy, doubled <= dx);
+        let is_last = x == x1 && y == y1;
+        let direction = match (is_last, step_x, step_y) {
+            (true, _, _) => "end",
+            (_, true, true) => "diagonal",
+            (_, true, false) => "x",
+            _ => "y",
+        };
+        steps.push(RasterStep {
+            pixels: vec![(x, y, 1.0)],
+            decision: format!("e = {}, 2e = {}, step {}", error, doubled, direction),
+        });
+
+        if is_last {
+            break;
+        }
+        if step_x {
+            error += dy;
+            x += sx;
+        }
+        if step_y {
+            error += dx;
+            y += sy;
+        }
+    }
+
+    steps
+}
+
+// Two pixels across the line at every step along the longer axis,
+// with intensities split by the distance of the line from their centers
+fn wu(start: Point, end: Point) -> Vec<RasterStep> {
+    let is_steep = (end.y - start.y).abs() > (end.x - start.x).abs();
+    // Computed as if the line were flat, pixels are swapped back when stored
+    let (mut start, mut end) = if is_steep {
+        (Point::new(start.y, start.x), Point::new(end.y, end.x))
+    } else {
+        (start, end)
+    };
+    if start.x > end.x {
+        std::mem::swap(&mut start, &mut end);
+    }
+
+    let dx = end.x - start.x;
+    let gradient = if dx.abs() <= f32::EPSILON { 1.0 } else { (end.y - start.y) / dx };
+    let pixel = |x: i32, y: i32, intensity: f32| if is_steep { (y, x, intensity) } else { (x, y, intensity) };
+    let fraction = |v: f32| v - v.floor();
+
+    // Ends are weighted by how much of their pixel column the line covers
+    let end_point = |point: Point| {
+        let x = point.x.round();
+        (x as i32, point.y + gradient * (x - point.x))
+    };
+    let (x_start, y_start) = end_point(start);
+    let (x_end, y_end) = end_point(end);
+    let (start_coverage, end_coverage) = (1.0 - fraction(start.x + 0.5), fraction(end.x + 0.5));
+
+    let mut steps = vec![];
+    let mut push_step = |x: i32, y: f32, coverage: f32| {
+        let (low, high) = (1.0 - fraction(y), fraction(y));
+        steps.push(RasterStep {
+            pixels: vec![
+                pixel(x, y.floor() as i32, low * coverage),
+                pixel(x, y.floor() as i32 + 1, high * coverage),
+            ],
+            decision: format!("y = {:.2}, intensities {:.2} / {:.2}", y, low * coverage, high * coverage),
+        });
+    };
+
+    push_step(x_start, y_start, start_coverage);
+    if x_end == x_start {
+        return steps;
+    }
+    let mut y = y_start + gradient;
+    for x in (x_start + 1)..x_end {
+        push_step(x, y, 1.0);
+        y += gradient;
+    }
+    push_step(x_end, y_end, end_coverage);
+
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: (i32, i32) = (3, -2);
+
+    // Ends all around the start, through every octant and on their borders, and the start itself
+    fn ends() -> impl Iterator<Item = (i32, i32)> {
+        (-9..=9).flat_map(|dx| (-9..=9).map(move |dy| (START.0 + dx, START.1 + dy)))
+    }
+
+    fn distance_to_line(start: Point, end: Point, (x, y): (i32, i32)) -> f32 {
+        let (direction, offset) = (end - start, Point::new(x as f32, y as f32) - start);
+        if direction.norm() <= f32::EPSILON {
+            offset.norm()
+        } else {
+            direction.perp(&offset).abs() / direction.norm()
+        }
+    }
+
+    fn point((x, y): (i32, i32)) -> Point {
+        Point::new(x as f32, y as f32)
+    }
+
+    // The coordinate along the longer axis, and along the shorter one
+    fn major_minor(start: (i32, i32), end: (i32, i32), (x, y): (i32, i32)) -> (i32, i32) {
+        if (end.1 - start.1).abs() > (end.0 - start.0).abs() { (y, x) } else { (x, y) }
+    }
+
+    fn assert_one_pixel_per_step(algorithm: LineAlgorithm) {
+        for end in ends() {
+            let context = format!("{} from {:?} to {:?}", algorithm.label(), START, end);
+            let steps = algorithm.rasterize(point(START), point(end));
+            let pixels: Vec<(i32, i32)> = steps
+                .iter()
+                .map(|step| {
+                    assert_eq!(step.pixels.len(), 1, "{}", context);
+                    (step.pixels[0].0, step.pixels[0].1)
+                })
+                .collect();
+
+            assert_eq!(pixels.first(), Some(&START), "{}", context);
+            assert_eq!(pixels.last(), Some(&end), "{}", context);
+
+            let (major_start, _) = major_minor(START, end, START);
+            let (major_end, _) = major_minor(START, end, end);
+            let direction = (major_end - major_start).signum();
+            assert_eq!(pixels.len() as i32, (major_end - major_start).abs() + 1, "{}", context);
+            for (i, pixel) in pixels.iter().enumerate() {
+                assert_eq!(major_minor(START, end, *pixel).0, major_start + direction * i as i32, "{}", context);
+
+                let distance = distance_to_line(point(START), point(end), *pixel);
+                assert!(distance <= 0.5, "{}: {:?} is {} px away", context, pixel, distance);
+            }
+        }
+    }
+
+    #[test]
+    fn dda_steps_once_per_pixel_along_the_line_in_every_octant() {
+        assert_one_pixel_per_step(LineAlgorithm::Dda);
+    }
+
+    #[test]
+    fn bresenham_steps_once_per_pixel_along_the_line_in_every_octant() {
+        assert_one_pixel_per_step(LineAlgorithm::Bresenham);
+    }
+
+    #[test]
+    fn dda_and_bresenham_round_the_ends_to_pixel_centers() {
+        for algorithm in [LineAlgorithm::Dda, LineAlgorithm::Bresenham] {
+            let steps = algorithm.rasterize(Point::new(0.4, -0.6), Point::new(4.6, 1.2));
+            let pixels: Vec<(i32, i32)> = steps.iter().map(|step| (step.pixels[0].0, step.pixels[0].1)).collect();
+
+            assert_eq!(pixels.first(), Some(&(0, -1)), "{}", algorithm.label());
+            assert_eq!(pixels.last(), Some(&(5, 1)), "{}", algorithm.label());
+        }
+    }
+
+    // Every column along the longer axis gets the two pixels around the line, the brighter one
+    // within half a pixel of it; inside the segment both add up to a fully lit pixel
+    fn assert_wu_line(start: Point, end: Point) {
+        let context = format!("Wu from {} to {}", start, end);
+        let steps = LineAlgorithm::Wu.rasterize(start, end);
+        let is_steep = (end.y - start.y).abs() > (end.x - start.x).abs();
+        let major = |(x, y, _): (i32, i32, f32)| if is_steep { y } else { x };
+        let (major_start, major_end) = if is_steep { (start.y, end.y) } else { (start.x, end.x) };
+        let (first, last) = (major_start.min(major_end).round() as i32, major_start.max(major_end).round() as i32);
+
+        assert_eq!(steps.len() as i32, last - first + 1, "{}", context);
+        for (i, step) in steps.iter().enumerate() {
+            let [low, high] = [step.pixels[0], step.pixels[1]];
+            assert_eq!(major(low), first + i as i32, "{}", context);
+            assert_eq!(major(high), first + i as i32, "{}", context);
+
+            let brighter = if low.2 >= high.2 { low } else { high };
+            let distance = distance_to_line(start, end, (brighter.0, brighter.1));
+            assert!(distance <= 0.5, "{}: {:?} is {} px away", context, brighter, distance);
+
+            if i > 0 && i + 1 < steps.len() {
+                assert!((low.2 + high.2 - 1.0).abs() < 1e-5, "{}: column {} is not fully lit", context, i);
+            }
+        }
+    }
+
+    #[test]
+    fn wu_covers_every_column_around_the_line_in_every_octant() {
+        for end in ends() {
+            assert_wu_line(point(START), point(end));
+        }
+    }
+
+    #[test]
+    fn wu_keeps_fractional_ends() {
+        let fractions = [(0.0, 0.0), (0.3, -0.2), (-0.45, 0.4)];
+        for end in ends() {
+            for (dx, dy) in fractions {
+                assert_wu_line(Point::new(START.0 as f32 + 0.25, START.1 as f32 - 0.1), Point::new(end.0 as f32 + dx, end.1 as f32 + dy));
+            }
+        }
+    }
+
+    #[test]
+    fn zero_length_segments_are_a_single_step() {
+        for algorithm in LineAlgorithm::ALL {
+            let steps = algorithm.rasterize(point(START), point(START));
+            assert_eq!(steps.len(), 1, "{}", algorithm.label());
+            assert!(steps[0].pixels.iter().any(|(x, y, intensity)| (*x, *y) == START && *intensity > 0.0), "{}", algorithm.label());
+        }
+    }
+}
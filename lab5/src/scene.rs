@@ -0,0 +1,149 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::framebuffer::Point;
+use lab_common::scene_file::{Color, Errors, Validate};
+use crate::shapes::ShapeKind;
+
+static SCENE: OnceLock<Scene> = OnceLock::new();
+
+// The shape on the grids at startup, the handles of every shape, colors and the pixel size
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    pub shape: ShapeKind,
+    pub cell_size: u32,
+    pub handles: Handles,
+    pub colors: Colors,
+    pub ui: Ui,
+}
+
+// In framebuffer pixels, as described by `ShapeKind::default_handles`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Handles {
+    pub line: Vec<[f32; 2]>,
+    pub circle: Vec<[f32; 2]>,
+    pub ellipse: Vec<[f32; 2]>,
+    pub polygon: Vec<[f32; 2]>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub pixel: Color,
+    pub ideal_shape: Color,
+    pub endpoint: Color,
+    pub current_step: Color,
+    pub grid: Color,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ui {
+    pub cell_size_range: [f32; 2],
+}
+
+impl Handles {
+    pub fn of(&self, shape: ShapeKind) -> Vec<Point> {
+        let handles = match shape {
+            ShapeKind::Line => &self.line,
+            ShapeKind::Circle => &self.circle,
+            ShapeKind::Ellipse => &self.ellipse,
+            ShapeKind::Polygon => &self.polygon,
+        };
+        handles.iter().map(|handle| Point::from(*handle)).collect()
+    }
+
+    // From the handles of every shape, in the order of `ShapeKind::ALL`
+    pub fn from_shapes(shape_handles: &[Vec<Point>]) -> Self {
+        let handles = |shape: ShapeKind| shape_handles[shape as usize].iter().map(|handle| [handle.x, handle.y]).collect();
+        Handles {
+            line: handles(ShapeKind::Line),
+            circle: handles(ShapeKind::Circle),
+            ellipse: handles(ShapeKind::Ellipse),
+            polygon: handles(ShapeKind::Polygon),
+        }
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            shape: ShapeKind::Line,
+            cell_size: 16,
+            handles: Handles::default(),
+            colors: Colors::default(),
+            ui: Ui::default(),
+        }
+    }
+}
+
+impl Default for Handles {
+    fn default() -> Self {
+        let shape_handles: Vec<Vec<Point>> = ShapeKind::ALL.iter().map(|s| s.default_handles()).collect();
+        Handles::from_shapes(&shape_handles)
+    }
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            pixel: [0.8, 0.0, 0.0],
+            ideal_shape: [0.0, 0.3, 1.0],
+            endpoint: [0.0, 0.3, 1.0],
+            current_step: [1.0, 0.6, 0.0],
+            grid: [0.85, 0.85, 0.85],
+        }
+    }
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Ui { cell_size_range: [4.0, 40.0] }
+    }
+}
+
+impl Validate for Scene {
+    fn validate(&self, errors: &mut Errors) {
+        errors.range("ui.cell_size_range", &self.ui.cell_size_range);
+        let [min, max] = self.ui.cell_size_range;
+        errors.check("ui.cell_size_range", min >= 1.0, "pixels must be at least 1 screen pixel wide");
+        errors.check("cell_size", (min..=max).contains(&(self.cell_size as f32)), "must be within ui.cell_size_range");
+
+        for (key, handles, count) in [
+            ("handles.line", &self.handles.line, 2),
+            ("handles.circle", &self.handles.circle, 2),
+            ("handles.ellipse", &self.handles.ellipse, 3),
+        ] {
+            errors.check(key, handles.len() == count, &format!("{} handles are expected", count));
+        }
+        errors.check("handles.polygon", !self.handles.polygon.is_empty(), "the fill seed comes first, then the vertices");
+        for (shape, handles) in [
+            ("line", &self.handles.line),
+            ("circle", &self.handles.circle),
+            ("ellipse", &self.handles.ellipse),
+            ("polygon", &self.handles.polygon),
+        ] {
+            for (i, handle) in handles.iter().enumerate() {
+                errors.check(&format!("handles.{}[{}]", shape, i), handle.iter().all(|c| *c >= 0.0), "pixel coordinates can't be negative");
+            }
+        }
+
+        errors.color("colors.pixel", &self.colors.pixel);
+        errors.color("colors.ideal_shape", &self.colors.ideal_shape);
+        errors.color("colors.endpoint", &self.colors.endpoint);
+        errors.color("colors.current_step", &self.colors.current_step);
+        errors.color("colors.grid", &self.colors.grid);
+    }
+}
+
+// Set once at startup, before anything reads the scene
+pub fn install(scene: Scene) {
+    let _ = SCENE.set(scene);
+}
+
+pub fn scene() -> &'static Scene {
+    SCENE.get_or_init(Scene::default)
+}
@@ -0,0 +1,304 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::conics::{self, Ellipse};
+use crate::fill::{self, Connectivity, FillRule};
+use crate::framebuffer::Point;
+use crate::rasterization::{LineAlgorithm, RasterStep};
+
+// A shape in framebuffer coordinates, as it is given to the algorithms
+#[derive(Clone)]
+pub enum Geometry {
+    Segment(Point, Point),
+    Circle { center: Point, radius: f32 },
+    Ellipse(Ellipse),
+    // Closed, the seed is where the flood and boundary fills start
+    Polygon { vertices: Vec<Point>, seed: Point },
+}
+
+impl Geometry {
+    pub fn distance(&self, point: &Point) -> f32 {
+        match self {
+            Geometry::Segment(start, end) => {
+                let direction = end - start;
+                let length = direction.norm_squared();
+                let t = if length <= f32::EPSILON { 0.0 } else { ((point - start).dot(&direction) / length).clamp(0.0, 1.0) };
+                (point - (start + direction * t)).norm()
+            },
+            Geometry::Circle { center, radius } => ((point - center).norm() - radius).abs(),
+            Geometry::Ellipse(ellipse) => ellipse.distance(point),
+            Geometry::Polygon { vertices, .. } => (0..vertices.len())
+                .map(|i| Geometry::Segment(vertices[i], vertices[(i + 1) % vertices.len()]).distance(point))
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+
+    // Points along the shape, the given number per pixel of its length;
+    // closed shapes end with their first point
+    pub fn points(&self, per_pixel: f32) -> Vec<Point> {
+        let count = |length: f32| (length * per_pixel).ceil().max(1.0) as usize;
+
+        match self {
+            Geometry::Segment(start, end) => {
+                let count = count((end - start).norm());
+                (0..=count).map(|i| start + (end - start) * (i as f32 / count as f32)).collect()
+            },
+            Geometry::Circle { center, radius } => {
+                let ellipse = Ellipse { center: *center, radii: (*radius, *radius), rotation: 0.0 };
+                Geometry::Ellipse(ellipse).points(per_pixel)
+            },
+            Geometry::Ellipse(ellipse) => {
+                let count = count(std::f32::consts::TAU * ellipse.radii.0.max(ellipse.radii.1)).max(32);
+                (0..=count).map(|i| ellipse.point(std::f32::consts::TAU * i as f32 / count as f32)).collect()
+            },
+            Geometry::Polygon { vertices, .. } => (0..vertices.len())
+                .flat_map(|i| {
+                    let mut edge = Geometry::Segment(vertices[i], vertices[(i + 1) % vertices.len()]).points(per_pixel);
+                    edge.pop();
+                    edge
+                })
+                .chain(vertices.first().copied())
+                .collect(),
+        }
+    }
+
+    // Radii along x and y, when the ellipse axes are the coordinate axes
+    fn axis_aligned_radii(ellipse: &Ellipse) -> Option<(f32, f32)> {
+        let (sin, cos) = ellipse.rotation.sin_cos();
+        if sin.abs() <= 1e-4 {
+            Some(ellipse.radii)
+        } else if cos.abs() <= 1e-4 {
+            Some((ellipse.radii.1, ellipse.radii.0))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ShapeKind {
+    Line,
+    Circle,
+    Ellipse,
+    Polygon,
+}
+
+impl ShapeKind {
+    pub const ALL: [ShapeKind; 4] = [ShapeKind::Line, ShapeKind::Circle, ShapeKind::Ellipse, ShapeKind::Polygon];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShapeKind::Line => "Line",
+            ShapeKind::Circle => "Circle",
+            ShapeKind::Ellipse => "Ellipse",
+            ShapeKind::Polygon => "Polygon fill",
+        }
+    }
+
+    pub fn algorithms(&self) -> Vec<Algorithm> {
+        match self {
+            ShapeKind::Line => LineAlgorithm::ALL.iter().map(|a| Algorithm::Line(*a)).collect(),
+            ShapeKind::Circle => vec![Algorithm::MidpointCircle, Algorithm::WuCircle],
+            ShapeKind::Ellipse => vec![Algorithm::MidpointEllipse, Algorithm::AntialiasedEllipse],
+            ShapeKind::Polygon => vec![
+                Algorithm::ScanLine(FillRule::EvenOdd),
+                Algorithm::ScanLine(FillRule::NonZero),
+                Algorithm::FloodFill(Connectivity::Four),
+                Algorithm::FloodFill(Connectivity::Eight),
+                Algorithm::BoundaryFill,
+            ],
+        }
+    }
+
+    // Line: the endpoints. Circle: the center and a point on the circle.
+    // Ellipse: the center and the ends of its two axes. Polygon: the fill seed and the vertices,
+    // a self-intersecting star by default so that the fill rules differ.
+    pub fn default_handles(&self) -> Vec<Point> {
+        match self {
+            ShapeKind::Line => vec![Point::new(2.0, 3.0), Point::new(17.0, 11.0)],
+            ShapeKind::Circle => vec![Point::new(11.0, 7.0), Point::new(17.0, 7.0)],
+            ShapeKind::Ellipse => vec![Point::new(11.0, 7.0), Point::new(20.0, 7.0), Point::new(11.0, 12.0)],
+            ShapeKind::Polygon => vec![
+                Point::new(7.0, 7.0),
+                Point::new(2.0, 2.0), Point::new(12.0, 12.0), Point::new(20.0, 3.0), Point::new(1.0, 9.0), Point::new(21.0, 10.0),
+            ],
+        }
+    }
+
+    // Line ends move freely. Other handles snap to pixel centers; the center of a circle or an
+    // ellipse carries the other handles with it, and the second axis stays perpendicular to the first.
+    pub fn move_handle(&self, handles: &mut [Point], index: usize, position: Point) {
+        let snapped = Point::new(position.x.round(), position.y.round());
+
+        match (self, index) {
+            (ShapeKind::Line, _) => handles[index] = position,
+            (ShapeKind::Polygon, _) => handles[index] = snapped,
+            (_, 0) => {
+                let shift = snapped - handles[0];
+                handles.iter_mut().for_each(|handle| *handle += shift);
+            },
+            (ShapeKind::Circle, _) => handles[index] = snapped,
+            (ShapeKind::Ellipse, _) => {
+                let center = handles[0];
+                if index == 1 {
+                    if snapped == center {
+                        return;
+                    }
+                    let second_radius = (handles[2] - center).norm();
+                    handles[1] = snapped;
+                    handles[2] = center + Self::second_axis(handles) * second_radius;
+                } else {
+                    let axis = Self::second_axis(handles);
+                    handles[2] = center + axis * (snapped - center).dot(&axis);
+                }
+            },
+        }
+    }
+
+    fn second_axis(handles: &[Point]) -> nalgebra::Vector2<f32> {
+        let axis = (handles[1] - handles[0]).normalize();
+        nalgebra::Vector2::new(-axis.y, axis.x)
+    }
+
+    pub fn geometry(&self, handles: &[Point]) -> Geometry {
+        match self {
+            ShapeKind::Line => Geometry::Segment(handles[0], handles[1]),
+            ShapeKind::Circle => Geometry::Circle { center: handles[0], radius: (handles[1] - handles[0]).norm().round() },
+            ShapeKind::Ellipse => {
+                let first_axis = handles[1] - handles[0];
+                Geometry::Ellipse(Ellipse {
+                    center: handles[0],
+                    radii: (first_axis.norm().max(0.5), (handles[2] - handles[0]).norm().max(0.5)),
+                    rotation: first_axis.y.atan2(first_axis.x),
+                })
+            },
+            ShapeKind::Polygon => Geometry::Polygon { vertices: handles[1..].to_vec(), seed: handles[0] },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Line(LineAlgorithm),
+    MidpointCircle,
+    WuCircle,
+    // Falls back to the implicit boundary pixels for rotated ellipses, titled as such
+    MidpointEllipse,
+    AntialiasedEllipse,
+    ScanLine(FillRule),
+    FloodFill(Connectivity),
+    BoundaryFill,
+}
+
+impl Algorithm {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Algorithm::Line(algorithm) => algorithm.label(),
+            Algorithm::MidpointCircle => "Midpoint circle",
+            Algorithm::WuCircle => "Wu circle",
+            Algorithm::MidpointEllipse => "Midpoint ellipse",
+            Algorithm::AntialiasedEllipse => "Anti-aliased ellipse",
+            Algorithm::ScanLine(FillRule::EvenOdd) => "Scan-line, even-odd",
+            Algorithm::ScanLine(FillRule::NonZero) => "Scan-line, non-zero",
+            Algorithm::FloodFill(Connectivity::Four) => "Flood fill, 4-connected",
+            Algorithm::FloodFill(Connectivity::Eight) => "Flood fill, 8-connected",
+            Algorithm::BoundaryFill => "Boundary fill",
+        }
+    }
+
+    // The label of what really draws the shape
+    pub fn title(&self, geometry: &Geometry) -> &'static str {
+        match (self, self.drawn_geometry(geometry)) {
+            (Algorithm::MidpointEllipse, Geometry::Ellipse(ellipse)) if ellipse.rotation != 0.0 => "Implicit ellipse, rotated",
+            _ => self.label(),
+        }
+    }
+
+    // The shape the algorithm really draws: integer algorithms work with rounded ends, centers and radii
+    pub fn drawn_geometry(&self, geometry: &Geometry) -> Geometry {
+        let round = |p: &Point| Point::new(p.x.round(), p.y.round());
+
+        match (self, geometry) {
+            (Algorithm::Line(LineAlgorithm::Wu), _) | (Algorithm::AntialiasedEllipse, _) | (_, Geometry::Polygon { .. }) => geometry.clone(),
+            (_, Geometry::Segment(start, end)) => Geometry::Segment(round(start), round(end)),
+            (_, Geometry::Circle { center, radius }) => Geometry::Circle { center: round(center), radius: radius.round() },
+            (_, Geometry::Ellipse(ellipse)) => match Geometry::axis_aligned_radii(ellipse) {
+                Some((a, b)) => Geometry::Ellipse(Ellipse { center: round(&ellipse.center), radii: (a.round(), b.round()), rotation: 0.0 }),
+                None => geometry.clone(),
+            },
+        }
+    }
+
+    // Fills are bounded by the framebuffer size, the other algorithms draw anywhere
+    pub fn rasterize(&self, geometry: &Geometry, size: (usize, usize)) -> Vec<RasterStep> {
+        let pixel = |p: &Point| (p.x.round() as i32, p.y.round() as i32);
+
+        match (self, &self.drawn_geometry(geometry)) {
+            (Algorithm::Line(algorithm), Geometry::Segment(start, end)) => algorithm.rasterize(*start, *end),
+            (Algorithm::MidpointCircle, Geometry::Circle { center, radius }) => conics::midpoint_circle(pixel(center), *radius as i32),
+            (Algorithm::WuCircle, Geometry::Circle { center, radius }) => conics::wu_circle(pixel(center), *radius),
+            (Algorithm::MidpointEllipse, Geometry::Ellipse(ellipse)) if ellipse.rotation == 0.0 => {
+                conics::midpoint_ellipse(pixel(&ellipse.center), ellipse.radii.0 as i32, ellipse.radii.1 as i32)
+            },
+            (Algorithm::MidpointEllipse, Geometry::Ellipse(ellipse)) => conics::implicit_ellipse(ellipse),
+            (Algorithm::AntialiasedEllipse, Geometry::Ellipse(ellipse)) => conics::antialiased_ellipse(ellipse),
+            (Algorithm::ScanLine(rule), Geometry::Polygon { vertices, .. }) => fill::scan_line(vertices, *rule),
+            (Algorithm::FloodFill(connectivity), Geometry::Polygon { vertices, seed }) => {
+                fill::flood_fill(&fill::boundary(vertices), pixel(seed), size, *connectivity)
+            },
+            (Algorithm::BoundaryFill, Geometry::Polygon { vertices, seed }) => fill::boundary_fill(&fill::boundary(vertices), pixel(seed), size),
+            _ => vec![],
+        }
+    }
+
+    // One line on how the result compares with the analytic shape; the fills are checked by the tests
+    pub fn summary(&self, geometry: &Geometry, steps: &[RasterStep]) -> String {
+        match (self, geometry) {
+            (Algorithm::ScanLine(_), _) => format!("{} pixels filled", steps.iter().map(|step| step.pixels.len()).sum::<usize>()),
+            (Algorithm::FloodFill(_) | Algorithm::BoundaryFill, _) => format!("{} pixels filled", steps.len().saturating_sub(1)),
+            _ => {
+                let check = check(&self.drawn_geometry(geometry), steps);
+                format!("{:.2} px off at most, {} gaps", check.max_distance, check.gaps)
+            },
+        }
+    }
+}
+
+// How well the pixels follow the shape: the farthest pixel center from it and how many
+// points along it have no pixel nearby. Pixels of at least half intensity count.
+pub struct RasterCheck {
+    pub max_distance: f32,
+    pub gaps: usize,
+}
+
+pub fn check(geometry: &Geometry, steps: &[RasterStep]) -> RasterCheck {
+    let lit: HashSet<(i32, i32)> = steps
+        .iter()
+        .flat_map(|step| step.pixels.iter())
+        .filter(|(_, _, intensity)| *intensity >= 0.5)
+        .map(|(x, y, _)| (*x, *y))
+        .collect();
+
+    let max_distance = lit
+        .iter()
+        .map(|(x, y)| geometry.distance(&Point::new(*x as f32, *y as f32)))
+        .fold(0.0, f32::max);
+
+    // Segment ends are left out, Wu weakens the pixels there by their coverage
+    let is_inner = |point: &Point| match geometry {
+        Geometry::Segment(start, end) => (point - start).norm() >= 1.0 && (point - end).norm() >= 1.0,
+        _ => true,
+    };
+    let gaps = geometry
+        .points(4.0)
+        .iter()
+        .filter(|point| is_inner(point))
+        .filter(|point| {
+            let (x, y) = (point.x.round() as i32, point.y.round() as i32);
+            !(-1..=1).any(|dx| (-1..=1).any(|dy| lit.contains(&(x + dx, y + dy))))
+        })
+        .count();
+
+    RasterCheck { max_distance, gaps }
+}